@@ -0,0 +1,402 @@
+use cuuri_core::app_type::RawDatabaseChatEntry;
+use cuuri_core::chat_events::{ApprovalFuture, ChatEvents};
+use cuuri_core::chat_exchange::ChatRequest;
+use cuuri_core::chat_record::ChatStatus;
+use cuuri_core::config::Config;
+use cuuri_core::config_overrides::ENV_DB_PASSPHRASE;
+use cuuri_core::conversation_export::ExportFormat;
+use cuuri_core::db_pool::{DatabaseState, DbState};
+use cuuri_core::document_index::RetrievedChunk;
+use cuuri_core::get_config::get_config;
+use cuuri_core::get_config_path::get_config_path;
+use cuuri_core::open_chat_database::open_chat_database;
+use cuuri_core::stderr_log::{log_to_stderr, LevelFilter};
+use cuuri_core::tool_approvals::{ApprovalDecision, ToolApprovalRequest};
+use cuuri_core::tool_calls::{ToolCall, ToolCallResult};
+use cuuri_core::Cuuri;
+use std::env;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: cuuri-cli [--profile <name>] [--data-dir <path>] [--config <path>] <command>
+
+Commands:
+  send [--session <id>] [--model <name>] [--approve-tools] [<prompt>...]
+      Sends a prompt, read from standard input when none is given, and
+      streams the answer. Without --session a new session is started.
+  sessions [--json]
+      Lists the sessions, the most recently active first.
+  show <session-id> [--json]
+      Prints the questions and answers of a session.
+  export <session-id> [--format markdown|json|html] [--output <path>] [--embed-images]
+      Exports a session to a file, or to standard output without --output.
+";
+
+// Read by `command_line_args` for every build of Cuuri
+const GLOBAL_FLAGS: [&str; 4] = ["--config", "--data-dir", "--default-model", "--profile"];
+
+/// The command line, without the global flags.
+struct Args {
+    positional: Vec<String>,
+    flags: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    fn parse<I: Iterator<Item = String>>(mut args: I, value_flags: &[&str]) -> Self {
+        let mut parsed = Args {
+            positional: Vec::new(),
+            flags: Vec::new(),
+        };
+
+        while let Some(arg) = args.next() {
+            if arg == "--" {
+                parsed.positional.extend(args.by_ref());
+                break;
+            }
+            if !arg.starts_with("--") {
+                parsed.positional.push(arg);
+                continue;
+            }
+
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            let takes_value =
+                GLOBAL_FLAGS.contains(&flag.as_str()) || value_flags.contains(&flag.as_str());
+            let value = match inline_value {
+                Some(value) => Some(value),
+                None if takes_value => args.next(),
+                None => None,
+            };
+            if !GLOBAL_FLAGS.contains(&flag.as_str()) {
+                parsed.flags.push((flag, value));
+            }
+        }
+
+        parsed
+    }
+
+    fn has(&self, flag: &str) -> bool {
+        self.flags.iter().any(|(name, _)| name == flag)
+    }
+
+    fn value(&self, flag: &str) -> Result<Option<String>, String> {
+        match self.flags.iter().rev().find(|(name, _)| name == flag) {
+            Some((_, Some(value))) => Ok(Some(value.clone())),
+            Some((_, None)) => Err(format!("{} needs a value", flag)),
+            None => Ok(None),
+        }
+    }
+
+    /// Fails on flags the command does not know, which are likely typos.
+    fn check_flags(&self, known: &[&str]) -> Result<(), String> {
+        match self
+            .flags
+            .iter()
+            .find(|(name, _)| !known.contains(&name.as_str()))
+        {
+            Some((name, _)) => Err(format!("Unknown option `{}`\n\n{}", name, USAGE)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Prints the answer as it arrives. Tools that need approval only run with
+/// `--approve-tools`, since nobody may be there to answer a prompt.
+struct TerminalEvents {
+    approve_tools: bool,
+}
+
+impl ChatEvents for TerminalEvents {
+    fn token(&self, token: &str) {
+        let mut stdout = io::stdout().lock();
+        let _ = stdout.write_all(token.as_bytes());
+        let _ = stdout.flush();
+    }
+
+    fn context_sources(&self, sources: &[RetrievedChunk]) {
+        for (index, source) in sources.iter().enumerate() {
+            eprintln!(
+                "[{}] {} (lines {}-{})",
+                index + 1,
+                source.file_path,
+                source.start_line,
+                source.end_line
+            );
+        }
+    }
+
+    fn tool_call(&self, call: &ToolCall) {
+        eprintln!("\n[tool] {}({})", call.name, call.arguments);
+    }
+
+    fn tool_result(&self, result: &ToolCallResult) {
+        if result.is_error {
+            eprintln!("[tool] {}: {}", result.name, result.content);
+        }
+    }
+
+    fn request_approval(&self, _request: ToolApprovalRequest) -> ApprovalFuture {
+        let decision = if self.approve_tools {
+            ApprovalDecision::Approved
+        } else {
+            ApprovalDecision::Denied(Some(
+                "tools that need approval only run when cuuri-cli is given --approve-tools"
+                    .to_string(),
+            ))
+        };
+        Box::pin(async move { Some(decision) })
+    }
+}
+
+/// The config file of the active profile, or the defaults and overrides
+/// when there is none yet.
+fn load_config() -> Result<Config, String> {
+    let config_path = get_config_path().map_err(|e| e.to_string())?;
+    if config_path.exists() {
+        return get_config();
+    }
+    let mut config = Config::default();
+    config.apply_overrides();
+    Ok(config)
+}
+
+/// The config and database of the active profile. Unlike the app, a
+/// database that cannot be opened is an error.
+fn open_cuuri() -> Result<Cuuri, String> {
+    let config = load_config()?;
+    let db = match open_chat_database(&config, None) {
+        DatabaseState::Locked => {
+            return Err(format!(
+                "The chat database is encrypted. Set {} to its passphrase.",
+                ENV_DB_PASSPHRASE
+            ))
+        }
+        DatabaseState::Failed { message, .. } => return Err(message),
+        state => DbState::new(state),
+    };
+    Ok(Cuuri::new(config, db))
+}
+
+fn read_prompt(args: &Args) -> Result<String, String> {
+    let prompt = if args.positional.is_empty() || args.positional == ["-"] {
+        let mut prompt = String::new();
+        io::stdin()
+            .read_to_string(&mut prompt)
+            .map_err(|e| format!("Failed to read the prompt: {}", e))?;
+        prompt
+    } else {
+        args.positional.join(" ")
+    };
+
+    let prompt = prompt.trim().to_string();
+    if prompt.is_empty() {
+        return Err("The prompt is empty".to_string());
+    }
+    Ok(prompt)
+}
+
+async fn send(args: Args) -> Result<(), String> {
+    args.check_flags(&["--session", "--model", "--approve-tools"])?;
+    let cuuri = open_cuuri()?;
+    let config = cuuri.config();
+    if config.openai_api_key.is_empty() {
+        return Err(
+            "No OpenAI API key is set. Add openai_api_key to config.toml or set OPENAI_API_KEY."
+                .to_string(),
+        );
+    }
+    let model = args
+        .value("--model")?
+        .or_else(|| config.default_model.clone())
+        .ok_or("No model is set. Pass --model or set default_model in config.toml.")?;
+    let session_id = match args.value("--session")? {
+        Some(session_id) => session_id,
+        None => {
+            let session_id = uuid::Uuid::new_v4().to_string();
+            eprintln!("Session: {}", session_id);
+            session_id
+        }
+    };
+    let message = read_prompt(&args)?;
+
+    let events = TerminalEvents {
+        approve_tools: args.has("--approve-tools"),
+    };
+
+    let request = ChatRequest {
+        session_id,
+        message,
+        base64_images: None,
+        image_detail: None,
+        documents: None,
+        folder_ids: None,
+        model,
+        api_key: config.openai_api_key,
+    };
+    let result = cuuri.stream_message(request, &events).await;
+    println!();
+    result.map(|_| ())
+}
+
+async fn sessions(args: Args) -> Result<(), String> {
+    args.check_flags(&["--json"])?;
+    let sessions = open_cuuri()?.list_sessions().await?;
+
+    if args.has("--json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&sessions).map_err(|e| e.to_string())?
+        );
+        return Ok(());
+    }
+    for session in sessions {
+        let first_line = session.first_question.lines().next().unwrap_or("");
+        let title: String = first_line.chars().take(60).collect();
+        // Down to the minute
+        let last_active = session
+            .last_active
+            .get(..16)
+            .unwrap_or(&session.last_active);
+        println!(
+            "{}  {}  {:>3}  {}",
+            session.session_id, last_active, session.question_count, title
+        );
+    }
+    Ok(())
+}
+
+fn print_entry(entry: &RawDatabaseChatEntry) {
+    println!("## User ({})\n\n{}\n", entry.created_at, entry.question);
+    for attachment in &entry.attachments {
+        println!(
+            "[{}: {}]",
+            attachment.kind,
+            attachment
+                .file_name
+                .as_deref()
+                .unwrap_or(&attachment.mime_type)
+        );
+    }
+    for call in &entry.tool_calls {
+        println!("[tool] {}({})", call.name, call.arguments);
+    }
+
+    let heading = match &entry.model {
+        Some(model) => format!("## Assistant ({})", model),
+        None => "## Assistant".to_string(),
+    };
+    let status = match ChatStatus::parse(&entry.status) {
+        Some(ChatStatus::Complete) | None => String::new(),
+        Some(_) => format!(" [{}]", entry.status),
+    };
+    println!("{}{}\n\n{}\n", heading, status, entry.answer);
+}
+
+async fn show(args: Args) -> Result<(), String> {
+    args.check_flags(&["--json"])?;
+    let [session_id] = args.positional.as_slice() else {
+        return Err(format!("Expected one session id\n\n{}", USAGE));
+    };
+    let entries = open_cuuri()?.chat_history(Some(session_id.clone())).await?;
+    if entries.is_empty() {
+        return Err(format!("Session `{}` does not exist", session_id));
+    }
+
+    if args.has("--json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&entries).map_err(|e| e.to_string())?
+        );
+        return Ok(());
+    }
+    for entry in &entries {
+        print_entry(entry);
+    }
+    Ok(())
+}
+
+async fn export(args: Args) -> Result<(), String> {
+    args.check_flags(&["--format", "--output", "--embed-images"])?;
+    let [session_id] = args.positional.as_slice() else {
+        return Err(format!("Expected one session id\n\n{}", USAGE));
+    };
+    let format = match args.value("--format")?.as_deref() {
+        None | Some("markdown") => ExportFormat::Markdown,
+        Some("json") => ExportFormat::Json,
+        Some("html") => ExportFormat::Html,
+        Some(other) => return Err(format!("Unknown export format `{}`", other)),
+    };
+    let output = args.value("--output")?.map(PathBuf::from);
+    let embed_images = args.has("--embed-images");
+
+    let cuuri = open_cuuri()?;
+    let session_id = session_id.clone();
+    match output {
+        Some(path) => {
+            cuuri
+                .export_session(session_id, format, path.clone(), embed_images)
+                .await?;
+            eprintln!("Exported to {}", path.display());
+            Ok(())
+        }
+        None => {
+            let content = cuuri.render_session(session_id, format).await?;
+            io::stdout()
+                .write_all(content.as_bytes())
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    // Standard output is kept for answers and listings
+    log_to_stderr(LevelFilter::Warn);
+
+    let mut args = env::args().skip(1).peekable();
+    // Global flags may come before the command; `command_line_args` reads them
+    while let Some(arg) = args.next_if(|arg| {
+        GLOBAL_FLAGS.contains(&arg.split_once('=').map_or(arg.as_str(), |(flag, _)| flag))
+    }) {
+        if !arg.contains('=') {
+            args.next();
+        }
+    }
+    let command = args.next();
+
+    let runtime = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = runtime.block_on(async move {
+        match command.as_deref() {
+            Some("send") => send(Args::parse(args, &["--session", "--model"])).await,
+            Some("sessions") => sessions(Args::parse(args, &[])).await,
+            Some("show") => show(Args::parse(args, &[])).await,
+            Some("export") => export(Args::parse(args, &["--format", "--output"])).await,
+            Some("help") | Some("--help") | Some("-h") => {
+                print!("{}", USAGE);
+                Ok(())
+            }
+            Some(other) => Err(format!("Unknown command `{}`\n\n{}", other, USAGE)),
+            None => Err(USAGE.to_string()),
+        }
+    });
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
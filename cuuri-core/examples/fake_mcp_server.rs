@@ -0,0 +1,97 @@
+//! A minimal MCP server speaking over stdio, for trying out the MCP client.
+//!
+//! Build it with `cargo build -p cuuri-core --example fake_mcp_server` and add it to
+//! `config.toml`:
+//!
+//! ```toml
+//! [mcp_servers.fake]
+//! command = "/path/to/cuuri/target/debug/examples/fake_mcp_server"
+//! ```
+//!
+//! It offers an `echo` tool that returns its `text` argument and an `add`
+//! tool that adds the numbers `a` and `b`.
+
+use serde_json::json;
+use std::io::{self, BufRead, Write};
+
+fn tool_list() -> serde_json::Value {
+    json!([
+        {
+            "name": "echo",
+            "description": "Returns the given text unchanged.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "text": { "type": "string" } },
+                "required": ["text"]
+            }
+        },
+        {
+            "name": "add",
+            "description": "Adds two numbers.",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "a": { "type": "number" }, "b": { "type": "number" } },
+                "required": ["a", "b"]
+            }
+        }
+    ])
+}
+
+fn call_tool(params: &serde_json::Value) -> serde_json::Value {
+    let arguments = &params["arguments"];
+    let (text, is_error) = match params["name"].as_str() {
+        Some("echo") => match arguments["text"].as_str() {
+            Some(text) => (text.to_string(), false),
+            None => ("Missing `text` argument".to_string(), true),
+        },
+        Some("add") => match (arguments["a"].as_f64(), arguments["b"].as_f64()) {
+            (Some(a), Some(b)) => ((a + b).to_string(), false),
+            _ => ("`a` and `b` must be numbers".to_string(), true),
+        },
+        _ => ("Unknown tool".to_string(), true),
+    };
+    json!({ "content": [{ "type": "text", "text": text }], "isError": is_error })
+}
+
+fn main() {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(request) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+        // Notifications need no answer
+        if request["id"].is_null() {
+            continue;
+        }
+
+        let result = match request["method"].as_str() {
+            Some("initialize") => json!({
+                "protocolVersion": request["params"]["protocolVersion"],
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "fake_mcp_server", "version": "0.1.0" }
+            }),
+            Some("ping") => json!({}),
+            Some("tools/list") => json!({ "tools": tool_list() }),
+            Some("tools/call") => call_tool(&request["params"]),
+            _ => {
+                let response = json!({
+                    "jsonrpc": "2.0",
+                    "id": request["id"],
+                    "error": { "code": -32601, "message": "Method not found" }
+                });
+                let _ = writeln!(stdout, "{}", response);
+                let _ = stdout.flush();
+                continue;
+            }
+        };
+
+        let response = json!({ "jsonrpc": "2.0", "id": request["id"], "result": result });
+        let _ = writeln!(stdout, "{}", response);
+        let _ = stdout.flush();
+    }
+}
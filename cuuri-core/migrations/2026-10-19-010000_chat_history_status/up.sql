@@ -0,0 +1,2 @@
+-- Rows written before this migration always held a finished answer
+ALTER TABLE chat_histories ADD COLUMN status TEXT NOT NULL DEFAULT 'complete';
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS attachments_chat_history_id;
+DROP TABLE attachments;
@@ -0,0 +1,13 @@
+-- Files sent with a question. The bytes live under `attachments/` in the
+-- profile directory, named by their SHA-256 hash.
+CREATE TABLE IF NOT EXISTS attachments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    chat_history_id INTEGER NOT NULL REFERENCES chat_histories (id) ON DELETE CASCADE,
+    kind TEXT NOT NULL,
+    mime_type TEXT NOT NULL,
+    file_name TEXT,
+    content_hash TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS attachments_chat_history_id ON attachments (chat_history_id);
@@ -0,0 +1,3 @@
+-- Text extracted from document attachments, so it does not have to be
+-- extracted again every time the conversation is continued
+ALTER TABLE attachments ADD COLUMN extracted_text TEXT;
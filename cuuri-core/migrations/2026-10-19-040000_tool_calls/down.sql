@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS tool_calls_chat_history_id;
+DROP TABLE tool_calls;
@@ -0,0 +1,15 @@
+-- Tool calls made while answering a question, in the order they were made.
+-- `round` counts the model requests that ended in tool calls.
+CREATE TABLE IF NOT EXISTS tool_calls (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    chat_history_id INTEGER NOT NULL REFERENCES chat_histories (id) ON DELETE CASCADE,
+    round INTEGER NOT NULL,
+    call_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    arguments TEXT NOT NULL,
+    result TEXT NOT NULL,
+    is_error BOOLEAN NOT NULL DEFAULT 0,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS tool_calls_chat_history_id ON tool_calls (chat_history_id);
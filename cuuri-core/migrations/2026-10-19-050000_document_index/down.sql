@@ -0,0 +1,4 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS document_chunks_folder_file;
+DROP TABLE document_chunks;
+DROP TABLE indexed_folders;
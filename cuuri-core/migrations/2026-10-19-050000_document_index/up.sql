@@ -0,0 +1,24 @@
+-- Folders indexed for retrieval, and the embedded chunks of their files.
+-- `file_path` is relative to the folder; `embedding` holds little-endian f32 values.
+CREATE TABLE IF NOT EXISTS indexed_folders (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    path TEXT NOT NULL UNIQUE,
+    embedding_model TEXT NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    indexed_at TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS document_chunks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    folder_id INTEGER NOT NULL REFERENCES indexed_folders (id) ON DELETE CASCADE,
+    file_path TEXT NOT NULL,
+    file_hash TEXT NOT NULL,
+    chunk_index INTEGER NOT NULL,
+    start_line INTEGER NOT NULL,
+    end_line INTEGER NOT NULL,
+    content TEXT NOT NULL,
+    embedding BLOB NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS document_chunks_folder_file ON document_chunks (folder_id, file_path);
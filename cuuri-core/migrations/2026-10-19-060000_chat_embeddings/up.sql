@@ -0,0 +1,8 @@
+-- One embedding per answered question, for semantic search over past conversations.
+-- `embedding` holds little-endian f32 values.
+CREATE TABLE IF NOT EXISTS chat_embeddings (
+    chat_history_id INTEGER PRIMARY KEY NOT NULL REFERENCES chat_histories (id) ON DELETE CASCADE,
+    embedding_model TEXT NOT NULL,
+    embedding BLOB NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
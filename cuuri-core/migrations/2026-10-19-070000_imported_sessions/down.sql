@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE chat_histories DROP COLUMN model;
+DROP TABLE imported_sessions;
@@ -0,0 +1,15 @@
+-- Conversations imported from other apps. `source` and `source_id` identify
+-- the original conversation, so importing the same export again adds only
+-- what is new.
+CREATE TABLE IF NOT EXISTS imported_sessions (
+    session_id TEXT PRIMARY KEY NOT NULL,
+    source TEXT NOT NULL,
+    source_id TEXT NOT NULL,
+    title TEXT,
+    source_updated_at TIMESTAMP NOT NULL,
+    imported_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    UNIQUE (source, source_id)
+);
+
+-- The model that wrote the answer; unknown for rows written before this migration
+ALTER TABLE chat_histories ADD COLUMN model TEXT;
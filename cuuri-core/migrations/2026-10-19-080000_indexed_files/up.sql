@@ -0,0 +1,11 @@
+-- The hash of every indexed file, including files without any text to embed,
+-- so that unchanged files are not read again on the next run.
+CREATE TABLE IF NOT EXISTS indexed_files (
+    folder_id INTEGER NOT NULL REFERENCES indexed_folders (id) ON DELETE CASCADE,
+    file_path TEXT NOT NULL,
+    file_hash TEXT NOT NULL,
+    PRIMARY KEY (folder_id, file_path)
+);
+
+INSERT OR IGNORE INTO indexed_files (folder_id, file_path, file_hash)
+SELECT DISTINCT folder_id, file_path, file_hash FROM document_chunks;
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+#[derive(Default)]
+pub struct CancelHandle {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Completes once `cancel` has been called.
+    pub async fn cancelled(&self) {
+        loop {
+            let notified = self.notify.notified();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+/// Answers currently in progress, keyed by session id, so they can be cancelled.
+/// Answers relayed by the proxy server are kept under ids of their own.
+#[derive(Default)]
+pub struct ActiveStreams {
+    streams: Mutex<HashMap<String, Arc<CancelHandle>>>,
+}
+
+impl ActiveStreams {
+    pub fn register(&self, session_id: &str) -> Arc<CancelHandle> {
+        let handle = Arc::new(CancelHandle::default());
+        if let Ok(mut streams) = self.streams.lock() {
+            // A new question in the same session supersedes the previous response
+            if let Some(previous) = streams.insert(session_id.to_string(), handle.clone()) {
+                previous.cancel();
+            }
+        }
+        handle
+    }
+
+    pub fn remove(&self, session_id: &str, handle: &Arc<CancelHandle>) {
+        if let Ok(mut streams) = self.streams.lock() {
+            if streams
+                .get(session_id)
+                .is_some_and(|current| Arc::ptr_eq(current, handle))
+            {
+                streams.remove(session_id);
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.streams
+            .lock()
+            .map(|streams| streams.is_empty())
+            .unwrap_or(true)
+    }
+
+    /// Cancels the response streamed for `session_id`, returning whether one was running.
+    pub fn cancel(&self, session_id: &str) -> bool {
+        let handle = self
+            .streams
+            .lock()
+            .ok()
+            .and_then(|streams| streams.get(session_id).cloned());
+
+        match handle {
+            Some(handle) => {
+                handle.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::config_overrides::ConfigSource;
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct ChatResponse {
+    pub response: String,
+    pub created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct RawDatabaseChatEntry {
+    pub session_id: String,
+    pub question: String,
+    pub answer: String,
+    pub created_at: String,
+    pub status: String,
+    pub model: Option<String>,
+    pub attachments: Vec<RawAttachment>,
+    pub tool_calls: Vec<RawToolCall>,
+}
+
+#[derive(Serialize)]
+pub struct RawAttachment {
+    pub id: i32,
+    pub kind: String,
+    pub mime_type: String,
+    pub file_name: Option<String>,
+    /// The file contents, base64 encoded.
+    pub data: String,
+    pub created_at: String,
+    /// The text sent to the model, for document attachments.
+    pub extracted_text: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RawToolCall {
+    pub id: i32,
+    pub round: i32,
+    pub call_id: String,
+    pub name: String,
+    /// The arguments as the JSON string produced by the model.
+    pub arguments: String,
+    pub result: String,
+    pub is_error: bool,
+    pub created_at: String,
+}
+
+#[derive(Serialize)]
+pub struct SessionId(pub String);
+
+#[derive(Serialize)]
+pub struct ConfigValueSource {
+    pub key: String,
+    pub value: Option<String>,
+    pub source: ConfigSource,
+}
+
+#[derive(Serialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    pub path: String,
+    pub active: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct McpServerStatus {
+    pub name: String,
+    pub transport: String,
+    pub enabled: bool,
+    pub connected: bool,
+    /// Names under which the server's tools are offered to the model.
+    pub tools: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct IndexedFolderInfo {
+    pub id: i32,
+    pub path: String,
+    pub embedding_model: String,
+    pub file_count: i64,
+    pub chunk_count: i64,
+    pub created_at: String,
+    pub indexed_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SemanticSearchResult {
+    pub session_id: String,
+    pub question: String,
+    pub answer: String,
+    pub created_at: String,
+    pub score: f32,
+}
+
+#[derive(Serialize, Default)]
+pub struct ImportReport {
+    /// Nothing was written; the counts show what an import would do.
+    pub dry_run: bool,
+    pub conversations_imported: usize,
+    /// Conversations imported before that gained new messages.
+    pub conversations_updated: usize,
+    /// Conversations imported before with nothing new.
+    pub conversations_unchanged: usize,
+    pub chats_imported: usize,
+    pub skipped: Vec<SkippedImportItem>,
+}
+
+#[derive(Serialize)]
+pub struct SkippedImportItem {
+    /// Title or id of the conversation the item belongs to.
+    pub conversation: String,
+    pub reason: String,
+}
+
+#[derive(Serialize)]
+pub struct IntegrityCheckReport {
+    pub ok: bool,
+    /// The rows of `PRAGMA integrity_check`, just `ok` for a healthy database.
+    pub messages: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct DatabaseEncryptionStatus {
+    pub encrypted: bool,
+    /// Encrypted, and waiting for `unlock_database`.
+    pub locked: bool,
+    /// `keyring` or `passphrase` while the database is unlocked.
+    pub key_source: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DatabaseStatus {
+    /// `open`, `locked` or `failed`.
+    pub state: String,
+    /// Why the database could not be opened.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct ProxyStatus {
+    pub enabled: bool,
+    pub running: bool,
+    /// Base URL to give OpenAI clients, e.g. `http://127.0.0.1:8371/v1`.
+    pub base_url: Option<String>,
+    /// The API key clients have to send.
+    pub token: Option<String>,
+    /// Why the server could not be started.
+    pub error: Option<String>,
+}
@@ -0,0 +1,178 @@
+use crate::app_type::RawAttachment;
+use crate::models::{Attachment, NewAttachment};
+use crate::prepare_image::PreparedImage;
+use crate::schema::attachments;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::sql_types::Text;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const KIND_IMAGE: &str = "image";
+pub const KIND_DOCUMENT: &str = "document";
+
+/// The types image attachments are stored as, see `prepare_images`.
+pub const IMAGE_MIME_TYPES: [&str; 4] = ["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+/// An attachment row together with the bytes it refers to.
+pub struct LoadedAttachment {
+    pub attachment: Attachment,
+    pub data: Vec<u8>,
+}
+
+#[derive(QueryableByName)]
+struct DatabaseFile {
+    #[diesel(sql_type = Text)]
+    file: String,
+}
+
+/// The directory holding the attachment files of the database `conn` is
+/// connected to. They sit next to it, which for the app's own database is
+/// the profile directory.
+pub fn attachment_dir(conn: &mut SqliteConnection) -> Result<PathBuf, String> {
+    let database = diesel::sql_query("SELECT file FROM pragma_database_list WHERE name = 'main'")
+        .get_result::<DatabaseFile>(conn)
+        .map_err(|e| e.to_string())?;
+    // In-memory databases have no file
+    let parent = Path::new(&database.file)
+        .parent()
+        .filter(|_| !database.file.is_empty())
+        .ok_or("The chat database has no directory to keep attachments in".to_string())?;
+    Ok(parent.join("attachments"))
+}
+
+fn attachment_path(dir: &Path, content_hash: &str) -> Result<PathBuf, String> {
+    // Hashes also arrive from the frontend, so anything else could leave the directory
+    let is_sha256 = content_hash.len() == 64
+        && content_hash
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+    if !is_sha256 {
+        return Err(format!("Invalid attachment hash: {}", content_hash));
+    }
+
+    Ok(dir.join(content_hash))
+}
+
+pub fn attachment_hash(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Writes `data` to `dir` under its SHA-256 hash, so identical files are
+/// stored once.
+pub fn store_attachment_blob(dir: &Path, data: &[u8]) -> Result<String, String> {
+    let content_hash = attachment_hash(data);
+    let path = attachment_path(dir, &content_hash)?;
+
+    if !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create attachment directory: {}", e))?;
+        }
+        fs::write(&path, data).map_err(|e| format!("Failed to store attachment: {}", e))?;
+    }
+
+    Ok(content_hash)
+}
+
+pub fn read_attachment_blob(dir: &Path, content_hash: &str) -> Result<Vec<u8>, String> {
+    let path = attachment_path(dir, content_hash)?;
+    fs::read(&path).map_err(|e| format!("Failed to read attachment {}: {}", content_hash, e))
+}
+
+pub fn insert_attachment(
+    conn: &mut SqliteConnection,
+    chat_id: i32,
+    kind: &str,
+    mime_type: &str,
+    file_name: Option<&str>,
+    content_hash: &str,
+    extracted_text: Option<&str>,
+) -> Result<(), String> {
+    let new_attachment = NewAttachment {
+        chat_history_id: chat_id,
+        kind,
+        mime_type,
+        file_name,
+        content_hash,
+        created_at: Utc::now().naive_utc(),
+        extracted_text,
+    };
+
+    diesel::insert_into(attachments::table)
+        .values(&new_attachment)
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+pub fn save_image_attachments(
+    conn: &mut SqliteConnection,
+    chat_id: i32,
+    images: &[PreparedImage],
+) -> Result<(), String> {
+    let dir = attachment_dir(conn)?;
+    for image in images {
+        let content_hash = store_attachment_blob(&dir, &image.data)?;
+        insert_attachment(conn, chat_id, KIND_IMAGE, image.mime_type, None, &content_hash, None)?;
+    }
+    Ok(())
+}
+
+/// Loads the attachments of the given chat rows, grouped by chat row id.
+pub fn load_attachments(
+    conn: &mut SqliteConnection,
+    chat_ids: &[i32],
+) -> Result<HashMap<i32, Vec<LoadedAttachment>>, String> {
+    let rows = attachments::table
+        .filter(attachments::chat_history_id.eq_any(chat_ids))
+        .order(attachments::id.asc())
+        .load::<Attachment>(conn)
+        .map_err(|e| e.to_string())?;
+    if rows.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let dir = attachment_dir(conn)?;
+    let mut grouped: HashMap<i32, Vec<LoadedAttachment>> = HashMap::new();
+    for attachment in rows {
+        // A missing file should not hide the rest of the conversation
+        let data = match read_attachment_blob(&dir, &attachment.content_hash) {
+            Ok(data) => data,
+            Err(e) => {
+                log::warn!("{}", e);
+                continue;
+            }
+        };
+        grouped
+            .entry(attachment.chat_history_id)
+            .or_default()
+            .push(LoadedAttachment { attachment, data });
+    }
+
+    Ok(grouped)
+}
+
+pub fn to_raw_attachment(loaded: &LoadedAttachment) -> RawAttachment {
+    RawAttachment {
+        id: loaded.attachment.id,
+        kind: loaded.attachment.kind.clone(),
+        mime_type: loaded.attachment.mime_type.clone(),
+        file_name: loaded.attachment.file_name.clone(),
+        data: STANDARD.encode(&loaded.data),
+        created_at: loaded.attachment.created_at.to_string(),
+        extracted_text: loaded.attachment.extracted_text.clone(),
+    }
+}
+
+pub fn to_data_url(loaded: &LoadedAttachment) -> String {
+    format!(
+        "data:{};base64,{}",
+        loaded.attachment.mime_type,
+        STANDARD.encode(&loaded.data)
+    )
+}
@@ -0,0 +1,140 @@
+use crate::evaluate_expression::evaluate_expression;
+use crate::tool_registry::{Tool, ToolContext, ToolRegistry};
+use chrono::{Local, Utc};
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+
+// Larger files would crowd out the rest of the conversation
+const MAX_FILE_BYTES: u64 = 256 * 1024;
+
+// Far longer than any expression worth evaluating
+const MAX_EXPRESSION_CHARS: usize = 1000;
+
+async fn current_time(
+    _arguments: serde_json::Value,
+    _context: ToolContext,
+) -> Result<String, String> {
+    Ok(json!({
+        "utc": Utc::now().to_rfc3339(),
+        "local": Local::now().to_rfc3339(),
+    })
+    .to_string())
+}
+
+async fn calculator(arguments: serde_json::Value, _context: ToolContext) -> Result<String, String> {
+    let expression = arguments["expression"]
+        .as_str()
+        .ok_or("Missing `expression` argument".to_string())?;
+    if expression.chars().count() > MAX_EXPRESSION_CHARS {
+        return Err(format!(
+            "The expression is too long (limit {} characters)",
+            MAX_EXPRESSION_CHARS
+        ));
+    }
+    evaluate_expression(expression).map(|value| value.to_string())
+}
+
+async fn read_local_file(
+    arguments: serde_json::Value,
+    context: ToolContext,
+) -> Result<String, String> {
+    let path = arguments["path"]
+        .as_str()
+        .ok_or("Missing `path` argument".to_string())?;
+
+    // Resolve symlinks and `..` before comparing against the allowed directories
+    let path = fs::canonicalize(path).map_err(|e| format!("Cannot access {}: {}", path, e))?;
+    let allowed = context
+        .config
+        .tool_allowed_directories
+        .iter()
+        .filter_map(|dir| fs::canonicalize(PathBuf::from(dir)).ok())
+        .any(|dir| path.starts_with(dir));
+    if !allowed {
+        return Err(format!(
+            "Access denied: {} is outside the directories listed in `tool_allowed_directories`",
+            path.display()
+        ));
+    }
+
+    let metadata = fs::metadata(&path).map_err(|e| e.to_string())?;
+    if !metadata.is_file() {
+        return Err(format!("{} is not a file", path.display()));
+    }
+    if metadata.len() > MAX_FILE_BYTES {
+        return Err(format!(
+            "{} is too large ({} bytes, limit {} bytes)",
+            path.display(),
+            metadata.len(),
+            MAX_FILE_BYTES
+        ));
+    }
+
+    let data = fs::read(&path).map_err(|e| e.to_string())?;
+    String::from_utf8(data).map_err(|_| format!("{} is not a UTF-8 text file", path.display()))
+}
+
+pub fn register_builtin_tools(registry: &ToolRegistry) {
+    registry.register(Tool::new(
+        "current_time",
+        "Returns the current date and time in UTC and in the user's local time zone.",
+        json!({ "type": "object", "properties": {} }),
+        current_time,
+    ));
+
+    registry.register(Tool::new(
+        "calculator",
+        "Evaluates an arithmetic expression. Supports + - * / % ^, parentheses, pi, e and the functions sqrt, abs, sin, cos, tan, asin, acos, atan, ln, log, exp, floor, ceil and round.",
+        json!({
+            "type": "object",
+            "properties": {
+                "expression": { "type": "string", "description": "The expression to evaluate, e.g. \"(3 + 4) * 2\"" }
+            },
+            "required": ["expression"]
+        }),
+        calculator,
+    ));
+
+    registry.register(Tool::new(
+        "read_local_file",
+        "Reads a UTF-8 text file from the user's computer. Only files inside the directories the user has allowed can be read.",
+        json!({
+            "type": "object",
+            "properties": {
+                "path": { "type": "string", "description": "Absolute path of the file" }
+            },
+            "required": ["path"]
+        }),
+        read_local_file,
+    )
+    .requiring_approval());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    fn context() -> ToolContext {
+        ToolContext {
+            config: Config::default(),
+            session_id: "session".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn calculator_evaluates_expressions() {
+        let result = calculator(json!({ "expression": "6 * 7" }), context()).await;
+        assert_eq!(result, Ok("42".to_string()));
+    }
+
+    #[tokio::test]
+    async fn calculator_refuses_long_expressions() {
+        let expression = "1+".repeat(MAX_EXPRESSION_CHARS) + "1";
+        let error = calculator(json!({ "expression": expression }), context())
+            .await
+            .unwrap_err();
+        assert!(error.contains("too long"), "{}", error);
+    }
+}
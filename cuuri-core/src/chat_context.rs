@@ -0,0 +1,119 @@
+use crate::attachments::{load_attachments, to_data_url, LoadedAttachment, KIND_DOCUMENT, KIND_IMAGE};
+use crate::chat_record::ChatStatus;
+use crate::documents::{build_document_parts, ExtractedDocument};
+use crate::models::{ChatHistory, ToolCallRecord};
+use crate::prepare_image::{ImageDetail, PreparedImage};
+use crate::schema::chat_histories::dsl::*;
+use crate::tool_calls::{build_tool_messages, load_tool_calls};
+use diesel::prelude::*;
+use serde_json::json;
+
+/// A stored question/answer pair with the files that were sent with the
+/// question and the tools called while answering it.
+pub struct HistoryEntry {
+    pub chat: ChatHistory,
+    pub attachments: Vec<LoadedAttachment>,
+    pub tool_calls: Vec<ToolCallRecord>,
+}
+
+pub fn fetch_session_history(
+    conn: &mut SqliteConnection,
+    input_session_id: &String,
+) -> Result<Vec<HistoryEntry>, String> {
+    // Questions without a usable answer are left out of the context
+    let chats = chat_histories
+        .filter(session_id.eq(input_session_id))
+        .filter(status.eq_any([ChatStatus::Complete.as_str(), ChatStatus::Cancelled.as_str()]))
+        .order(created_at.asc())
+        .load::<ChatHistory>(conn)
+        .map_err(|e| e.to_string())?;
+
+    let chat_ids: Vec<i32> = chats.iter().map(|chat| chat.id).collect();
+    let mut attachments = load_attachments(conn, &chat_ids)?;
+    let mut tool_calls = load_tool_calls(conn, &chat_ids)?;
+
+    Ok(chats
+        .into_iter()
+        .map(|chat| HistoryEntry {
+            attachments: attachments.remove(&chat.id).unwrap_or_default(),
+            tool_calls: tool_calls.remove(&chat.id).unwrap_or_default(),
+            chat,
+        })
+        .collect())
+}
+
+pub fn build_messages_from_history(
+    session_history: &[HistoryEntry],
+    document_token_budget: usize,
+) -> Vec<serde_json::Value> {
+    session_history
+        .iter()
+        .flat_map(|entry| {
+            let mut user_content = vec![json!({ "type": "text", "text": entry.chat.question.clone() })];
+            user_content.extend(build_document_parts(
+                entry
+                    .attachments
+                    .iter()
+                    .filter(|loaded| loaded.attachment.kind == KIND_DOCUMENT)
+                    .map(|loaded| {
+                        (
+                            loaded.attachment.file_name.as_deref().unwrap_or("attachment"),
+                            loaded.attachment.extracted_text.as_deref().unwrap_or(""),
+                        )
+                    }),
+                document_token_budget,
+            ));
+            user_content.extend(
+                entry
+                    .attachments
+                    .iter()
+                    .filter(|loaded| loaded.attachment.kind == KIND_IMAGE)
+                    .map(|loaded| {
+                        json!({
+                            "type": "image_url",
+                            "image_url": { "url": to_data_url(loaded) }
+                        })
+                    }),
+            );
+
+            let mut messages = vec![json!({
+                "role": "user",
+                "content": user_content
+            })];
+            messages.extend(build_tool_messages(&entry.tool_calls));
+            messages.push(json!({
+                "role": "assistant",
+                "content": [
+                    { "type": "text", "text": entry.chat.answer.clone() }
+                ]
+            }));
+            messages
+        })
+        .collect()
+}
+
+pub fn build_user_message(
+    message: &String,
+    documents: &[ExtractedDocument],
+    document_token_budget: usize,
+    images: &[PreparedImage],
+    detail: ImageDetail,
+) -> serde_json::Value {
+    let mut user_content = vec![json!({ "type": "text", "text": message })];
+    user_content.extend(build_document_parts(
+        documents
+            .iter()
+            .map(|document| (document.file_name.as_str(), document.text.as_str())),
+        document_token_budget,
+    ));
+    for image in images {
+        user_content.push(json!({
+            "type": "image_url",
+            "image_url": {
+                "url": image.to_data_url(),
+                "detail": detail.as_str()
+            }
+        }));
+    }
+    json!({ "role": "user", "content": user_content })
+}
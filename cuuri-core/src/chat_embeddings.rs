@@ -0,0 +1,171 @@
+use crate::app_type::SemanticSearchResult;
+use crate::chat_record::ChatStatus;
+use crate::config_state::ConfigState;
+use crate::db_pool::{run_on_pool, DbState};
+use crate::embeddings::{
+    cosine_similarity, decode_embedding, encode_embedding, request_embeddings,
+};
+use crate::models::{ChatHistory, NewChatEmbedding};
+use crate::provider_service::Provider;
+use crate::schema::{chat_embeddings, chat_histories};
+use chrono::Utc;
+use diesel::prelude::*;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+// Questions embedded per request
+const EMBEDDING_BATCH_SIZE: i64 = 32;
+// Keeps long answers well inside the embedding model's input limit
+const MAX_EMBEDDED_CHARS: usize = 8000;
+// How long to wait before trying again after a failure, e.g. when offline
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+/// The background task that embeds new questions and answers.
+#[derive(Default)]
+pub struct ChatEmbedder {
+    wake: Notify,
+}
+
+impl ChatEmbedder {
+    pub fn wake(&self) {
+        // A stored permit makes sure a wake-up during a batch is not lost
+        self.wake.notify_one();
+    }
+
+    /// Keeps embeddings up to date: new answers are embedded when woken, and
+    /// everything is embedded again after the embedding model changes. Runs
+    /// for as long as the app does.
+    pub async fn run(&self, config: &ConfigState, db: &DbState) {
+        loop {
+            match embed_next_batch(config, db).await {
+                Ok(0) => {}
+                Ok(_) => continue,
+                Err(e) => log::warn!("Failed to embed chat history: {}", e),
+            }
+
+            tokio::select! {
+                _ = self.wake.notified() => {}
+                _ = tokio::time::sleep(RETRY_INTERVAL) => {}
+            }
+        }
+    }
+}
+
+pub fn embedding_text(chat: &ChatHistory) -> String {
+    format!("Question: {}\nAnswer: {}", chat.question, chat.answer)
+        .chars()
+        .take(MAX_EMBEDDED_CHARS)
+        .collect()
+}
+
+/// Answered questions without an embedding from `model`, oldest first.
+pub fn find_unembedded_chats(
+    conn: &mut SqliteConnection,
+    model: &str,
+    limit: i64,
+) -> Result<Vec<ChatHistory>, String> {
+    chat_histories::table
+        .left_join(chat_embeddings::table)
+        .filter(chat_histories::status.eq(ChatStatus::Complete.as_str()))
+        .filter(
+            chat_embeddings::embedding_model
+                .is_null()
+                .or(chat_embeddings::embedding_model.ne(model)),
+        )
+        .select(chat_histories::all_columns)
+        .order(chat_histories::id.asc())
+        .limit(limit)
+        .load::<ChatHistory>(conn)
+        .map_err(|e| e.to_string())
+}
+
+pub fn save_chat_embeddings(
+    conn: &mut SqliteConnection,
+    model: &str,
+    embeddings: &[(i32, Vec<f32>)],
+) -> Result<(), String> {
+    let now = Utc::now().naive_utc();
+    let encoded: Vec<(i32, Vec<u8>)> = embeddings
+        .iter()
+        .map(|(chat_id, vector)| (*chat_id, encode_embedding(vector)))
+        .collect();
+    let rows: Vec<NewChatEmbedding> = encoded
+        .iter()
+        .map(|(chat_id, embedding)| NewChatEmbedding {
+            chat_history_id: *chat_id,
+            embedding_model: model,
+            embedding,
+            created_at: now,
+        })
+        .collect();
+
+    diesel::replace_into(chat_embeddings::table)
+        .values(&rows)
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// The `k` stored questions and answers closest to `query`.
+pub fn search_chats(
+    conn: &mut SqliteConnection,
+    model: &str,
+    query: &[f32],
+    k: usize,
+) -> Result<Vec<SemanticSearchResult>, String> {
+    let rows = chat_embeddings::table
+        .inner_join(chat_histories::table)
+        .filter(chat_embeddings::embedding_model.eq(model))
+        .select((chat_embeddings::embedding, chat_histories::all_columns))
+        .load::<(Vec<u8>, ChatHistory)>(conn)
+        .map_err(|e| e.to_string())?;
+
+    let mut results: Vec<SemanticSearchResult> = rows
+        .into_iter()
+        .map(|(embedding, chat)| SemanticSearchResult {
+            score: cosine_similarity(query, &decode_embedding(&embedding)),
+            session_id: chat.session_id,
+            question: chat.question,
+            answer: chat.answer,
+            created_at: chat.created_at.to_string(),
+        })
+        .collect();
+    results.sort_by(|a, b| b.score.total_cmp(&a.score));
+    results.truncate(k);
+    Ok(results)
+}
+
+/// Embeds one batch of questions, returning how many were embedded.
+async fn embed_next_batch(config: &ConfigState, db: &DbState) -> Result<usize, String> {
+    let config = config.get();
+    if config.openai_api_key.is_empty() {
+        return Ok(0);
+    }
+
+    // Both steps use the same database even if the profile changes meanwhile
+    // Nothing can be embedded while the database is locked
+    let Ok(pool) = db.pool() else {
+        return Ok(0);
+    };
+    let model = config.embedding_model.clone();
+    let chats = run_on_pool(pool.clone(), move |conn| {
+        find_unembedded_chats(conn, &model, EMBEDDING_BATCH_SIZE)
+    })
+    .await?;
+    if chats.is_empty() {
+        return Ok(0);
+    }
+
+    let inputs: Vec<String> = chats.iter().map(embedding_text).collect();
+    let provider = Provider::from_config(&config);
+    let vectors = request_embeddings(&provider, &config.embedding_model, &inputs).await?;
+    let embeddings: Vec<(i32, Vec<f32>)> = chats.iter().map(|chat| chat.id).zip(vectors).collect();
+
+    let count = embeddings.len();
+    let model = config.embedding_model;
+    run_on_pool(pool, move |conn| {
+        save_chat_embeddings(conn, &model, &embeddings)
+    })
+    .await?;
+    Ok(count)
+}
@@ -0,0 +1,25 @@
+use crate::document_index::RetrievedChunk;
+use crate::tool_approvals::{ApprovalDecision, ToolApprovalRequest};
+use crate::tool_calls::{ToolCall, ToolCallDelta, ToolCallResult};
+use std::future::Future;
+use std::pin::Pin;
+
+/// Resolves to the user's decision, or `None` when nobody answered.
+pub type ApprovalFuture = Pin<Box<dyn Future<Output = Option<ApprovalDecision>> + Send>>;
+
+/// Receives the progress of an answer: the app forwards it to the window as
+/// events, the command-line client prints it.
+pub trait ChatEvents: Send + Sync {
+    fn token(&self, token: &str);
+
+    fn context_sources(&self, _sources: &[RetrievedChunk]) {}
+
+    fn tool_call_delta(&self, _delta: ToolCallDelta) {}
+
+    fn tool_call(&self, _call: &ToolCall) {}
+
+    fn tool_result(&self, _result: &ToolCallResult) {}
+
+    /// Asks whether a tool that needs approval may run.
+    fn request_approval(&self, request: ToolApprovalRequest) -> ApprovalFuture;
+}
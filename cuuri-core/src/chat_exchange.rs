@@ -0,0 +1,485 @@
+use crate::active_streams::{ActiveStreams, CancelHandle};
+use crate::app_type::ChatResponse;
+use crate::attachments::{attachment_dir, save_image_attachments};
+use crate::chat_context::{build_messages_from_history, build_user_message, fetch_session_history};
+use crate::chat_events::ChatEvents;
+use crate::chat_record::{finish_chat, insert_pending_chat, update_pending_answer, ChatStatus};
+use crate::config::Config;
+use crate::db_pool::{in_transaction, run_on_pool, DbPool, DbState};
+use crate::document_index::{build_context_message, retrieve_context};
+use crate::documents::{load_stored_documents, save_document_attachments, ExtractedDocument};
+use crate::event_stream::EventStreamDecoder;
+use crate::prepare_image::{prepare_images, ImageDetail};
+use crate::provider_service::Provider;
+use crate::tool_calls::{
+    assistant_tool_call_message, execute_tool_calls, parse_tool_calls, save_tool_calls,
+    tool_result_message, ToolCall, ToolCallAccumulator, MAX_TOOL_ROUNDS,
+};
+use crate::tool_registry::{ToolContext, ToolRegistry};
+use chrono::Utc;
+use serde_json::json;
+use std::time::{Duration, Instant};
+
+// How often the partial answer is written to the database while streaming
+const PARTIAL_SAVE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// A question to send in a session, with what was attached to it.
+pub struct ChatRequest {
+    pub session_id: String,
+    pub message: String,
+    pub base64_images: Option<Vec<String>>,
+    pub image_detail: Option<ImageDetail>,
+    pub documents: Option<Vec<ExtractedDocument>>,
+    pub folder_ids: Option<Vec<i32>>,
+    pub model: String,
+    pub api_key: String,
+}
+
+/// What is sent to the provider, and the pending row the answer goes into.
+struct PreparedChat {
+    /// The database the question went into, kept even if the profile is switched.
+    pool: DbPool,
+    chat_id: i32,
+    messages: Vec<serde_json::Value>,
+    tool_definitions: Vec<serde_json::Value>,
+    tool_context: ToolContext,
+}
+
+enum StreamOutcome {
+    /// The model stopped, either with its answer or to call tools.
+    Finished {
+        content: String,
+        tool_calls: Vec<ToolCall>,
+    },
+    Cancelled,
+}
+
+/// Stores the question right away, so it survives a crash or a failed
+/// request, then rebuilds the conversation so far and adds the question. The
+/// question is marked failed if anything after storing it fails.
+async fn prepare_chat(
+    db: &DbState,
+    config: Config,
+    tools: &ToolRegistry,
+    provider: &Provider,
+    events: &dyn ChatEvents,
+    request: &ChatRequest,
+) -> Result<PreparedChat, String> {
+    let pending_session_id = request.session_id.clone();
+    let pending_message = request.message.clone();
+    let pending_model = request.model.clone();
+    let pool = db.pool()?;
+    let chat_id = run_on_pool(pool.clone(), move |conn| {
+        insert_pending_chat(conn, &pending_session_id, &pending_message, &pending_model)
+    })
+    .await?;
+
+    match build_chat(&pool, config, tools, provider, events, request, chat_id).await {
+        Ok(prepared) => Ok(prepared),
+        Err(e) => {
+            run_on_pool(pool, move |conn| {
+                finish_chat(conn, chat_id, &String::new(), ChatStatus::Failed)
+            })
+            .await?;
+            Err(e)
+        }
+    }
+}
+
+/// Builds what is sent to the provider for the question stored as `chat_id`
+/// and stores its attachments.
+async fn build_chat(
+    pool: &DbPool,
+    config: Config,
+    tools: &ToolRegistry,
+    provider: &Provider,
+    events: &dyn ChatEvents,
+    request: &ChatRequest,
+    chat_id: i32,
+) -> Result<PreparedChat, String> {
+    let document_token_budget = config.document_token_budget;
+    let documents = request.documents.clone().unwrap_or_default();
+    let documents = if documents.is_empty() {
+        documents
+    } else {
+        let dir = run_on_pool(pool.clone(), attachment_dir).await?;
+        tokio::task::spawn_blocking(move || load_stored_documents(&dir, documents))
+            .await
+            .map_err(|e| e.to_string())??
+    };
+
+    // Step 1: fetch session history, which leaves out the pending question
+    let history_session_id = request.session_id.clone();
+    let session_history = run_on_pool(pool.clone(), move |conn| {
+        fetch_session_history(conn, &history_session_id)
+    })
+    .await?;
+
+    // Step 2: build messages from history
+    let mut messages = build_messages_from_history(&session_history, document_token_budget);
+
+    // Step 3: add excerpts from the indexed folders chosen for this question
+    let sources = retrieve_context(
+        pool.clone(),
+        provider,
+        &config.embedding_model,
+        request.folder_ids.clone().unwrap_or_default(),
+        config.retrieval_top_k,
+        &request.message,
+    )
+    .await?;
+    if !sources.is_empty() {
+        events.context_sources(&sources);
+        messages.push(build_context_message(&sources));
+    }
+
+    // Step 4: add user message, with images checked and scaled down to the provider limits
+    let detail = request.image_detail.unwrap_or_default();
+    let images = prepare_images(request.base64_images.clone(), detail).await?;
+    messages.push(build_user_message(
+        &request.message,
+        &documents,
+        document_token_budget,
+        &images,
+        detail,
+    ));
+
+    // Step 5: offer the registered tools unless they are turned off
+    let tool_definitions = if config.enable_tools {
+        tools.definitions()
+    } else {
+        Vec::new()
+    };
+    let tool_context = ToolContext {
+        config,
+        session_id: request.session_id.clone(),
+    };
+
+    // Step 6: store the attachments of the question
+    run_on_pool(pool.clone(), move |conn| {
+        in_transaction(conn, |conn| {
+            save_image_attachments(conn, chat_id, &images)?;
+            save_document_attachments(conn, chat_id, &documents)
+        })
+    })
+    .await?;
+
+    Ok(PreparedChat {
+        pool: pool.clone(),
+        chat_id,
+        messages,
+        tool_definitions,
+        tool_context,
+    })
+}
+
+fn build_request_body(
+    model: &str,
+    messages: &[serde_json::Value],
+    tools: &[serde_json::Value],
+    stream: bool,
+) -> serde_json::Value {
+    let mut body = json!({
+        "model": model,
+        "messages": messages,
+    });
+    if stream {
+        body["stream"] = json!(true);
+    }
+    if !tools.is_empty() {
+        body["tools"] = json!(tools);
+    }
+    body
+}
+
+async fn fetch_streaming_response(
+    provider: &Provider,
+    request_body: &serde_json::Value,
+) -> Result<reqwest::Response, String> {
+    provider
+        .post("chat/completions")
+        .json(request_body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send request: {}", e))
+}
+
+async fn process_stream_response(
+    mut res: reqwest::Response,
+    events: &dyn ChatEvents,
+    pool: &DbPool,
+    chat_id: i32,
+    cancel: &CancelHandle,
+    full_response: &mut String,
+) -> Result<StreamOutcome, String> {
+    if !res.status().is_success() {
+        let http_status = res.status();
+        let body = res.text().await.unwrap_or_default();
+        return Err(format!("Request failed: HTTP {} {}", http_status, body));
+    }
+
+    let mut content = String::new();
+    let mut tool_calls = ToolCallAccumulator::default();
+    let mut decoder = EventStreamDecoder::default();
+    let mut last_saved = Instant::now();
+    loop {
+        let chunk = tokio::select! {
+            chunk = res.chunk() => chunk.map_err(|e| e.to_string())?,
+            _ = cancel.cancelled() => return Ok(StreamOutcome::Cancelled),
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+
+        for event in decoder.push(&chunk) {
+            // Errors after the response started arrive as events
+            if let Some(message) = event["error"]["message"].as_str() {
+                return Err(format!("Request failed: {}", message));
+            }
+            let delta = &event["choices"][0]["delta"];
+            if let Some(token) = delta["content"].as_str() {
+                content.push_str(token);
+                full_response.push_str(token);
+                events.token(token);
+            }
+            for tool_call_delta in tool_calls.apply(&delta["tool_calls"]) {
+                events.tool_call_delta(tool_call_delta);
+            }
+        }
+
+        // Persist progress so a crash loses at most the last interval
+        if last_saved.elapsed() >= PARTIAL_SAVE_INTERVAL {
+            let partial_answer = full_response.clone();
+            run_on_pool(pool.clone(), move |conn| {
+                update_pending_answer(conn, chat_id, &partial_answer)
+            })
+            .await?;
+            last_saved = Instant::now();
+        }
+    }
+    Ok(StreamOutcome::Finished {
+        content,
+        tool_calls: tool_calls.into_calls(),
+    })
+}
+
+/// Sends the question of `request` and streams the answer to `events`,
+/// running the tools the model asks for. The question is stored first and
+/// the answer is saved as it arrives, so whatever was received is kept when
+/// the answer fails or is cancelled through `streams`.
+pub async fn stream_chat(
+    db: &DbState,
+    config: Config,
+    streams: &ActiveStreams,
+    tools: &ToolRegistry,
+    provider: &Provider,
+    events: &dyn ChatEvents,
+    request: ChatRequest,
+) -> Result<ChatResponse, String> {
+    // Registered before the question is stored, so that switching profiles
+    // cannot mark it failed while the answer is being prepared
+    let cancel = streams.register(&request.session_id);
+    let PreparedChat {
+        pool,
+        chat_id,
+        mut messages,
+        tool_definitions,
+        tool_context,
+    } = match prepare_chat(db, config, tools, provider, events, &request).await {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            streams.remove(&request.session_id, &cancel);
+            return Err(e);
+        }
+    };
+
+    // Send streaming requests, running the requested tools and sending their
+    // results back until the model answers
+    let mut full_response = String::new();
+    let mut round = 0;
+    let result = loop {
+        let request_body = build_request_body(&request.model, &messages, &tool_definitions, true);
+        let outcome = match fetch_streaming_response(provider, &request_body).await {
+            Ok(res) => {
+                process_stream_response(res, events, &pool, chat_id, &cancel, &mut full_response)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        let (content, tool_calls) = match outcome {
+            Ok(StreamOutcome::Finished {
+                content,
+                tool_calls,
+            }) if !tool_calls.is_empty() => (content, tool_calls),
+            other => break other,
+        };
+        if round >= MAX_TOOL_ROUNDS {
+            break Err(format!(
+                "The model requested tools more than {} times in a row",
+                MAX_TOOL_ROUNDS
+            ));
+        }
+
+        let results = execute_tool_calls(tools, &tool_context, &tool_calls, events, &cancel).await;
+        messages.push(assistant_tool_call_message(&content, &tool_calls));
+        messages.extend(
+            results
+                .iter()
+                .map(|result| tool_result_message(&result.id, &result.content)),
+        );
+
+        let saved_calls = tool_calls.clone();
+        if let Err(e) = run_on_pool(pool.clone(), move |conn| {
+            save_tool_calls(conn, chat_id, round, &saved_calls, &results)
+        })
+        .await
+        {
+            break Err(e);
+        }
+        if cancel.is_cancelled() {
+            break Ok(StreamOutcome::Cancelled);
+        }
+        round += 1;
+    };
+    streams.remove(&request.session_id, &cancel);
+
+    // Commit the final state, keeping whatever was received
+    let final_status = match &result {
+        Ok(StreamOutcome::Finished { .. }) => ChatStatus::Complete,
+        Ok(StreamOutcome::Cancelled) => ChatStatus::Cancelled,
+        Err(_) => ChatStatus::Failed,
+    };
+    let answer_text = full_response.clone();
+    run_on_pool(pool, move |conn| {
+        finish_chat(conn, chat_id, &answer_text, final_status)
+    })
+    .await?;
+    result?;
+
+    Ok(ChatResponse {
+        response: full_response,
+        created_at: Utc::now().naive_utc().to_string(),
+    })
+}
+
+/// Sends a non-streaming request and returns the assistant message.
+async fn request_chat_completion(
+    provider: &Provider,
+    request_body: &serde_json::Value,
+) -> Result<serde_json::Value, String> {
+    let res = provider
+        .post("chat/completions")
+        .json(request_body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+
+    if !res.status().is_success() {
+        let http_status = res.status();
+        let body = res.text().await.unwrap_or_default();
+        return Err(format!("Request failed: HTTP {} {}", http_status, body));
+    }
+
+    let json: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+
+    let message = &json["choices"][0]["message"];
+    if message.is_null() {
+        return Err("No response from API".to_string());
+    }
+    Ok(message.clone())
+}
+
+/// Like `stream_chat`, but waits for the whole answer of each request.
+/// Cancelling through `streams` drops the request or tool approval being
+/// waited for and stores the answer as cancelled.
+pub async fn complete_chat(
+    db: &DbState,
+    config: Config,
+    streams: &ActiveStreams,
+    tools: &ToolRegistry,
+    provider: &Provider,
+    events: &dyn ChatEvents,
+    request: ChatRequest,
+) -> Result<ChatResponse, String> {
+    let cancel = streams.register(&request.session_id);
+    let PreparedChat {
+        pool,
+        chat_id,
+        mut messages,
+        tool_definitions,
+        tool_context,
+    } = match prepare_chat(db, config, tools, provider, events, &request).await {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            streams.remove(&request.session_id, &cancel);
+            return Err(e);
+        }
+    };
+
+    // Run the requested tools and send their results back until the model
+    // answers; `Ok(None)` means the answer was cancelled
+    let mut round = 0;
+    let result = loop {
+        let request_body = build_request_body(&request.model, &messages, &tool_definitions, false);
+        let message = tokio::select! {
+            message = request_chat_completion(provider, &request_body) => match message {
+                Ok(message) => message,
+                Err(e) => break Err(e),
+            },
+            _ = cancel.cancelled() => break Ok(None),
+        };
+        let tool_calls = parse_tool_calls(&message);
+        if tool_calls.is_empty() {
+            break message["content"]
+                .as_str()
+                .map(|content| Some(content.to_string()))
+                .ok_or_else(|| "No response from API".to_string());
+        }
+        if round >= MAX_TOOL_ROUNDS {
+            break Err(format!(
+                "The model requested tools more than {} times in a row",
+                MAX_TOOL_ROUNDS
+            ));
+        }
+
+        let results = execute_tool_calls(tools, &tool_context, &tool_calls, events, &cancel).await;
+        messages.push(assistant_tool_call_message(
+            message["content"].as_str().unwrap_or(""),
+            &tool_calls,
+        ));
+        messages.extend(
+            results
+                .iter()
+                .map(|result| tool_result_message(&result.id, &result.content)),
+        );
+        if let Err(e) = run_on_pool(pool.clone(), move |conn| {
+            save_tool_calls(conn, chat_id, round, &tool_calls, &results)
+        })
+        .await
+        {
+            break Err(e);
+        }
+        if cancel.is_cancelled() {
+            break Ok(None);
+        }
+        round += 1;
+    };
+    streams.remove(&request.session_id, &cancel);
+
+    let (final_answer, final_status) = match &result {
+        Ok(Some(response)) => (response.clone(), ChatStatus::Complete),
+        Ok(None) => (String::new(), ChatStatus::Cancelled),
+        Err(_) => (String::new(), ChatStatus::Failed),
+    };
+    run_on_pool(pool, move |conn| {
+        finish_chat(conn, chat_id, &final_answer, final_status)
+    })
+    .await?;
+    let response = result?.unwrap_or_default();
+
+    Ok(ChatResponse {
+        response,
+        created_at: Utc::now().naive_utc().to_string(),
+    })
+}
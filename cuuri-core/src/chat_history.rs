@@ -0,0 +1,110 @@
+use crate::app_type::RawDatabaseChatEntry;
+use crate::attachments::{load_attachments, to_raw_attachment};
+use crate::models::ChatHistory;
+use crate::schema::chat_histories;
+use crate::tool_calls::{load_tool_calls, to_raw_tool_call};
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+/// A session as listed by the command-line client.
+#[derive(Serialize)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub first_question: String,
+    pub question_count: usize,
+    pub last_active: String,
+}
+
+pub fn list_session_ids(conn: &mut SqliteConnection) -> Result<Vec<String>, String> {
+    chat_histories::table
+        .select(chat_histories::session_id)
+        .distinct()
+        .load::<String>(conn)
+        .map_err(|e| e.to_string())
+}
+
+/// Every session, the most recently active first.
+pub fn list_sessions(conn: &mut SqliteConnection) -> Result<Vec<SessionSummary>, String> {
+    let rows = chat_histories::table
+        .select((
+            chat_histories::session_id,
+            chat_histories::question,
+            chat_histories::created_at,
+        ))
+        .order((chat_histories::created_at.asc(), chat_histories::id.asc()))
+        .load::<(String, String, NaiveDateTime)>(conn)
+        .map_err(|e| e.to_string())?;
+
+    let mut sessions: Vec<(SessionSummary, NaiveDateTime)> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for (session_id, question, created_at) in rows {
+        match positions.get(&session_id) {
+            Some(&position) => {
+                let (summary, last_active) = &mut sessions[position];
+                summary.question_count += 1;
+                *last_active = created_at;
+            }
+            None => {
+                positions.insert(session_id.clone(), sessions.len());
+                sessions.push((
+                    SessionSummary {
+                        session_id,
+                        first_question: question,
+                        question_count: 1,
+                        last_active: String::new(),
+                    },
+                    created_at,
+                ));
+            }
+        }
+    }
+
+    sessions.sort_by_key(|(_, last_active)| Reverse(*last_active));
+    Ok(sessions
+        .into_iter()
+        .map(|(mut summary, last_active)| {
+            summary.last_active = last_active.to_string();
+            summary
+        })
+        .collect())
+}
+
+/// Loads the stored questions and answers of one session, or of all
+/// sessions, with their attachments and tool calls.
+pub fn load_chat_entries(
+    conn: &mut SqliteConnection,
+    session_id: Option<&str>,
+) -> Result<Vec<RawDatabaseChatEntry>, String> {
+    let mut query = chat_histories::table.into_boxed();
+    if let Some(session_id) = session_id {
+        query = query.filter(chat_histories::session_id.eq(session_id));
+    }
+    let results = query.load::<ChatHistory>(conn).map_err(|e| e.to_string())?;
+
+    let chat_ids: Vec<i32> = results.iter().map(|chat| chat.id).collect();
+    let attachments = load_attachments(conn, &chat_ids)?;
+    let tool_calls = load_tool_calls(conn, &chat_ids)?;
+
+    Ok(results
+        .into_iter()
+        .map(|chat| RawDatabaseChatEntry {
+            attachments: attachments
+                .get(&chat.id)
+                .map(|loaded| loaded.iter().map(to_raw_attachment).collect())
+                .unwrap_or_default(),
+            tool_calls: tool_calls
+                .get(&chat.id)
+                .map(|records| records.iter().map(to_raw_tool_call).collect())
+                .unwrap_or_default(),
+            session_id: chat.session_id,
+            question: chat.question,
+            answer: chat.answer,
+            created_at: chat.created_at.to_string(),
+            status: chat.status,
+            model: chat.model,
+        })
+        .collect())
+}
@@ -0,0 +1,118 @@
+use crate::models::NewChatHistory;
+use crate::schema::chat_histories::dsl::*;
+use chrono::Utc;
+use diesel::prelude::*;
+
+diesel::define_sql_function! {
+    fn last_insert_rowid() -> Integer;
+}
+
+/// Lifecycle of a question/answer row in `chat_histories`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChatStatus {
+    /// The question is stored and the answer is still being received.
+    Pending,
+    Complete,
+    Failed,
+    Cancelled,
+}
+
+impl ChatStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChatStatus::Pending => "pending",
+            ChatStatus::Complete => "complete",
+            ChatStatus::Failed => "failed",
+            ChatStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(ChatStatus::Pending),
+            "complete" => Some(ChatStatus::Complete),
+            "failed" => Some(ChatStatus::Failed),
+            "cancelled" => Some(ChatStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// Inserts a chat row, returning its id.
+pub fn insert_chat(conn: &mut SqliteConnection, new_chat: &NewChatHistory) -> Result<i32, String> {
+    conn.transaction(|conn| {
+        diesel::insert_into(chat_histories)
+            .values(new_chat)
+            .execute(conn)?;
+        diesel::select(last_insert_rowid()).get_result::<i32>(conn)
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Stores the question before any request is sent, returning the new row id.
+pub fn insert_pending_chat(
+    conn: &mut SqliteConnection,
+    input_session_id: &String,
+    question_text: &String,
+    model_name: &str,
+) -> Result<i32, String> {
+    let new_chat = NewChatHistory {
+        session_id: input_session_id,
+        question: question_text,
+        answer: &String::new(),
+        created_at: Utc::now().naive_utc(),
+        status: ChatStatus::Pending.as_str(),
+        model: Some(model_name),
+    };
+
+    insert_chat(conn, &new_chat)
+}
+
+/// Saves the part of the answer received so far.
+pub fn update_pending_answer(
+    conn: &mut SqliteConnection,
+    chat_id: i32,
+    partial_answer: &String,
+) -> Result<(), String> {
+    diesel::update(chat_histories.filter(id.eq(chat_id)).filter(status.eq(ChatStatus::Pending.as_str())))
+        .set(answer.eq(partial_answer))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Commits the final answer and status of a pending row.
+pub fn finish_chat(
+    conn: &mut SqliteConnection,
+    chat_id: i32,
+    final_answer: &String,
+    final_status: ChatStatus,
+) -> Result<(), String> {
+    conn.transaction(|conn| {
+        let current_status = chat_histories
+            .filter(id.eq(chat_id))
+            .select(status)
+            .first::<String>(conn)?;
+
+        // A row that was already finished (e.g. by recovery) is left alone
+        if current_status != ChatStatus::Pending.as_str() {
+            return Ok(());
+        }
+
+        diesel::update(chat_histories.filter(id.eq(chat_id)))
+            .set((answer.eq(final_answer), status.eq(final_status.as_str())))
+            .execute(conn)
+            .map(|_| ())
+    })
+    .map_err(|e: diesel::result::Error| e.to_string())
+}
+
+/// Marks rows left pending by a crash or an app close as failed.
+///
+/// Must only run when no response is in flight, i.e. at startup.
+pub fn recover_pending_chats(conn: &mut SqliteConnection) -> Result<usize, String> {
+    diesel::update(chat_histories.filter(status.eq(ChatStatus::Pending.as_str())))
+        .set(status.eq(ChatStatus::Failed.as_str()))
+        .execute(conn)
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,274 @@
+use crate::app_type::SkippedImportItem;
+use crate::chat_record::ChatStatus;
+use crate::conversation_import::{ImportedChat, ImportedConversation};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+pub const SOURCE_CHATGPT: &str = "chatgpt";
+
+#[derive(Deserialize)]
+struct ChatGptConversation {
+    id: Option<String>,
+    conversation_id: Option<String>,
+    title: Option<String>,
+    create_time: Option<f64>,
+    update_time: Option<f64>,
+    mapping: HashMap<String, ChatGptNode>,
+    current_node: Option<String>,
+    default_model_slug: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatGptNode {
+    message: Option<ChatGptMessage>,
+    parent: Option<String>,
+    #[serde(default)]
+    children: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatGptMessage {
+    author: ChatGptAuthor,
+    create_time: Option<f64>,
+    content: Option<ChatGptContent>,
+    recipient: Option<String>,
+    #[serde(default)]
+    metadata: Value,
+}
+
+#[derive(Deserialize)]
+struct ChatGptAuthor {
+    role: String,
+}
+
+#[derive(Deserialize)]
+struct ChatGptContent {
+    content_type: String,
+    #[serde(default)]
+    parts: Vec<Value>,
+}
+
+fn timestamp(seconds: Option<f64>) -> Option<NaiveDateTime> {
+    seconds
+        .and_then(|seconds| DateTime::<Utc>::from_timestamp_millis((seconds * 1000.0) as i64))
+        .map(|time| time.naive_utc())
+}
+
+/// The text of a message and how many images or files it referred to.
+fn message_text(message: &ChatGptMessage) -> (String, usize) {
+    let Some(content) = &message.content else {
+        return (String::new(), 0);
+    };
+    if content.content_type != "text" && content.content_type != "multimodal_text" {
+        return (String::new(), 0);
+    }
+
+    let mut texts = Vec::new();
+    let mut files = 0;
+    for part in &content.parts {
+        match part {
+            Value::String(text) if !text.is_empty() => texts.push(text.as_str()),
+            Value::String(_) | Value::Null => {}
+            _ => files += 1,
+        }
+    }
+    (texts.join("\n\n"), files)
+}
+
+/// The messages on the branch the user last looked at, oldest first. Edited
+/// questions and regenerated answers leave other branches in the tree.
+fn active_branch(conversation: &ChatGptConversation) -> Vec<&ChatGptMessage> {
+    let mapping = &conversation.mapping;
+    let leaf = conversation
+        .current_node
+        .clone()
+        .filter(|id| mapping.contains_key(id))
+        .or_else(|| {
+            // Without a current node, follow the latest reply from the root
+            let mut id = mapping
+                .iter()
+                .find(|(_, node)| {
+                    node.parent
+                        .as_ref()
+                        .is_none_or(|parent| !mapping.contains_key(parent))
+                })
+                .map(|(id, _)| id.clone())?;
+            while let Some(child) = mapping
+                .get(&id)
+                .and_then(|node| node.children.last())
+                .filter(|child| mapping.contains_key(*child))
+            {
+                id = child.clone();
+            }
+            Some(id)
+        });
+
+    let mut messages = Vec::new();
+    let mut current = leaf;
+    while let Some(node) = current.and_then(|id| mapping.get(&id)) {
+        // A malformed tree with a cycle must not loop forever
+        if messages.len() > mapping.len() {
+            break;
+        }
+        if let Some(message) = &node.message {
+            messages.push(message);
+        }
+        current = node.parent.clone();
+    }
+    messages.reverse();
+    messages
+}
+
+fn is_hidden(message: &ChatGptMessage) -> bool {
+    message
+        .metadata
+        .get("is_visually_hidden_from_conversation")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn convert_conversation(
+    conversation: ChatGptConversation,
+    skipped: &mut Vec<SkippedImportItem>,
+) -> Option<ImportedConversation> {
+    let label = conversation
+        .title
+        .clone()
+        .or_else(|| conversation.conversation_id.clone())
+        .or_else(|| conversation.id.clone())
+        .unwrap_or_else(|| "Untitled conversation".to_string());
+    let mut skip = |reason: String| {
+        skipped.push(SkippedImportItem {
+            conversation: label.clone(),
+            reason,
+        })
+    };
+
+    let Some(source_id) = conversation
+        .conversation_id
+        .clone()
+        .or_else(|| conversation.id.clone())
+    else {
+        skip("The conversation has no id".to_string());
+        return None;
+    };
+    let conversation_time = timestamp(conversation.create_time);
+
+    let mut chats: Vec<ImportedChat> = Vec::new();
+    let mut skipped_messages = 0;
+    let mut skipped_files = 0;
+    for message in active_branch(&conversation) {
+        if is_hidden(message) {
+            continue;
+        }
+        let (text, files) = message_text(message);
+        let to_everyone = message
+            .recipient
+            .as_deref()
+            .is_none_or(|recipient| recipient == "all");
+
+        match message.author.role.as_str() {
+            "user" => {
+                skipped_files += files;
+                if text.is_empty() && files == 0 {
+                    continue;
+                }
+                chats.push(ImportedChat::new(
+                    text,
+                    timestamp(message.create_time)
+                        .or(conversation_time)
+                        .unwrap_or_else(|| Utc::now().naive_utc()),
+                ));
+            }
+            "assistant" if to_everyone && !text.is_empty() => {
+                let Some(chat) = chats.last_mut() else {
+                    skipped_messages += 1;
+                    continue;
+                };
+                if !chat.answer.is_empty() {
+                    chat.answer.push_str("\n\n");
+                }
+                chat.answer.push_str(&text);
+                chat.status = ChatStatus::Complete;
+                if let Some(slug) = message.metadata.get("model_slug").and_then(Value::as_str) {
+                    chat.model = Some(slug.to_string());
+                }
+            }
+            "system" if text.is_empty() => {}
+            _ => skipped_messages += 1,
+        }
+    }
+
+    if skipped_messages > 0 {
+        skip(format!(
+            "{} tool, system or non-text messages were not imported",
+            skipped_messages
+        ));
+    }
+    if skipped_files > 0 {
+        skip(format!(
+            "{} images or files were not imported, as conversations.json does not contain them",
+            skipped_files
+        ));
+    }
+    if chats.is_empty() {
+        skip("The conversation has no questions to import".to_string());
+        return None;
+    }
+
+    for chat in &mut chats {
+        if chat.model.is_none() && chat.status == ChatStatus::Complete {
+            chat.model = conversation.default_model_slug.clone();
+        }
+    }
+    let updated_at = timestamp(conversation.update_time)
+        .or(conversation_time)
+        .into_iter()
+        .chain(chats.iter().map(|chat| chat.created_at))
+        .max()
+        .unwrap_or_else(|| Utc::now().naive_utc());
+
+    Some(ImportedConversation {
+        source: SOURCE_CHATGPT.to_string(),
+        source_id,
+        title: conversation.title,
+        updated_at,
+        chats,
+    })
+}
+
+/// Reads the `conversations.json` of a ChatGPT data export. Conversations that
+/// cannot be read are reported as skipped rather than failing the import.
+pub fn parse_chatgpt_export(
+    data: &[u8],
+) -> Result<(Vec<ImportedConversation>, Vec<SkippedImportItem>), String> {
+    let items: Vec<Value> = serde_json::from_slice(data).map_err(|e| {
+        format!(
+            "Not a ChatGPT conversations.json, which holds a list of conversations: {}",
+            e
+        )
+    })?;
+
+    let mut conversations = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, item) in items.into_iter().enumerate() {
+        let label = item
+            .get("title")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("Conversation {}", index + 1));
+        match serde_json::from_value::<ChatGptConversation>(item) {
+            Ok(conversation) => {
+                conversations.extend(convert_conversation(conversation, &mut skipped))
+            }
+            Err(e) => skipped.push(SkippedImportItem {
+                conversation: label,
+                reason: format!("Unrecognized conversation: {}", e),
+            }),
+        }
+    }
+
+    Ok((conversations, skipped))
+}
@@ -0,0 +1,156 @@
+use crate::config_document::{parse_config_document, upgrade_config_document};
+use crate::config_overrides::{
+    default_model_override, openai_api_key_override, openai_base_url_override,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use toml_edit::DocumentMut;
+
+/// Version of the config file layout written by this build.
+pub const CURRENT_CONFIG_VERSION: i64 = 1;
+
+pub const DEFAULT_DOCUMENT_TOKEN_BUDGET: usize = 8000;
+
+pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-3-small";
+
+pub const DEFAULT_RETRIEVAL_TOP_K: usize = 5;
+
+pub const DEFAULT_BACKUP_INTERVAL_HOURS: u64 = 24;
+
+pub const DEFAULT_BACKUP_RETENTION: usize = 7;
+
+pub const DEFAULT_PROXY_PORT: u16 = 8371;
+
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Config {
+    pub config_version: i64,
+    pub openai_api_key: String,
+    /// An OpenAI-compatible API to use instead of OpenAI, e.g. `http://localhost:11434/v1`.
+    pub openai_base_url: Option<String>,
+    pub default_model: Option<String>,
+    /// Upper bound on the tokens taken by attached documents in one message.
+    pub document_token_budget: usize,
+    /// Whether the model is offered the tools in the tool registry.
+    pub enable_tools: bool,
+    /// Directories the `read_local_file` tool may read from.
+    pub tool_allowed_directories: Vec<String>,
+    /// Tools that run without asking even though they need approval.
+    pub always_allowed_tools: Vec<String>,
+    /// Model used to embed indexed documents and questions.
+    pub embedding_model: String,
+    /// How many document chunks are added to a question about indexed folders.
+    pub retrieval_top_k: usize,
+    /// Hours between automatic backups of the chat database; 0 turns them off.
+    pub backup_interval_hours: u64,
+    /// How many automatic backups are kept before the oldest is deleted.
+    pub backup_retention: usize,
+    /// Where automatic backups are written, instead of `backups` in the profile directory.
+    pub backup_directory: Option<String>,
+    /// MCP servers whose tools are offered to the model, keyed by a short name.
+    pub mcp_servers: BTreeMap<String, McpServerConfig>,
+    /// Whether other programs can send chat requests through Cuuri.
+    pub proxy_enabled: bool,
+    /// Port of the proxy server on 127.0.0.1.
+    pub proxy_port: u16,
+}
+
+/// An MCP server, either launched as a subprocess speaking over stdio
+/// (`command`) or reached over Streamable HTTP (`url`).
+#[derive(Deserialize, Serialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct McpServerConfig {
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub url: Option<String>,
+    /// Extra HTTP headers, e.g. for authentication.
+    pub headers: BTreeMap<String, String>,
+    pub enabled: bool,
+}
+
+impl Default for McpServerConfig {
+    fn default() -> Self {
+        McpServerConfig {
+            command: None,
+            args: Vec::new(),
+            env: BTreeMap::new(),
+            url: None,
+            headers: BTreeMap::new(),
+            enabled: true,
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            config_version: CURRENT_CONFIG_VERSION,
+            openai_api_key: String::new(),
+            openai_base_url: None,
+            default_model: None,
+            document_token_budget: DEFAULT_DOCUMENT_TOKEN_BUDGET,
+            enable_tools: true,
+            tool_allowed_directories: Vec::new(),
+            always_allowed_tools: Vec::new(),
+            embedding_model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            retrieval_top_k: DEFAULT_RETRIEVAL_TOP_K,
+            backup_interval_hours: DEFAULT_BACKUP_INTERVAL_HOURS,
+            backup_retention: DEFAULT_BACKUP_RETENTION,
+            backup_directory: None,
+            mcp_servers: BTreeMap::new(),
+            proxy_enabled: false,
+            proxy_port: DEFAULT_PROXY_PORT,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read config file: {}", e))?;
+        let mut document = parse_config_document(&content)?;
+
+        // Older layouts are upgraded in memory; init_config_file persists the upgrade
+        upgrade_config_document(&mut document)?;
+        Config::from_document(&document)
+    }
+
+    pub fn from_document(document: &DocumentMut) -> Result<Self, String> {
+        let table: toml::Table = toml::from_str(&document.to_string())
+            .map_err(|e| format!("Failed to parse config file: {}", e.message()))?;
+
+        toml::Value::Table(table.clone())
+            .try_into::<Config>()
+            .map_err(|e| match find_invalid_key(&table) {
+                Some((key, message)) => format!("Invalid value for `{}` in config file: {}", key, message),
+                None => format!("Failed to parse config file: {}", e.message()),
+            })
+    }
+
+    /// Replaces file values with any environment variable or command line overrides.
+    pub fn apply_overrides(&mut self) {
+        if let Some(api_key) = openai_api_key_override() {
+            self.openai_api_key = api_key.value;
+        }
+        if let Some(base_url) = openai_base_url_override() {
+            self.openai_base_url = Some(base_url.value);
+        }
+        if let Some(model) = default_model_override() {
+            self.default_model = Some(model.value);
+        }
+    }
+}
+
+// Deserializes each top-level key on its own to report which one is malformed
+fn find_invalid_key(table: &toml::Table) -> Option<(String, String)> {
+    table.iter().find_map(|(key, value)| {
+        let mut single = toml::Table::new();
+        single.insert(key.clone(), value.clone());
+        toml::Value::Table(single)
+            .try_into::<Config>()
+            .err()
+            .map(|e| (key.clone(), e.message().to_string()))
+    })
+}
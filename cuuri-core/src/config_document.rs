@@ -0,0 +1,93 @@
+use crate::config::CURRENT_CONFIG_VERSION;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use toml_edit::{DocumentMut, Item, Value};
+
+// Each step upgrades a document from the version at its index to the next one
+const UPGRADES: &[fn(&mut DocumentMut)] = &[upgrade_v0_to_v1];
+
+// Version 0 is every file written before `config_version` existed
+fn upgrade_v0_to_v1(document: &mut DocumentMut) {
+    set_config_value(document, "config_version", 1);
+}
+
+/// Sets a top-level key, keeping any comment attached to the previous value.
+pub fn set_config_value(document: &mut DocumentMut, key: &str, new_value: impl Into<Value>) {
+    let mut new_value = new_value.into();
+    if let Some(existing) = document.get(key).and_then(|item| item.as_value()) {
+        *new_value.decor_mut() = existing.decor().clone();
+    }
+    document[key] = Item::Value(new_value);
+}
+
+pub fn parse_config_document(content: &str) -> Result<DocumentMut, String> {
+    content
+        .parse::<DocumentMut>()
+        .map_err(|e| format!("Failed to parse config file: {}", e))
+}
+
+pub fn read_config_document(path: &Path) -> Result<DocumentMut, String> {
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read config file: {}", e))?;
+    parse_config_document(&content)
+}
+
+/// Writes the document to a temporary file next to `path` and renames it into
+/// place, so a crash never leaves a half-written config file behind.
+pub fn write_config_document(path: &Path, document: &DocumentMut) -> Result<(), String> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let write_temp = || -> std::io::Result<()> {
+        let mut file = fs::File::create(&temp_path)?;
+        file.write_all(document.to_string().as_bytes())?;
+        file.sync_all()
+    };
+
+    if let Err(e) = write_temp().and_then(|_| fs::rename(&temp_path, path)) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Failed to write to config file: {}", e));
+    }
+
+    Ok(())
+}
+
+/// Brings a document up to `CURRENT_CONFIG_VERSION`, returning whether it changed.
+pub fn upgrade_config_document(document: &mut DocumentMut) -> Result<bool, String> {
+    let version = match document.get("config_version") {
+        None => 0,
+        Some(item) => item.as_integer().ok_or_else(|| {
+            "Invalid value for `config_version` in config file: expected an integer".to_string()
+        })?,
+    };
+
+    if version > CURRENT_CONFIG_VERSION {
+        return Err(format!(
+            "Config file version {} was written by a newer version of Cuuri (supported: {})",
+            version, CURRENT_CONFIG_VERSION
+        ));
+    }
+    if version < 0 {
+        return Err(format!(
+            "Invalid value for `config_version` in config file: {}",
+            version
+        ));
+    }
+
+    for upgrade in &UPGRADES[version as usize..] {
+        upgrade(document);
+    }
+
+    Ok(version < CURRENT_CONFIG_VERSION)
+}
+
+/// Upgrades the config file on disk, keeping comments, ordering and unknown keys.
+pub fn upgrade_config_file(path: &Path) -> Result<(), String> {
+    let mut document = read_config_document(path)?;
+    if upgrade_config_document(&mut document)? {
+        write_config_document(path, &document)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use serde::Serialize;
+use std::env;
+use std::sync::OnceLock;
+
+pub const ENV_CONFIG: &str = "CUURI_CONFIG";
+pub const ENV_DATA_DIR: &str = "CUURI_DATA_DIR";
+pub const ENV_DB_PASSPHRASE: &str = "CUURI_DB_PASSPHRASE";
+pub const ENV_DEFAULT_MODEL: &str = "CUURI_DEFAULT_MODEL";
+pub const ENV_OPENAI_API_KEY: &str = "OPENAI_API_KEY";
+pub const ENV_OPENAI_BASE_URL: &str = "CUURI_OPENAI_BASE_URL";
+pub const ENV_PROFILE: &str = "CUURI_PROFILE";
+
+/// Where an effective configuration value came from.
+///
+/// Variants are listed from the highest to the lowest precedence.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    CommandLine,
+    Environment,
+    ConfigFile,
+    Default,
+}
+
+pub struct Resolved<T> {
+    pub value: T,
+    pub source: ConfigSource,
+}
+
+#[derive(Default)]
+pub struct CommandLineArgs {
+    pub config: Option<String>,
+    pub data_dir: Option<String>,
+    pub default_model: Option<String>,
+    pub profile: Option<String>,
+}
+
+impl CommandLineArgs {
+    pub fn parse<I: Iterator<Item = String>>(mut args: I) -> Self {
+        let mut parsed = CommandLineArgs::default();
+
+        while let Some(arg) = args.next() {
+            // Accept both `--flag value` and `--flag=value`
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+
+            let target = match flag.as_str() {
+                "--config" => &mut parsed.config,
+                "--data-dir" => &mut parsed.data_dir,
+                "--default-model" => &mut parsed.default_model,
+                "--profile" => &mut parsed.profile,
+                // Unknown arguments (e.g. ones added by the OS launcher) are ignored
+                _ => continue,
+            };
+
+            let value = match inline_value {
+                Some(value) => Some(value),
+                None => args.next(),
+            };
+            *target = value.filter(|v| !v.is_empty());
+        }
+
+        parsed
+    }
+}
+
+static COMMAND_LINE_ARGS: OnceLock<CommandLineArgs> = OnceLock::new();
+
+pub fn command_line_args() -> &'static CommandLineArgs {
+    COMMAND_LINE_ARGS.get_or_init(|| CommandLineArgs::parse(env::args().skip(1)))
+}
+
+fn env_value(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+/// Looks up an override, preferring the command line over the environment.
+pub fn lookup_override(cli_value: Option<&String>, env_name: &str) -> Option<Resolved<String>> {
+    if let Some(value) = cli_value {
+        return Some(Resolved {
+            value: value.clone(),
+            source: ConfigSource::CommandLine,
+        });
+    }
+
+    env_value(env_name).map(|value| Resolved {
+        value,
+        source: ConfigSource::Environment,
+    })
+}
+
+pub fn config_path_override() -> Option<Resolved<String>> {
+    lookup_override(command_line_args().config.as_ref(), ENV_CONFIG)
+}
+
+pub fn data_dir_override() -> Option<Resolved<String>> {
+    lookup_override(command_line_args().data_dir.as_ref(), ENV_DATA_DIR)
+}
+
+pub fn default_model_override() -> Option<Resolved<String>> {
+    lookup_override(command_line_args().default_model.as_ref(), ENV_DEFAULT_MODEL)
+}
+
+pub fn profile_override() -> Option<Resolved<String>> {
+    lookup_override(command_line_args().profile.as_ref(), ENV_PROFILE)
+}
+
+// The API key is deliberately not accepted on the command line, where it
+// would be visible to other users through the process list.
+pub fn openai_api_key_override() -> Option<Resolved<String>> {
+    lookup_override(None, ENV_OPENAI_API_KEY)
+}
+
+pub fn openai_base_url_override() -> Option<Resolved<String>> {
+    lookup_override(None, ENV_OPENAI_BASE_URL)
+}
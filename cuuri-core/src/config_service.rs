@@ -0,0 +1,258 @@
+use crate::app_type::{ConfigValueSource, ProfileInfo};
+use crate::config::Config;
+use crate::config_document::{
+    read_config_document, upgrade_config_document, write_config_document,
+};
+use crate::config_overrides::{
+    default_model_override, openai_api_key_override, openai_base_url_override, ConfigSource,
+};
+use crate::cuuri::Cuuri;
+use crate::db_pool::DatabaseState;
+use crate::get_config::get_config;
+use crate::get_config_path::{get_config_path, resolve_config_path};
+use crate::get_data_dir::resolve_data_dir;
+use crate::init_config_file::{init_config_file, init_config_file_at};
+use crate::open_chat_database::{open_chat_database, recover_interrupted_chats};
+use crate::profile::{
+    get_active_profile_name, get_profile_dir_for, list_profile_names, replace_switched_profile,
+    resolve_active_profile, set_active_profile, validate_profile_name,
+};
+use crate::settings::{mask_secret, Settings, SettingsPatch};
+use std::fs;
+
+fn entry(key: &str, value: Option<String>, source: ConfigSource) -> ConfigValueSource {
+    ConfigValueSource {
+        key: key.to_string(),
+        value,
+        source,
+    }
+}
+
+fn profile_info(name: String) -> Result<ProfileInfo, String> {
+    let path = get_profile_dir_for(&name).map_err(|e| e.to_string())?;
+    Ok(ProfileInfo {
+        active: name == get_active_profile_name(),
+        path: path.to_string_lossy().to_string(),
+        name,
+    })
+}
+
+impl Cuuri {
+    /// Re-reads the config file, returning the new configuration if it
+    /// changed. An invalid file keeps the previous configuration in place.
+    pub fn reload_config(&self) -> Result<Option<Config>, String> {
+        let config = get_config()?;
+        if !self.config_state().replace(config.clone()) {
+            return Ok(None);
+        }
+        self.wake_chat_embedder();
+        Ok(Some(config))
+    }
+
+    /// The values stored in the config file, not environment or command line overrides.
+    pub fn settings(&self) -> Result<Settings, String> {
+        let config_path = get_config_path().map_err(|e| e.to_string())?;
+        let config_path_str = config_path
+            .to_str()
+            .ok_or("Failed to convert config path to string".to_string())?;
+
+        let config = Config::from_file(config_path_str)
+            .map_err(|e| format!("Failed to load configuration: {}", e))?;
+
+        Ok(Settings::from(config))
+    }
+
+    /// Writes the patch to the config file and makes it effective.
+    pub fn update_settings(&self, patch: &SettingsPatch) -> Result<Settings, String> {
+        let config_path = get_config_path().map_err(|e| e.to_string())?;
+
+        let mut document = read_config_document(&config_path)
+            .map_err(|e| format!("Failed to load configuration: {}", e))?;
+        upgrade_config_document(&mut document)?;
+
+        patch.apply_to(&mut document)?;
+
+        // Validate before touching the file so a bad patch leaves it unchanged
+        let config = Config::from_document(&document)?;
+        write_config_document(&config_path, &document)?;
+
+        // Update the in-memory copy right away instead of waiting for the file watcher
+        let mut effective = config.clone();
+        effective.apply_overrides();
+        if self.config_state().replace(effective) {
+            self.wake_chat_embedder();
+        }
+
+        Ok(Settings::from(config))
+    }
+
+    /// Lets `tool_name` run without asking for approval from now on. Returns
+    /// the new settings, or `None` if the tool was allowed already.
+    pub fn always_allow_tool(&self, tool_name: String) -> Result<Option<Settings>, String> {
+        let mut allowed = self.config().always_allowed_tools;
+        if allowed.contains(&tool_name) {
+            return Ok(None);
+        }
+        allowed.push(tool_name);
+        let patch = SettingsPatch {
+            always_allowed_tools: Some(allowed),
+            ..Default::default()
+        };
+        self.update_settings(&patch).map(Some)
+    }
+
+    pub fn set_openai_api_key(&self, api_key: String) -> Result<Settings, String> {
+        let patch = SettingsPatch {
+            openai_api_key: Some(api_key),
+            ..Default::default()
+        };
+        self.update_settings(&patch)
+    }
+
+    /// Where the data directory, profile, config file, API key, API base URL
+    /// and default model come from.
+    pub fn config_sources(&self) -> Result<Vec<ConfigValueSource>, String> {
+        let data_dir = resolve_data_dir().map_err(|e| e.to_string())?;
+        let profile = resolve_active_profile();
+        let config_path = resolve_config_path().map_err(|e| e.to_string())?;
+
+        // Read the file without overrides so that its own values can be told apart
+        let file_config = config_path
+            .value
+            .to_str()
+            .and_then(|path| Config::from_file(path).ok());
+
+        let api_key = match openai_api_key_override() {
+            Some(resolved) => (Some(resolved.value), resolved.source),
+            None => match file_config.as_ref().map(|c| c.openai_api_key.clone()) {
+                Some(key) if !key.is_empty() => (Some(key), ConfigSource::ConfigFile),
+                _ => (None, ConfigSource::Default),
+            },
+        };
+
+        let base_url = match openai_base_url_override() {
+            Some(resolved) => (Some(resolved.value), resolved.source),
+            None => match file_config.as_ref().and_then(|c| c.openai_base_url.clone()) {
+                Some(base_url) => (Some(base_url), ConfigSource::ConfigFile),
+                None => (None, ConfigSource::Default),
+            },
+        };
+
+        let default_model = match default_model_override() {
+            Some(resolved) => (Some(resolved.value), resolved.source),
+            None => match file_config.as_ref().and_then(|c| c.default_model.clone()) {
+                Some(model) => (Some(model), ConfigSource::ConfigFile),
+                None => (None, ConfigSource::Default),
+            },
+        };
+
+        Ok(vec![
+            entry(
+                "data_dir",
+                Some(data_dir.value.to_string_lossy().to_string()),
+                data_dir.source,
+            ),
+            entry("profile", Some(profile.value), profile.source),
+            entry(
+                "config",
+                Some(config_path.value.to_string_lossy().to_string()),
+                config_path.source,
+            ),
+            entry(
+                "openai_api_key",
+                api_key.0.map(|key| mask_secret(&key)),
+                api_key.1,
+            ),
+            entry("openai_base_url", base_url.0, base_url.1),
+            entry("default_model", default_model.0, default_model.1),
+        ])
+    }
+
+    pub fn list_profiles(&self) -> Result<Vec<ProfileInfo>, String> {
+        list_profile_names()
+            .map_err(|e| e.to_string())?
+            .into_iter()
+            .map(profile_info)
+            .collect()
+    }
+
+    pub fn active_profile(&self) -> Result<ProfileInfo, String> {
+        profile_info(get_active_profile_name())
+    }
+
+    pub fn create_profile(&self, name: String) -> Result<ProfileInfo, String> {
+        validate_profile_name(&name)?;
+
+        let existing = list_profile_names().map_err(|e| e.to_string())?;
+        if existing.contains(&name) {
+            return Err(format!("Profile `{}` already exists", name));
+        }
+
+        let profile_dir = get_profile_dir_for(&name).map_err(|e| e.to_string())?;
+        fs::create_dir_all(&profile_dir)
+            .map_err(|e| format!("Failed to create profile directory: {}", e))?;
+
+        // The database is created and migrated when the profile is first used
+        init_config_file_at(&profile_dir.join("config.toml"))
+            .map_err(|e| format!("Failed to initialize config file: {}", e))?;
+
+        profile_info(name)
+    }
+
+    /// Makes `name` the active profile and opens its database. The switch is
+    /// only saved once the database opened (or is waiting for its passphrase),
+    /// so a failure leaves the previous profile active. Its configuration is
+    /// picked up by `reload_config`. Refused while answers are being received,
+    /// as opening the database fails the answers it finds pending.
+    pub async fn switch_profile(&self, name: &str) -> Result<ProfileInfo, String> {
+        if !self.streams().is_empty() {
+            return Err(
+                "Wait for the answers being received to finish before switching profiles"
+                    .to_string(),
+            );
+        }
+
+        let existing = list_profile_names().map_err(|e| e.to_string())?;
+        if !existing.iter().any(|profile| profile == name) {
+            return Err(format!("Profile `{}` does not exist", name));
+        }
+
+        let previous = replace_switched_profile(Some(name.to_string()));
+        let state = match open_profile_database().await {
+            Ok(state) => state,
+            Err(e) => {
+                replace_switched_profile(previous);
+                return Err(e);
+            }
+        };
+        if let Err(e) = set_active_profile(name) {
+            replace_switched_profile(previous);
+            return Err(format!("Failed to switch profile: {}", e));
+        }
+
+        self.db().set(state);
+        self.wake_chat_embedder();
+        profile_info(name.to_string())
+    }
+}
+
+/// Opens the database of the active profile with that profile's config.
+async fn open_profile_database() -> Result<DatabaseState, String> {
+    init_config_file().map_err(|e| format!("Failed to initialize config file: {}", e))?;
+    let config = get_config()?;
+    let state = tokio::task::spawn_blocking(move || {
+        let state = open_chat_database(&config, None);
+        // Answers left pending there were cut off when the app last used it
+        if let DatabaseState::Open { pool, .. } = &state {
+            recover_interrupted_chats(pool);
+        }
+        state
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    match state {
+        DatabaseState::Failed { message, .. } => Err(message),
+        state => Ok(state),
+    }
+}
@@ -0,0 +1,33 @@
+use crate::config::Config;
+use std::sync::RwLock;
+
+/// The effective configuration, refreshed when the file changes.
+pub struct ConfigState {
+    config: RwLock<Config>,
+}
+
+impl ConfigState {
+    pub fn new(config: Config) -> Self {
+        ConfigState {
+            config: RwLock::new(config),
+        }
+    }
+
+    pub fn get(&self) -> Config {
+        // A poisoned lock still holds the last configuration that was stored
+        self.config
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Stores a new configuration, returning whether it differs from the previous one.
+    pub fn replace(&self, config: Config) -> bool {
+        let mut current = self.config.write().unwrap_or_else(|e| e.into_inner());
+        if *current == config {
+            return false;
+        }
+        *current = config;
+        true
+    }
+}
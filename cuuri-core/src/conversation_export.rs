@@ -0,0 +1,654 @@
+use crate::attachments::{load_attachments, IMAGE_MIME_TYPES, KIND_IMAGE};
+use crate::models::ChatHistory;
+use crate::schema::{chat_histories, imported_sessions};
+use crate::tool_calls::load_tool_calls;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Value of `format` in JSON exports, so importers can recognize them.
+pub const EXPORT_FORMAT_NAME: &str = "cuuri-export";
+/// Version of the JSON export layout written by this build.
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+/// The JSON export: every stored detail of the exported sessions, including
+/// attachment bytes, so that importing it recreates them exactly.
+#[derive(Serialize, Deserialize)]
+pub struct ExportDocument {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    pub sessions: Vec<ExportedSession>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExportedSession {
+    pub session_id: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Where the session was imported from, if it was.
+    #[serde(default)]
+    pub origin: Option<ExportedOrigin>,
+    pub entries: Vec<ExportedEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExportedOrigin {
+    pub source: String,
+    pub source_id: String,
+    pub source_updated_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExportedEntry {
+    pub question: String,
+    pub answer: String,
+    pub created_at: String,
+    pub status: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<ExportedAttachment>,
+    #[serde(default)]
+    pub tool_calls: Vec<ExportedToolCall>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExportedAttachment {
+    pub kind: String,
+    pub mime_type: String,
+    pub file_name: Option<String>,
+    pub content_hash: String,
+    pub extracted_text: Option<String>,
+    pub created_at: String,
+    /// The file contents, base64 encoded.
+    pub data: String,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExportedToolCall {
+    pub round: i32,
+    pub call_id: String,
+    pub name: String,
+    pub arguments: String,
+    pub result: String,
+    pub is_error: bool,
+    pub created_at: String,
+}
+
+/// Loads the given sessions, or all sessions, ordered by their first question.
+pub fn load_sessions(
+    conn: &mut SqliteConnection,
+    session_ids: Option<&[String]>,
+) -> Result<Vec<ExportedSession>, String> {
+    let mut query = chat_histories::table
+        .order((chat_histories::created_at.asc(), chat_histories::id.asc()))
+        .into_boxed();
+    if let Some(session_ids) = session_ids {
+        query = query.filter(chat_histories::session_id.eq_any(session_ids));
+    }
+    let chats = query.load::<ChatHistory>(conn).map_err(|e| e.to_string())?;
+
+    let chat_ids: Vec<i32> = chats.iter().map(|chat| chat.id).collect();
+    let mut attachments = load_attachments(conn, &chat_ids)?;
+    let mut tool_calls = load_tool_calls(conn, &chat_ids)?;
+    let mut origins: HashMap<String, (Option<String>, ExportedOrigin)> = imported_sessions::table
+        .select((
+            imported_sessions::session_id,
+            imported_sessions::title,
+            imported_sessions::source,
+            imported_sessions::source_id,
+            imported_sessions::source_updated_at,
+        ))
+        .load::<(String, Option<String>, String, String, NaiveDateTime)>(conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(
+            |(session_id, title, source, source_id, source_updated_at)| {
+                let origin = ExportedOrigin {
+                    source,
+                    source_id,
+                    source_updated_at: source_updated_at.to_string(),
+                };
+                (session_id, (title, origin))
+            },
+        )
+        .collect();
+
+    let mut sessions: Vec<ExportedSession> = Vec::new();
+    for chat in chats {
+        let entry = ExportedEntry {
+            question: chat.question,
+            answer: chat.answer,
+            created_at: chat.created_at.to_string(),
+            status: chat.status,
+            model: chat.model,
+            attachments: attachments
+                .remove(&chat.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|loaded| ExportedAttachment {
+                    kind: loaded.attachment.kind,
+                    mime_type: loaded.attachment.mime_type,
+                    file_name: loaded.attachment.file_name,
+                    content_hash: loaded.attachment.content_hash,
+                    extracted_text: loaded.attachment.extracted_text,
+                    created_at: loaded.attachment.created_at.to_string(),
+                    data: STANDARD.encode(&loaded.data),
+                })
+                .collect(),
+            tool_calls: tool_calls
+                .remove(&chat.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|record| ExportedToolCall {
+                    round: record.round,
+                    call_id: record.call_id,
+                    name: record.name,
+                    arguments: record.arguments,
+                    result: record.result,
+                    is_error: record.is_error,
+                    created_at: record.created_at.to_string(),
+                })
+                .collect(),
+        };
+
+        match sessions
+            .iter_mut()
+            .find(|session| session.session_id == chat.session_id)
+        {
+            Some(session) => session.entries.push(entry),
+            None => {
+                let (title, origin) = match origins.remove(&chat.session_id) {
+                    Some((title, origin)) => (title, Some(origin)),
+                    None => (None, None),
+                };
+                sessions.push(ExportedSession {
+                    session_id: chat.session_id,
+                    title,
+                    origin,
+                    entries: vec![entry],
+                })
+            }
+        }
+    }
+
+    Ok(sessions)
+}
+
+pub fn render_json(sessions: Vec<ExportedSession>) -> Result<String, String> {
+    let document = ExportDocument {
+        format: EXPORT_FORMAT_NAME.to_string(),
+        version: EXPORT_FORMAT_VERSION,
+        exported_at: Utc::now().naive_utc().to_string(),
+        sessions,
+    };
+    serde_json::to_string_pretty(&document).map_err(|e| e.to_string())
+}
+
+/// The file extension of an image type Cuuri stores. Any other type, e.g.
+/// from an edited import, is not embedded as it could break out of the link.
+fn image_extension(mime_type: &str) -> Option<&str> {
+    IMAGE_MIME_TYPES
+        .contains(&mime_type)
+        .then(|| &mime_type["image/".len()..])
+}
+
+fn data_url(attachment: &ExportedAttachment) -> Option<String> {
+    image_extension(&attachment.mime_type)?;
+    Some(format!(
+        "data:{};base64,{}",
+        attachment.mime_type, attachment.data
+    ))
+}
+
+/// Where the Markdown export links an image attachment: a data URL, or a file
+/// written to `image_dir`. `None` for anything that is not a known image.
+fn image_link(
+    attachment: &ExportedAttachment,
+    image_dir: Option<(&Path, &str)>,
+) -> Result<Option<String>, String> {
+    if attachment.kind != KIND_IMAGE {
+        return Ok(None);
+    }
+    let Some((dir, link_prefix)) = image_dir else {
+        return Ok(data_url(attachment));
+    };
+    let Some(extension) = image_extension(&attachment.mime_type) else {
+        return Ok(None);
+    };
+
+    let file_name = format!("{}.{}", attachment.content_hash, extension);
+    let data = STANDARD
+        .decode(&attachment.data)
+        .map_err(|e| e.to_string())?;
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    fs::write(dir.join(&file_name), data).map_err(|e| format!("Failed to write image: {}", e))?;
+    Ok(Some(format!("{}/{}", link_prefix, file_name)))
+}
+
+fn session_heading(session: &ExportedSession) -> String {
+    session
+        .title
+        .clone()
+        .unwrap_or_else(|| format!("Conversation {}", session.session_id))
+}
+
+fn assistant_label(entry: &ExportedEntry) -> String {
+    match &entry.model {
+        Some(model) => format!("Assistant ({})", model),
+        None => "Assistant".to_string(),
+    }
+}
+
+fn status_note(status: &str) -> Option<&'static str> {
+    match status {
+        "pending" => Some("The answer was still being received when this was exported."),
+        "failed" => Some("The request failed; the answer may be incomplete."),
+        "cancelled" => Some("The answer was stopped before it finished."),
+        _ => None,
+    }
+}
+
+/// Renders a session as Markdown. Images are embedded as data URLs when
+/// `image_dir` is `None`; otherwise they are written to the given directory
+/// and linked through the given relative path.
+pub fn render_markdown(
+    session: &ExportedSession,
+    image_dir: Option<(&Path, &str)>,
+) -> Result<String, String> {
+    let mut out = format!("# {}\n", session_heading(session));
+
+    for entry in &session.entries {
+        out.push_str(&format!(
+            "\n## User\n\n_{}_\n\n{}\n",
+            entry.created_at, entry.question
+        ));
+
+        for attachment in &entry.attachments {
+            if let Some(target) = image_link(attachment, image_dir)? {
+                out.push_str(&format!("\n![image]({})\n", target));
+            } else {
+                out.push_str(&format!(
+                    "\n📎 {}\n",
+                    attachment.file_name.as_deref().unwrap_or("attachment")
+                ));
+            }
+        }
+
+        for call in &entry.tool_calls {
+            out.push_str(&format!(
+                "\n### Tool call: {}\n\n```json\n{}\n```\n\n{}\n\n```\n{}\n```\n",
+                call.name,
+                call.arguments,
+                if call.is_error { "Error:" } else { "Result:" },
+                call.result
+            ));
+        }
+
+        out.push_str(&format!(
+            "\n## {}\n\n{}\n",
+            assistant_label(entry),
+            entry.answer
+        ));
+        if let Some(note) = status_note(&entry.status) {
+            out.push_str(&format!("\n> {}\n", note));
+        }
+    }
+
+    Ok(out)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Anything else, e.g. `javascript:`, could run code or load content when opened
+fn is_safe_link(url: &str) -> bool {
+    let url = url
+        .trim_start_matches(|c: char| c <= ' ')
+        .to_ascii_lowercase();
+    url.starts_with('#')
+        || ["http://", "https://", "mailto:"]
+            .iter()
+            .any(|scheme| url.starts_with(scheme))
+}
+
+fn is_inline_image(url: &str) -> bool {
+    url.trim_start_matches(|c: char| c <= ' ')
+        .to_ascii_lowercase()
+        .starts_with("data:image/")
+}
+
+/// What became of a link or image, so its end can follow suit.
+enum LinkOutput {
+    Kept,
+    Dropped,
+    AsLink,
+}
+
+fn markdown_to_html(markdown: &str) -> String {
+    use pulldown_cmark::{Event, Tag, TagEnd};
+
+    let options = pulldown_cmark::Options::ENABLE_TABLES
+        | pulldown_cmark::Options::ENABLE_STRIKETHROUGH
+        | pulldown_cmark::Options::ENABLE_TASKLISTS;
+    // Links and images nest, e.g. an image inside a link
+    let mut open_links = Vec::new();
+    let parser = pulldown_cmark::Parser::new_ext(markdown, options).filter_map(move |event| {
+        match event {
+            // Answers are shown as text, never as live markup
+            Event::Html(html) | Event::InlineHtml(html) => Some(Event::Text(html)),
+            // Unsafe links keep only their text
+            Event::Start(Tag::Link { ref dest_url, .. }) => {
+                let safe = is_safe_link(dest_url);
+                open_links.push(if safe {
+                    LinkOutput::Kept
+                } else {
+                    LinkOutput::Dropped
+                });
+                safe.then_some(event)
+            }
+            // The file is opened offline, so only inlined images are shown;
+            // others become a link to the image, with the alt text
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => {
+                if is_inline_image(&dest_url) {
+                    open_links.push(LinkOutput::Kept);
+                    Some(Event::Start(Tag::Image {
+                        link_type,
+                        dest_url,
+                        title,
+                        id,
+                    }))
+                } else if is_safe_link(&dest_url) {
+                    open_links.push(LinkOutput::AsLink);
+                    Some(Event::Start(Tag::Link {
+                        link_type,
+                        dest_url,
+                        title,
+                        id,
+                    }))
+                } else {
+                    open_links.push(LinkOutput::Dropped);
+                    None
+                }
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => match open_links.pop() {
+                Some(LinkOutput::Dropped) => None,
+                Some(LinkOutput::AsLink) => Some(Event::End(TagEnd::Link)),
+                _ => Some(event),
+            },
+            event => Some(event),
+        }
+    });
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, parser);
+    html
+}
+
+const HTML_STYLE: &str = "body{font-family:system-ui,sans-serif;max-width:48rem;margin:2rem auto;padding:0 1rem;line-height:1.5;color:#222}\
+.message{border-radius:8px;padding:0.75rem 1rem;margin:1rem 0}\
+.user{background:#eef3fb}.assistant{background:#f6f6f6}\
+.role{font-weight:bold;font-size:0.9rem}.time{color:#777;font-size:0.8rem;margin-left:0.5rem}\
+.question{white-space:pre-wrap}.note{color:#a15c00;font-style:italic}\
+img{max-width:100%}pre{background:#272822;color:#f8f8f2;padding:0.75rem;overflow-x:auto;border-radius:6px}\
+code{font-family:ui-monospace,monospace}details{margin:0.5rem 0}";
+
+/// Renders a session as a single HTML file with images inlined.
+pub fn render_html(session: &ExportedSession) -> String {
+    let mut body = String::new();
+
+    for entry in &session.entries {
+        body.push_str(&format!(
+            "<div class=\"message user\"><div><span class=\"role\">User</span><span class=\"time\">{}</span></div><div class=\"question\">{}</div>",
+            escape_html(&entry.created_at),
+            escape_html(&entry.question)
+        ));
+        for attachment in &entry.attachments {
+            let image = match attachment.kind.as_str() {
+                KIND_IMAGE => data_url(attachment),
+                _ => None,
+            };
+            if let Some(image) = image {
+                body.push_str(&format!("<p><img src=\"{}\" alt=\"image\"></p>", image));
+            } else {
+                body.push_str(&format!(
+                    "<p>📎 {}</p>",
+                    escape_html(attachment.file_name.as_deref().unwrap_or("attachment"))
+                ));
+            }
+        }
+        body.push_str("</div>");
+
+        body.push_str(&format!(
+            "<div class=\"message assistant\"><div class=\"role\">{}</div>",
+            escape_html(&assistant_label(entry))
+        ));
+        for call in &entry.tool_calls {
+            body.push_str(&format!(
+                "<details><summary>Tool call: {}</summary><pre><code>{}</code></pre><pre><code>{}</code></pre></details>",
+                escape_html(&call.name),
+                escape_html(&call.arguments),
+                escape_html(&call.result)
+            ));
+        }
+        body.push_str(&markdown_to_html(&entry.answer));
+        if let Some(note) = status_note(&entry.status) {
+            body.push_str(&format!("<p class=\"note\">{}</p>", note));
+        }
+        body.push_str("</div>");
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n{}\n</body>\n</html>\n",
+        escape_html(&session_heading(session)),
+        HTML_STYLE,
+        escape_html(&session_heading(session)),
+        body
+    )
+}
+
+/// A file name derived from the session id, safe on every platform.
+pub fn session_file_stem(session_id: &str) -> String {
+    session_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Renders one session as a single self-contained document.
+pub fn render_session(session: ExportedSession, format: ExportFormat) -> Result<String, String> {
+    match format {
+        ExportFormat::Json => render_json(vec![session]),
+        ExportFormat::Html => Ok(render_html(&session)),
+        ExportFormat::Markdown => render_markdown(&session, None),
+    }
+}
+
+/// Writes one session to `path` in the given format.
+pub fn write_session_export(
+    session: ExportedSession,
+    format: ExportFormat,
+    path: &Path,
+    embed_images: bool,
+) -> Result<(), String> {
+    let content = match format {
+        ExportFormat::Markdown if !embed_images => {
+            // Images go next to the file, e.g. notes.md and notes_files/
+            let base_dir = path.parent().unwrap_or(Path::new("."));
+            let stem = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_else(|| session_file_stem(&session.session_id));
+            let link_prefix = format!("{}_files", stem);
+            let image_dir = base_dir.join(&link_prefix);
+            render_markdown(&session, Some((&image_dir, &link_prefix)))?
+        }
+        _ => render_session(session, format)?,
+    };
+
+    fs::write(path, content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+pub fn load_session(
+    conn: &mut SqliteConnection,
+    session_id: &str,
+) -> Result<ExportedSession, String> {
+    load_sessions(conn, Some(&[session_id.to_string()]))?
+        .pop()
+        .ok_or_else(|| format!("Session `{}` does not exist", session_id))
+}
+
+/// Loads one session and writes it to `path` in the given format.
+pub fn export_session_file(
+    conn: &mut SqliteConnection,
+    session_id: &str,
+    format: ExportFormat,
+    path: &Path,
+    embed_images: bool,
+) -> Result<(), String> {
+    let session = load_session(conn, session_id)?;
+    write_session_export(session, format, path, embed_images)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_web_and_mail_links() {
+        let html = markdown_to_html("[docs](https://example.com) [mail](mailto:a@example.com)");
+        assert!(
+            html.contains("<a href=\"https://example.com\">docs</a>"),
+            "{}",
+            html
+        );
+        assert!(
+            html.contains("<a href=\"mailto:a@example.com\">mail</a>"),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn drops_script_links_but_keeps_their_text() {
+        for url in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "vbscript:x",
+            "file:///etc/passwd",
+        ] {
+            let html = markdown_to_html(&format!("[click <b>me</b>]({})", url));
+            assert!(!html.contains("<a"), "{}", html);
+            assert!(html.contains("click"), "{}", html);
+        }
+        let autolink = markdown_to_html("<javascript:alert(1)>");
+        assert!(!autolink.contains("<a"), "{}", autolink);
+    }
+
+    #[test]
+    fn remote_images_become_links() {
+        let html = markdown_to_html("![a chart](https://example.com/chart.png)");
+        assert!(!html.contains("<img"), "{}", html);
+        assert!(
+            html.contains("<a href=\"https://example.com/chart.png\">a chart</a>"),
+            "{}",
+            html
+        );
+
+        let inline = markdown_to_html("![dot](data:image/png;base64,iVBORw0KGgo=)");
+        assert!(
+            inline.contains("<img src=\"data:image/png;base64,iVBORw0KGgo=\""),
+            "{}",
+            inline
+        );
+
+        let script = markdown_to_html("![x](javascript:alert(1))");
+        assert!(
+            !script.contains("<img") && !script.contains("<a"),
+            "{}",
+            script
+        );
+    }
+
+    #[test]
+    fn handles_images_inside_links() {
+        let html = markdown_to_html("[![logo](https://example.com/logo.png)](javascript:alert(1))");
+        assert!(!html.contains("javascript"), "{}", html);
+        assert_eq!(
+            html.matches("<a").count(),
+            html.matches("</a>").count(),
+            "{}",
+            html
+        );
+    }
+
+    #[test]
+    fn images_of_unknown_types_are_not_embedded() {
+        let session = ExportedSession {
+            session_id: "session".to_string(),
+            title: None,
+            origin: None,
+            entries: vec![ExportedEntry {
+                question: "Look".to_string(),
+                answer: "Nice".to_string(),
+                created_at: "2026-01-01 00:00:00".to_string(),
+                status: "complete".to_string(),
+                model: None,
+                attachments: vec![ExportedAttachment {
+                    kind: KIND_IMAGE.to_string(),
+                    mime_type: "image/png\" onerror=\"alert(1)".to_string(),
+                    file_name: None,
+                    content_hash: "hash".to_string(),
+                    extracted_text: None,
+                    created_at: "2026-01-01 00:00:00".to_string(),
+                    data: "iVBORw0KGgo=".to_string(),
+                }],
+                tool_calls: Vec::new(),
+            }],
+        };
+
+        let html = render_html(&session);
+        assert!(!html.contains("onerror"), "{}", html);
+        assert!(!html.contains("<img"), "{}", html);
+        let markdown = render_markdown(&session, None).expect("render Markdown");
+        assert!(!markdown.contains("onerror"), "{}", markdown);
+    }
+}
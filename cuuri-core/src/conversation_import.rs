@@ -0,0 +1,237 @@
+use crate::app_type::{ImportReport, SkippedImportItem};
+use crate::attachments::{attachment_dir, attachment_hash, store_attachment_blob};
+use crate::chat_record::{insert_chat, ChatStatus};
+use crate::db_pool::{in_rolled_back_transaction, in_transaction};
+use crate::models::{NewAttachment, NewChatHistory, NewImportedSession, NewToolCallRecord};
+use crate::schema::{attachments, imported_sessions, tool_calls};
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+pub struct ImportedAttachment {
+    pub kind: String,
+    pub mime_type: String,
+    pub file_name: Option<String>,
+    pub extracted_text: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub data: Vec<u8>,
+}
+
+pub struct ImportedToolCall {
+    pub round: i32,
+    pub call_id: String,
+    pub name: String,
+    pub arguments: String,
+    pub result: String,
+    pub is_error: bool,
+    pub created_at: NaiveDateTime,
+}
+
+/// A question and its answer read from an export.
+pub struct ImportedChat {
+    pub question: String,
+    pub answer: String,
+    pub created_at: NaiveDateTime,
+    pub status: ChatStatus,
+    pub model: Option<String>,
+    pub attachments: Vec<ImportedAttachment>,
+    pub tool_calls: Vec<ImportedToolCall>,
+}
+
+impl ImportedChat {
+    pub fn new(question: String, created_at: NaiveDateTime) -> Self {
+        ImportedChat {
+            question,
+            answer: String::new(),
+            created_at,
+            // Stays failed unless an answer follows
+            status: ChatStatus::Failed,
+            model: None,
+            attachments: Vec::new(),
+            tool_calls: Vec::new(),
+        }
+    }
+}
+
+/// A conversation read from another app's export.
+pub struct ImportedConversation {
+    /// The app the conversation comes from, e.g. `chatgpt`.
+    pub source: String,
+    /// The conversation's id in that app.
+    pub source_id: String,
+    pub title: Option<String>,
+    pub updated_at: NaiveDateTime,
+    pub chats: Vec<ImportedChat>,
+}
+
+/// Stores chats with their attachments and tool calls. In a dry run the
+/// attachment files are not written; the caller rolls back the rest.
+pub fn save_imported_chats(
+    conn: &mut SqliteConnection,
+    session_id: &String,
+    chats: &[ImportedChat],
+    dry_run: bool,
+) -> Result<(), String> {
+    for chat in chats {
+        let chat_id = insert_chat(
+            conn,
+            &NewChatHistory {
+                session_id,
+                question: &chat.question,
+                answer: &chat.answer,
+                created_at: chat.created_at,
+                status: chat.status.as_str(),
+                model: chat.model.as_deref(),
+            },
+        )?;
+
+        for attachment in &chat.attachments {
+            let content_hash = if dry_run {
+                attachment_hash(&attachment.data)
+            } else {
+                store_attachment_blob(&attachment_dir(conn)?, &attachment.data)?
+            };
+            diesel::insert_into(attachments::table)
+                .values(&NewAttachment {
+                    chat_history_id: chat_id,
+                    kind: &attachment.kind,
+                    mime_type: &attachment.mime_type,
+                    file_name: attachment.file_name.as_deref(),
+                    content_hash: &content_hash,
+                    created_at: attachment.created_at,
+                    extracted_text: attachment.extracted_text.as_deref(),
+                })
+                .execute(conn)
+                .map_err(|e| e.to_string())?;
+        }
+
+        let records: Vec<NewToolCallRecord> = chat
+            .tool_calls
+            .iter()
+            .map(|call| NewToolCallRecord {
+                chat_history_id: chat_id,
+                round: call.round,
+                call_id: &call.call_id,
+                name: &call.name,
+                arguments: &call.arguments,
+                result: &call.result,
+                is_error: call.is_error,
+                created_at: call.created_at,
+            })
+            .collect();
+        diesel::insert_into(tool_calls::table)
+            .values(&records)
+            .execute(conn)
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Remembers where a session was imported from and its title.
+pub fn record_imported_session(
+    conn: &mut SqliteConnection,
+    session_id: &str,
+    source: &str,
+    source_id: &str,
+    title: Option<&str>,
+    source_updated_at: NaiveDateTime,
+) -> Result<(), String> {
+    diesel::insert_into(imported_sessions::table)
+        .values(&NewImportedSession {
+            session_id,
+            source,
+            source_id,
+            title,
+            source_updated_at,
+            imported_at: Utc::now().naive_utc(),
+        })
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Stores an imported conversation as a new session. A conversation imported
+/// before only gets the chats added to it since, so questions asked after
+/// the first import are never duplicated or lost.
+pub fn save_imported_conversation(
+    conn: &mut SqliteConnection,
+    conversation: ImportedConversation,
+    dry_run: bool,
+    report: &mut ImportReport,
+) -> Result<(), String> {
+    let existing = imported_sessions::table
+        .filter(imported_sessions::source.eq(&conversation.source))
+        .filter(imported_sessions::source_id.eq(&conversation.source_id))
+        .select((
+            imported_sessions::session_id,
+            imported_sessions::source_updated_at,
+        ))
+        .first::<(String, NaiveDateTime)>(conn)
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    let Some((session_id, source_updated_at)) = existing else {
+        let session_id = Uuid::new_v4().to_string();
+        save_imported_chats(conn, &session_id, &conversation.chats, dry_run)?;
+        record_imported_session(
+            conn,
+            &session_id,
+            &conversation.source,
+            &conversation.source_id,
+            conversation.title.as_deref(),
+            conversation.updated_at,
+        )?;
+
+        report.conversations_imported += 1;
+        report.chats_imported += conversation.chats.len();
+        return Ok(());
+    };
+
+    let new_chats: Vec<ImportedChat> = conversation
+        .chats
+        .into_iter()
+        .filter(|chat| chat.created_at > source_updated_at)
+        .collect();
+    if conversation.updated_at <= source_updated_at || new_chats.is_empty() {
+        report.conversations_unchanged += 1;
+        return Ok(());
+    }
+
+    save_imported_chats(conn, &session_id, &new_chats, dry_run)?;
+    diesel::update(imported_sessions::table.find(&session_id))
+        .set((
+            imported_sessions::title.eq(conversation.title),
+            imported_sessions::source_updated_at.eq(conversation.updated_at),
+            imported_sessions::imported_at.eq(Utc::now().naive_utc()),
+        ))
+        .execute(conn)
+        .map_err(|e| e.to_string())?;
+
+    report.conversations_updated += 1;
+    report.chats_imported += new_chats.len();
+    Ok(())
+}
+
+/// Runs `save` in one transaction, which is rolled back for a dry run so the
+/// report shows what would be imported.
+pub fn apply_import<F>(
+    conn: &mut SqliteConnection,
+    dry_run: bool,
+    skipped: Vec<SkippedImportItem>,
+    save: F,
+) -> Result<ImportReport, String>
+where
+    F: FnOnce(&mut SqliteConnection, &mut ImportReport) -> Result<(), String>,
+{
+    let mut report = ImportReport {
+        dry_run,
+        skipped,
+        ..Default::default()
+    };
+    if dry_run {
+        in_rolled_back_transaction(conn, |conn| save(conn, &mut report))?;
+    } else {
+        in_transaction(conn, |conn| save(conn, &mut report))?;
+    }
+    Ok(report)
+}
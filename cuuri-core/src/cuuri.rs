@@ -0,0 +1,117 @@
+use crate::active_streams::ActiveStreams;
+use crate::app_type::{McpServerStatus, ProxyStatus};
+use crate::builtin_tools::register_builtin_tools;
+use crate::chat_embeddings::ChatEmbedder;
+use crate::config::Config;
+use crate::config_state::ConfigState;
+use crate::database_backup::run_backup_scheduler;
+use crate::db_pool::DbState;
+use crate::mcp_servers::McpServers;
+use crate::proxy_server::{replace_proxy_token, ProxyServer};
+use crate::tool_approvals::ToolApprovals;
+use crate::tool_registry::ToolRegistry;
+use std::sync::Arc;
+
+/// Everything a Cuuri frontend works with: the configuration, the chat
+/// database of the active profile, and the tools and background services
+/// built on them. The app keeps one in Tauri state; the command-line client
+/// and tests create their own.
+pub struct Cuuri {
+    config: ConfigState,
+    db: DbState,
+    streams: ActiveStreams,
+    tools: ToolRegistry,
+    approvals: ToolApprovals,
+    embedder: ChatEmbedder,
+    mcp_servers: McpServers,
+    proxy: ProxyServer,
+}
+
+impl Cuuri {
+    /// Uses `config` and the database in `db`, with the built-in tools registered.
+    pub fn new(config: Config, db: DbState) -> Self {
+        let tools = ToolRegistry::default();
+        register_builtin_tools(&tools);
+
+        Cuuri {
+            config: ConfigState::new(config),
+            db,
+            streams: ActiveStreams::default(),
+            tools,
+            approvals: ToolApprovals::default(),
+            embedder: ChatEmbedder::default(),
+            mcp_servers: McpServers::default(),
+            proxy: ProxyServer::default(),
+        }
+    }
+
+    /// The effective configuration, with environment and command line overrides.
+    pub fn config(&self) -> Config {
+        self.config.get()
+    }
+
+    pub fn db(&self) -> &DbState {
+        &self.db
+    }
+
+    pub(crate) fn config_state(&self) -> &ConfigState {
+        &self.config
+    }
+
+    pub(crate) fn streams(&self) -> &ActiveStreams {
+        &self.streams
+    }
+
+    pub(crate) fn tools(&self) -> &ToolRegistry {
+        &self.tools
+    }
+
+    pub(crate) fn approvals(&self) -> &ToolApprovals {
+        &self.approvals
+    }
+
+    /// Embeds new questions and answers in the background; never returns.
+    pub async fn run_chat_embedder(&self) {
+        self.embedder.run(&self.config, &self.db).await
+    }
+
+    pub fn wake_chat_embedder(&self) {
+        self.embedder.wake();
+    }
+
+    /// Makes the configured automatic backups; never returns.
+    pub async fn run_backup_scheduler(&self) {
+        run_backup_scheduler(&self.config, &self.db).await
+    }
+
+    /// Brings the running MCP servers in line with the config, returning
+    /// whether anything changed.
+    pub async fn sync_mcp_servers(&self) -> bool {
+        let config = self.config();
+        self.mcp_servers
+            .sync(&config.mcp_servers, &self.tools)
+            .await
+    }
+
+    pub fn mcp_server_statuses(&self) -> Vec<McpServerStatus> {
+        self.mcp_servers.statuses()
+    }
+
+    /// Starts, stops or restarts the proxy server to match the config and
+    /// the active profile's token.
+    pub async fn sync_proxy_server(self: &Arc<Self>) {
+        let config = self.config();
+        self.proxy.sync(self, &config).await;
+    }
+
+    pub fn proxy_status(&self) -> ProxyStatus {
+        self.proxy.status()
+    }
+
+    /// Gives the proxy server a new token, restarting it if it is running.
+    pub async fn regenerate_proxy_token(self: &Arc<Self>) -> Result<ProxyStatus, String> {
+        replace_proxy_token()?;
+        self.sync_proxy_server().await;
+        Ok(self.proxy_status())
+    }
+}
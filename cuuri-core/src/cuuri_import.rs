@@ -0,0 +1,261 @@
+use crate::app_type::{ImportReport, SkippedImportItem};
+use crate::attachments::{attachment_hash, IMAGE_MIME_TYPES, KIND_DOCUMENT, KIND_IMAGE};
+use crate::chat_record::ChatStatus;
+use crate::conversation_export::{
+    ExportDocument, ExportedEntry, ExportedSession, EXPORT_FORMAT_NAME, EXPORT_FORMAT_VERSION,
+};
+use crate::conversation_import::{
+    record_imported_session, save_imported_chats, ImportedAttachment, ImportedChat,
+    ImportedToolCall,
+};
+use crate::documents::DOCUMENT_MIME_TYPES;
+use crate::schema::{chat_histories, imported_sessions};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::Path;
+
+fn parse_time(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").ok()
+}
+
+/// Reads a JSON export written by `export_session` or `export_all`.
+pub fn parse_cuuri_export(data: &[u8]) -> Result<ExportDocument, String> {
+    let value: Value =
+        serde_json::from_slice(data).map_err(|e| format!("Not a JSON file: {}", e))?;
+    if value.get("format").and_then(Value::as_str) != Some(EXPORT_FORMAT_NAME) {
+        return Err("Not a Cuuri export".to_string());
+    }
+    let version = value.get("version").and_then(Value::as_u64).unwrap_or(0);
+    if version > EXPORT_FORMAT_VERSION as u64 {
+        return Err(format!(
+            "The export was written by a newer version of Cuuri (format version {}). Update Cuuri to import it.",
+            version
+        ));
+    }
+
+    serde_json::from_value(value).map_err(|e| format!("The export is damaged: {}", e))
+}
+
+fn convert_entry(entry: ExportedEntry, skip: &mut impl FnMut(String)) -> Option<ImportedChat> {
+    let Some(created_at) = parse_time(&entry.created_at) else {
+        skip(format!(
+            "A question has an unreadable time `{}`",
+            entry.created_at
+        ));
+        return None;
+    };
+    let status = match ChatStatus::parse(&entry.status) {
+        // The answer stopped arriving when the export was written
+        Some(ChatStatus::Pending) => ChatStatus::Failed,
+        Some(status) => status,
+        None => {
+            skip(format!(
+                "A question has an unknown status `{}`",
+                entry.status
+            ));
+            return None;
+        }
+    };
+
+    let mut attachments = Vec::new();
+    for attachment in entry.attachments {
+        let label = attachment.file_name.as_deref().unwrap_or(&attachment.kind);
+        // Only types Cuuri itself stores, as exports embed them in links
+        let known_types: &[&str] = match attachment.kind.as_str() {
+            KIND_IMAGE => &IMAGE_MIME_TYPES,
+            KIND_DOCUMENT => &DOCUMENT_MIME_TYPES,
+            kind => {
+                skip(format!(
+                    "The {} attachment has an unknown kind `{}`",
+                    label, kind
+                ));
+                continue;
+            }
+        };
+        if !known_types.contains(&attachment.mime_type.as_str()) {
+            skip(format!(
+                "The {} attachment has an unsupported type `{}`",
+                label, attachment.mime_type
+            ));
+            continue;
+        }
+        // A name is shown, never used as a path
+        let file_name = attachment.file_name.as_deref().and_then(|name| {
+            Path::new(name)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        });
+
+        match STANDARD.decode(&attachment.data) {
+            Ok(data) if attachment_hash(&data) == attachment.content_hash => {
+                attachments.push(ImportedAttachment {
+                    created_at: parse_time(&attachment.created_at).unwrap_or(created_at),
+                    kind: attachment.kind,
+                    mime_type: attachment.mime_type,
+                    file_name,
+                    extracted_text: attachment.extracted_text,
+                    data,
+                });
+            }
+            Ok(_) => skip(format!(
+                "The {} attachment does not match its checksum",
+                label
+            )),
+            Err(_) => skip(format!("The {} attachment could not be decoded", label)),
+        }
+    }
+
+    let tool_calls = entry
+        .tool_calls
+        .into_iter()
+        .map(|call| ImportedToolCall {
+            created_at: parse_time(&call.created_at).unwrap_or(created_at),
+            round: call.round,
+            call_id: call.call_id,
+            name: call.name,
+            arguments: call.arguments,
+            result: call.result,
+            is_error: call.is_error,
+        })
+        .collect();
+
+    Some(ImportedChat {
+        question: entry.question,
+        answer: entry.answer,
+        created_at,
+        status,
+        model: entry.model,
+        attachments,
+        tool_calls,
+    })
+}
+
+/// Stores an exported session under its original id, so moving between
+/// machines keeps links to it working. Questions the session already holds
+/// are left out, which makes importing the same export again harmless.
+pub fn save_exported_session(
+    conn: &mut SqliteConnection,
+    session: ExportedSession,
+    dry_run: bool,
+    report: &mut ImportReport,
+) -> Result<(), String> {
+    let label = session
+        .title
+        .clone()
+        .unwrap_or_else(|| session.session_id.clone());
+    let mut skip = |reason: String| {
+        report.skipped.push(SkippedImportItem {
+            conversation: label.clone(),
+            reason,
+        })
+    };
+    if session.session_id.trim().is_empty() {
+        skip("The conversation has no session id".to_string());
+        return Ok(());
+    }
+
+    let existing: HashSet<(NaiveDateTime, String)> = chat_histories::table
+        .filter(chat_histories::session_id.eq(&session.session_id))
+        .select((chat_histories::created_at, chat_histories::question))
+        .load::<(NaiveDateTime, String)>(conn)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect();
+
+    let chats: Vec<ImportedChat> = session
+        .entries
+        .into_iter()
+        .filter_map(|entry| convert_entry(entry, &mut skip))
+        .filter(|chat| !existing.contains(&(chat.created_at, chat.question.clone())))
+        .collect();
+
+    if let Some(origin) = &session.origin {
+        let already_recorded = imported_sessions::table
+            .filter(
+                imported_sessions::session_id
+                    .eq(&session.session_id)
+                    .or(imported_sessions::source
+                        .eq(&origin.source)
+                        .and(imported_sessions::source_id.eq(&origin.source_id))),
+            )
+            .count()
+            .get_result::<i64>(conn)
+            .map_err(|e| e.to_string())?
+            > 0;
+        match parse_time(&origin.source_updated_at) {
+            Some(source_updated_at) if !already_recorded => record_imported_session(
+                conn,
+                &session.session_id,
+                &origin.source,
+                &origin.source_id,
+                session.title.as_deref(),
+                source_updated_at,
+            )?,
+            Some(_) => {}
+            None => skip(format!(
+                "Where the conversation was imported from was not kept, as its time `{}` is unreadable",
+                origin.source_updated_at
+            )),
+        }
+    }
+
+    if chats.is_empty() {
+        report.conversations_unchanged += 1;
+        return Ok(());
+    }
+    save_imported_chats(conn, &session.session_id, &chats, dry_run)?;
+    if existing.is_empty() {
+        report.conversations_imported += 1;
+    } else {
+        report.conversations_updated += 1;
+    }
+    report.chats_imported += chats.len();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversation_export::ExportedAttachment;
+
+    fn attachment(kind: &str, mime_type: &str, file_name: &str) -> ExportedAttachment {
+        let data = b"contents";
+        ExportedAttachment {
+            kind: kind.to_string(),
+            mime_type: mime_type.to_string(),
+            file_name: Some(file_name.to_string()),
+            content_hash: attachment_hash(data),
+            extracted_text: None,
+            created_at: "2026-01-01 00:00:00".to_string(),
+            data: STANDARD.encode(data),
+        }
+    }
+
+    #[test]
+    fn attachments_of_unknown_kinds_and_types_are_skipped() {
+        let entry = ExportedEntry {
+            question: "Look".to_string(),
+            answer: "Nice".to_string(),
+            created_at: "2026-01-01 00:00:00".to_string(),
+            status: "complete".to_string(),
+            model: None,
+            attachments: vec![
+                attachment(KIND_IMAGE, "image/png\" onerror=\"alert(1)", "a.png"),
+                attachment("script", "text/plain", "b.txt"),
+                attachment(KIND_DOCUMENT, "text/plain", "../../notes.txt"),
+            ],
+            tool_calls: Vec::new(),
+        };
+
+        let mut skipped = Vec::new();
+        let chat = convert_entry(entry, &mut |reason| skipped.push(reason)).expect("the chat");
+
+        assert_eq!(skipped.len(), 2, "{:?}", skipped);
+        assert_eq!(chat.attachments.len(), 1);
+        assert_eq!(chat.attachments[0].file_name.as_deref(), Some("notes.txt"));
+    }
+}
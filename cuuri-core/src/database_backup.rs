@@ -0,0 +1,281 @@
+use crate::app_type::IntegrityCheckReport;
+use crate::config::Config;
+use crate::config_state::ConfigState;
+use crate::database_encryption::{export_database_file, is_encrypted, open_database, DatabaseKey};
+use crate::db_pool::DbState;
+use crate::get_database_path::get_database_file;
+use crate::run_migrations::unknown_migrations;
+use crate::profile::get_profile_dir;
+use chrono::Local;
+use rusqlite::backup::{Backup, StepResult};
+use rusqlite::{Connection, OpenFlags};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// Small steps let the app keep writing while a backup runs
+const BACKUP_PAGES_PER_STEP: i32 = 256;
+const BACKUP_STEP_PAUSE: Duration = Duration::from_millis(10);
+// How often the scheduler checks whether an automatic backup is due
+const SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const AUTOMATIC_BACKUP_PREFIX: &str = "chat-";
+pub const PRE_RESTORE_PREFIX: &str = "pre-restore-";
+pub const PRE_MIGRATION_PREFIX: &str = "pre-migration-";
+
+fn open_read_only(path: &Path, key: Option<&DatabaseKey>) -> Result<Connection, String> {
+    if !path.is_file() {
+        return Err(format!("{} does not exist", path.display()));
+    }
+    open_database(path, key, OpenFlags::SQLITE_OPEN_READ_ONLY)
+}
+
+/// The key that opens `path`, given the key of the current database. Plain
+/// files need none, e.g. backups made before the database was encrypted.
+fn key_for<'a>(path: &Path, key: Option<&'a DatabaseKey>) -> Option<&'a DatabaseKey> {
+    key.filter(|_| is_encrypted(path))
+}
+
+/// Copies the database at `source` to `target` with SQLite's online backup
+/// API, which gives a consistent copy even while the app is writing to it.
+/// The copy is encrypted with the same `key` as the database, and written
+/// next to `target` first, so a failed backup never leaves a half-written
+/// file behind.
+pub fn backup_database_file(
+    source: &Path,
+    target: &Path,
+    key: Option<&DatabaseKey>,
+) -> Result<(), String> {
+    let source_conn = open_read_only(source, key)?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let partial = target.with_extension("partial");
+    let _ = fs::remove_file(&partial);
+    let result = (|| {
+        let mut target_conn = open_database(&partial, key, OpenFlags::default())?;
+        Backup::new(&source_conn, &mut target_conn)
+            .and_then(|backup| {
+                backup.run_to_completion(BACKUP_PAGES_PER_STEP, BACKUP_STEP_PAUSE, None)
+            })
+            .map_err(|e| e.to_string())?;
+        drop(target_conn);
+        fs::rename(&partial, target).map_err(|e| e.to_string())
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial);
+        return Err(format!("Failed to back up the database: {}", e));
+    }
+    Ok(())
+}
+
+pub fn run_integrity_check(conn: &Connection) -> Result<IntegrityCheckReport, String> {
+    let mut statement = conn
+        .prepare("PRAGMA integrity_check")
+        .map_err(|e| e.to_string())?;
+    let messages = statement
+        .query_map([], |row| row.get::<_, String>(0))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| e.to_string())?;
+
+    Ok(IntegrityCheckReport {
+        ok: messages.len() == 1 && messages[0] == "ok",
+        messages,
+    })
+}
+
+pub fn check_database_file(
+    path: &Path,
+    key: Option<&DatabaseKey>,
+) -> Result<IntegrityCheckReport, String> {
+    run_integrity_check(&open_read_only(path, key)?)
+}
+
+/// Makes sure `path` is an intact Cuuri database before it replaces the
+/// current one, which is encrypted with `key`.
+pub fn validate_backup_file(path: &Path, key: Option<&DatabaseKey>) -> Result<(), String> {
+    let key = key_for(path, key);
+    let conn = open_read_only(path, key)?;
+    let report = run_integrity_check(&conn).map_err(|e| match key {
+        Some(_) => format!(
+            "{} cannot be read with the current database key: {}",
+            path.display(),
+            e
+        ),
+        None => format!(
+            "{} is not a readable SQLite database: {}",
+            path.display(),
+            e
+        ),
+    })?;
+    if !report.ok {
+        return Err(format!(
+            "{} is damaged: {}",
+            path.display(),
+            report.messages.join("; ")
+        ));
+    }
+
+    let has_chats = conn
+        .query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'chat_histories'",
+            [],
+            |row| row.get::<_, i64>(0),
+        )
+        .map_err(|e| e.to_string())?
+        > 0;
+    if !has_chats {
+        return Err(format!("{} is not a Cuuri chat database", path.display()));
+    }
+
+    let mut statement = conn
+        .prepare("SELECT version FROM __diesel_schema_migrations")
+        .map_err(|e| e.to_string())?;
+    let applied = statement
+        .query_map([], |row| row.get::<_, String>(0))
+        .and_then(|rows| rows.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| e.to_string())?;
+    if !unknown_migrations(applied).is_empty() {
+        return Err(format!(
+            "{} was made by a newer version of Cuuri. Update Cuuri to restore it.",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Replaces the contents of the database at `target`, encrypted with `key`,
+/// with the backup at `source`. Connections already open on `target` see
+/// the restored data.
+pub fn restore_database_file(
+    source: &Path,
+    target: &Path,
+    key: Option<&DatabaseKey>,
+) -> Result<(), String> {
+    let source_key = key_for(source, key);
+    if source_key != key {
+        // The backup API only copies between databases with the same key
+        let converted = target.with_extension("restore");
+        let result = export_database_file(source, source_key, &converted, key)
+            .and_then(|_| restore_database_file(&converted, target, key));
+        let _ = fs::remove_file(&converted);
+        return result;
+    }
+
+    let source_conn = open_read_only(source, key)?;
+    let mut target_conn = open_database(target, key, OpenFlags::default())?;
+
+    // All pages in one step, so nothing can write in between
+    let backup = Backup::new(&source_conn, &mut target_conn)
+        .map_err(|e| format!("Failed to restore the database: {}", e))?;
+    loop {
+        match backup.step(-1) {
+            Ok(StepResult::Done) => return Ok(()),
+            Ok(_) => thread::sleep(BACKUP_STEP_PAUSE),
+            Err(e) => return Err(format!("Failed to restore the database: {}", e)),
+        }
+    }
+}
+
+pub fn backup_directory(config: &Config) -> Result<PathBuf, String> {
+    match &config.backup_directory {
+        Some(directory) => Ok(PathBuf::from(directory)),
+        None => get_profile_dir()
+            .map(|dir| dir.join("backups"))
+            .map_err(|e| e.to_string()),
+    }
+}
+
+fn backup_file_name(prefix: &str) -> String {
+    format!("{}{}.db", prefix, Local::now().format("%Y%m%d-%H%M%S"))
+}
+
+/// Backs up the database at `source` into the backup directory, e.g. before
+/// it is overwritten, and returns the path of the copy.
+pub fn snapshot_database(
+    config: &Config,
+    source: &Path,
+    prefix: &str,
+    key: Option<&DatabaseKey>,
+) -> Result<PathBuf, String> {
+    let target = backup_directory(config)?.join(backup_file_name(prefix));
+    backup_database_file(source, &target, key)?;
+    Ok(target)
+}
+
+/// Automatic backups in `dir`, oldest first. Their names sort by time.
+fn automatic_backups(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut backups: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .map(|name| name.to_string_lossy())
+                .is_some_and(|name| {
+                    name.starts_with(AUTOMATIC_BACKUP_PREFIX) && name.ends_with(".db")
+                })
+        })
+        .collect();
+    backups.sort();
+    backups
+}
+
+/// Makes an automatic backup if the last one is older than the configured
+/// interval, then deletes the oldest ones beyond the configured number.
+pub fn run_scheduled_backup(
+    config: &Config,
+    key: Option<&DatabaseKey>,
+) -> Result<Option<PathBuf>, String> {
+    if config.backup_interval_hours == 0 {
+        return Ok(None);
+    }
+    let dir = backup_directory(config)?;
+    let interval = Duration::from_secs(config.backup_interval_hours * 60 * 60);
+
+    let last_backup = automatic_backups(&dir)
+        .last()
+        .and_then(|path| fs::metadata(path).ok())
+        .and_then(|metadata| metadata.modified().ok());
+    let due = last_backup
+        .and_then(|time| SystemTime::now().duration_since(time).ok())
+        .is_none_or(|age| age >= interval);
+    if !due {
+        return Ok(None);
+    }
+
+    let source = get_database_file().map_err(|e| e.to_string())?;
+    let backup = snapshot_database(config, &source, AUTOMATIC_BACKUP_PREFIX, key)?;
+    let backups = automatic_backups(&dir);
+    let excess = backups.len().saturating_sub(config.backup_retention.max(1));
+    for old in &backups[..excess] {
+        if let Err(e) = fs::remove_file(old) {
+            log::warn!("Failed to delete old backup {}: {}", old.display(), e);
+        }
+    }
+    Ok(Some(backup))
+}
+
+/// Makes automatic backups as configured, for as long as the app runs.
+pub async fn run_backup_scheduler(config: &ConfigState, db: &DbState) {
+    loop {
+        let config = config.get();
+        // A locked database is backed up once it is unlocked
+        if let Ok(key) = db.key() {
+            let result =
+                tokio::task::spawn_blocking(move || run_scheduled_backup(&config, key.as_ref()))
+                    .await;
+            match result {
+                Ok(Err(e)) => log::error!("Automatic backup failed: {}", e),
+                Err(e) => log::error!("Automatic backup failed: {}", e),
+                Ok(Ok(_)) => {}
+            }
+        }
+        tokio::time::sleep(SCHEDULE_CHECK_INTERVAL).await;
+    }
+}
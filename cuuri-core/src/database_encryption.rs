@@ -0,0 +1,332 @@
+use crate::app_type::DatabaseEncryptionStatus;
+use crate::config_overrides::ENV_DB_PASSPHRASE;
+use crate::db_pool::{create_pool, DatabaseState, DbState};
+use crate::get_database_path::{get_database_file, get_database_path};
+use crate::profile::get_active_profile_name;
+#[cfg(feature = "keyring")]
+use keyring::Entry;
+use rusqlite::{params, Connection, OpenFlags};
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "Cuuri chat database";
+const SQLITE_HEADER: &[u8; 16] = b"SQLite format 3\0";
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+// Windows refuses to replace a file that is still open, e.g. by a connection
+// that was in use when the pool was dropped
+const REPLACE_ATTEMPTS: u32 = 20;
+const REPLACE_RETRY_PAUSE: Duration = Duration::from_millis(250);
+
+pub const KEY_SOURCE_KEYRING: &str = "keyring";
+pub const KEY_SOURCE_PASSPHRASE: &str = "passphrase";
+pub const LOCKED_MESSAGE: &str =
+    "The chat database is encrypted. Unlock it with its passphrase to continue.";
+
+/// The key of an encrypted chat database.
+#[derive(Clone, PartialEq)]
+pub enum DatabaseKey {
+    /// Chosen by the user; SQLCipher derives the actual key from it.
+    Passphrase(String),
+    /// A random key, as hex, kept in the OS keyring.
+    Raw(String),
+}
+
+// Keeps keys out of logs
+impl fmt::Debug for DatabaseKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DatabaseKey({})", self.source())
+    }
+}
+
+impl DatabaseKey {
+    pub fn generate() -> Result<Self, String> {
+        let mut bytes = [0u8; 32];
+        getrandom::fill(&mut bytes)
+            .map_err(|e| format!("Failed to generate a database key: {}", e))?;
+        Ok(DatabaseKey::Raw(
+            bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+        ))
+    }
+
+    pub fn passphrase(passphrase: String) -> Result<Self, String> {
+        if passphrase.is_empty() {
+            return Err("The passphrase cannot be empty".to_string());
+        }
+        Ok(DatabaseKey::Passphrase(passphrase))
+    }
+
+    /// The given passphrase, or a generated key for the OS keyring without one.
+    pub fn choose(passphrase: Option<String>) -> Result<Self, String> {
+        match passphrase {
+            Some(passphrase) => DatabaseKey::passphrase(passphrase),
+            None if cfg!(feature = "keyring") => DatabaseKey::generate(),
+            None => Err(
+                "This build has no OS keyring support, so a passphrase is required".to_string(),
+            ),
+        }
+    }
+
+    pub fn source(&self) -> &'static str {
+        match self {
+            DatabaseKey::Passphrase(_) => KEY_SOURCE_PASSPHRASE,
+            DatabaseKey::Raw(_) => KEY_SOURCE_KEYRING,
+        }
+    }
+
+    /// The key as SQLCipher takes it. Raw keys use its `x'...'` form, which
+    /// skips deriving a key from a passphrase.
+    fn sqlcipher_key(&self) -> String {
+        match self {
+            DatabaseKey::Passphrase(passphrase) => passphrase.clone(),
+            DatabaseKey::Raw(hex) => format!("x'{}'", hex),
+        }
+    }
+
+    /// The statement that unlocks a connection. It has to run before
+    /// anything else reads the database.
+    pub fn pragma(&self) -> String {
+        format!(
+            "PRAGMA key = '{}';",
+            self.sqlcipher_key().replace('\'', "''")
+        )
+    }
+}
+
+/// Whether the database at `path` is encrypted. Plain SQLite files start
+/// with a fixed header, which SQLCipher encrypts along with everything else.
+pub fn is_encrypted(path: &Path) -> bool {
+    let mut header = [0u8; 16];
+    match File::open(path).and_then(|mut file| file.read_exact(&mut header)) {
+        Ok(()) => &header != SQLITE_HEADER,
+        // Missing or empty, so SQLite creates a plain database
+        Err(_) => false,
+    }
+}
+
+pub fn encryption_status(db: &DbState) -> Result<DatabaseEncryptionStatus, String> {
+    let encrypted = is_encrypted(&get_database_file().map_err(|e| e.to_string())?);
+    Ok(DatabaseEncryptionStatus {
+        encrypted,
+        locked: matches!(db.state(), DatabaseState::Locked),
+        key_source: db.key().ok().flatten().map(|key| key.source().to_string()),
+    })
+}
+
+/// Opens the database at `path`, unlocked with `key` if it is encrypted.
+pub fn open_database(
+    path: &Path,
+    key: Option<&DatabaseKey>,
+    flags: OpenFlags,
+) -> Result<Connection, String> {
+    let conn = Connection::open_with_flags(path, flags)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    if let Some(key) = key {
+        conn.execute_batch(&key.pragma())
+            .map_err(|e| format!("Failed to unlock {}: {}", path.display(), e))?;
+    }
+    conn.busy_timeout(BUSY_TIMEOUT).map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+/// Checks that `key` unlocks the database at `path`. SQLCipher only notices
+/// a wrong key once something is read.
+pub fn verify_database_key(path: &Path, key: Option<&DatabaseKey>) -> Result<(), String> {
+    let conn = open_database(path, key, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+        row.get::<_, i64>(0)
+    })
+    .map(|_| ())
+    .map_err(|_| match key {
+        Some(DatabaseKey::Passphrase(_)) => "The passphrase is wrong".to_string(),
+        Some(DatabaseKey::Raw(_)) => {
+            "The key in the OS keyring does not unlock the chat database".to_string()
+        }
+        None => format!("{} is not a readable database", path.display()),
+    })
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_entry(profile: &str) -> Result<Entry, String> {
+    Entry::new(KEYRING_SERVICE, profile)
+        .map_err(|e| format!("Failed to open the OS keyring: {}", e))
+}
+
+/// The key kept in the OS keyring for the database of `profile`.
+#[cfg(feature = "keyring")]
+pub fn keyring_key(profile: &str) -> Result<Option<DatabaseKey>, String> {
+    match keyring_entry(profile)?.get_password() {
+        Ok(hex) => Ok(Some(DatabaseKey::Raw(hex))),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(format!(
+            "Failed to read the database key from the OS keyring: {}",
+            e
+        )),
+    }
+}
+
+/// Without the `keyring` feature there is no keyring, so only
+/// `CUURI_DB_PASSPHRASE` or `unlock_database` can unlock the database.
+#[cfg(not(feature = "keyring"))]
+pub fn keyring_key(_profile: &str) -> Result<Option<DatabaseKey>, String> {
+    Ok(None)
+}
+
+/// Keeps a generated key in the OS keyring. For a passphrase, or no key at
+/// all, any key kept before is removed.
+#[cfg(feature = "keyring")]
+pub fn remember_database_key(profile: &str, key: Option<&DatabaseKey>) -> Result<(), String> {
+    let entry = keyring_entry(profile)?;
+    match key {
+        Some(DatabaseKey::Raw(hex)) => entry
+            .set_password(hex)
+            .map_err(|e| format!("Failed to save the database key in the OS keyring: {}", e)),
+        _ => match entry.delete_credential() {
+            Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+            Err(e) => Err(format!(
+                "Failed to remove the database key from the OS keyring: {}",
+                e
+            )),
+        },
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn remember_database_key(_profile: &str, key: Option<&DatabaseKey>) -> Result<(), String> {
+    match key {
+        Some(DatabaseKey::Raw(_)) => {
+            Err("This build has no OS keyring support to keep the key in".to_string())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Finds the key of the database at `path`: the one in the OS keyring, or
+/// else the passphrase in `CUURI_DB_PASSPHRASE`. Without either, the error
+/// asks for the passphrase.
+pub fn find_database_key(profile: &str, path: &Path) -> Result<Option<DatabaseKey>, String> {
+    if !is_encrypted(path) {
+        return Ok(None);
+    }
+
+    // Without a usable keyring, the passphrase can still unlock the database
+    let stored = keyring_key(profile).unwrap_or_else(|e| {
+        log::warn!("{}", e);
+        None
+    });
+    let key = match stored {
+        Some(key) => key,
+        None => match env::var(ENV_DB_PASSPHRASE) {
+            Ok(passphrase) if !passphrase.is_empty() => DatabaseKey::Passphrase(passphrase),
+            _ => return Err(LOCKED_MESSAGE.to_string()),
+        },
+    };
+    verify_database_key(path, Some(&key))?;
+    Ok(Some(key))
+}
+
+/// Writes a copy of the database at `source` to `target`, encrypted with
+/// `target_key`, or as a plain SQLite database without one.
+pub fn export_database_file(
+    source: &Path,
+    source_key: Option<&DatabaseKey>,
+    target: &Path,
+    target_key: Option<&DatabaseKey>,
+) -> Result<(), String> {
+    let conn = open_database(source, source_key, OpenFlags::default())?;
+    let _ = fs::remove_file(target);
+
+    let target_key = target_key
+        .map(DatabaseKey::sqlcipher_key)
+        .unwrap_or_default();
+    let result = conn
+        .execute(
+            "ATTACH DATABASE ?1 AS exported KEY ?2",
+            params![target.to_string_lossy(), target_key],
+        )
+        .and_then(|_| conn.query_row("SELECT sqlcipher_export('exported')", [], |_| Ok(())))
+        .and_then(|_| conn.execute_batch("DETACH DATABASE exported"));
+
+    if let Err(e) = result {
+        drop(conn);
+        let _ = fs::remove_file(target);
+        return Err(format!("Failed to write {}: {}", target.display(), e));
+    }
+    Ok(())
+}
+
+fn replace_file(source: &Path, target: &Path) -> Result<(), String> {
+    let mut attempt = 1;
+    loop {
+        match fs::rename(source, target) {
+            Ok(()) => return Ok(()),
+            Err(_) if attempt < REPLACE_ATTEMPTS => {
+                attempt += 1;
+                thread::sleep(REPLACE_RETRY_PAUSE);
+            }
+            Err(e) => return Err(format!("Failed to replace {}: {}", target.display(), e)),
+        }
+    }
+}
+
+fn sidecar_file(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Encrypts the active profile's database with `new_key` in place, whether
+/// it is encrypted already or not, and reopens it. Changes made meanwhile
+/// through connections that were already open are lost, so callers make
+/// sure no answer is being received.
+pub fn rekey_active_database(db: &DbState, new_key: DatabaseKey) -> Result<(), String> {
+    let profile = get_active_profile_name();
+    let path = get_database_file().map_err(|e| e.to_string())?;
+    let database_path = get_database_path().map_err(|e| e.to_string())?;
+    let old_pool = db.pool()?;
+    let old_key = db.key()?;
+
+    // Nothing may write to the database from here on
+    db.set(DatabaseState::Failed {
+        message: "The chat database is being encrypted".to_string(),
+        key: old_key.clone(),
+    });
+    drop(old_pool);
+
+    let rewritten = path.with_extension("rekey");
+    let result = (|| {
+        let conn = open_database(&path, old_key.as_ref(), OpenFlags::default())?;
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+            .map_err(|e| e.to_string())?;
+        drop(conn);
+        export_database_file(&path, old_key.as_ref(), &rewritten, Some(&new_key))?;
+        verify_database_key(&rewritten, Some(&new_key))?;
+
+        // Saved before the file is replaced, so the key is never lost
+        remember_database_key(&profile, Some(&new_key))?;
+        if let Err(e) = replace_file(&rewritten, &path) {
+            let _ = remember_database_key(&profile, old_key.as_ref());
+            return Err(e);
+        }
+        // The old write-ahead log belongs to the replaced file
+        let _ = fs::remove_file(sidecar_file(&path, "-wal"));
+        let _ = fs::remove_file(sidecar_file(&path, "-shm"));
+        Ok(())
+    })();
+    let _ = fs::remove_file(&rewritten);
+
+    let key = match &result {
+        Ok(()) => Some(new_key),
+        Err(_) => old_key,
+    };
+    db.set(match create_pool(&database_path, key.clone()) {
+        Ok(pool) => DatabaseState::Open { pool, key },
+        Err(message) => DatabaseState::Failed { message, key },
+    });
+    result
+}
@@ -0,0 +1,186 @@
+use crate::app_type::DatabaseStatus;
+use crate::database_encryption::{DatabaseKey, LOCKED_MESSAGE};
+use diesel::connection::SimpleConnection;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool};
+use diesel::{Connection, SqliteConnection};
+use std::sync::RwLock;
+use std::time::Duration;
+
+pub type DbPool = Pool<ConnectionManager<SqliteConnection>>;
+
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+struct SqlitePragmas {
+    key: Option<DatabaseKey>,
+}
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for SqlitePragmas {
+    fn on_acquire(&self, conn: &mut SqliteConnection) -> Result<(), diesel::r2d2::Error> {
+        // The key has to be set before anything reads an encrypted database
+        if let Some(key) = &self.key {
+            conn.batch_execute(&key.pragma())
+                .map_err(diesel::r2d2::Error::QueryError)?;
+        }
+
+        // WAL lets readers proceed while a response is being written, and the
+        // busy timeout makes concurrent writers wait instead of failing
+        conn.batch_execute(&format!(
+            "PRAGMA journal_mode = WAL; PRAGMA synchronous = NORMAL; PRAGMA busy_timeout = {}; PRAGMA foreign_keys = ON;",
+            BUSY_TIMEOUT.as_millis()
+        ))
+        .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+/// Creates a pool for the database at `database_path`, unlocking every
+/// connection with `key` when the database is encrypted.
+pub fn create_pool(database_path: &str, key: Option<DatabaseKey>) -> Result<DbPool, String> {
+    let manager = ConnectionManager::<SqliteConnection>::new(database_path);
+    Pool::builder()
+        .max_size(8)
+        .connection_customizer(Box::new(SqlitePragmas { key }))
+        .build(manager)
+        .map_err(|e| format!("Failed to create connection pool: {}", e))
+}
+
+/// Whether the app can use the active profile's database.
+#[derive(Clone)]
+pub enum DatabaseState {
+    Open {
+        pool: DbPool,
+        key: Option<DatabaseKey>,
+    },
+    /// Encrypted, and waiting for its passphrase.
+    Locked,
+    /// Could not be opened, e.g. after a failed migration. The key is kept
+    /// so a backup can still be made or restored.
+    Failed {
+        message: String,
+        key: Option<DatabaseKey>,
+    },
+}
+
+impl DatabaseState {
+    /// Why the database could not be opened, if it failed to.
+    pub fn error(&self) -> Option<String> {
+        match self {
+            DatabaseState::Failed { message, .. } => Some(message.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// The connection pool of the active profile.
+pub struct DbState {
+    current: RwLock<DatabaseState>,
+}
+
+impl DbState {
+    pub fn new(state: DatabaseState) -> Self {
+        DbState {
+            current: RwLock::new(state),
+        }
+    }
+
+    pub fn state(&self) -> DatabaseState {
+        // A poisoned lock still holds a usable pool
+        match self.current.read() {
+            Ok(current) => current.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    /// Swaps in another database, e.g. after switching profiles.
+    pub fn set(&self, state: DatabaseState) {
+        match self.current.write() {
+            Ok(mut current) => *current = state,
+            Err(poisoned) => *poisoned.into_inner() = state,
+        }
+    }
+
+    pub fn pool(&self) -> Result<DbPool, String> {
+        match self.state() {
+            DatabaseState::Open { pool, .. } => Ok(pool),
+            DatabaseState::Locked => Err(LOCKED_MESSAGE.to_string()),
+            DatabaseState::Failed { message, .. } => Err(message),
+        }
+    }
+
+    pub fn status(&self) -> DatabaseStatus {
+        let state = self.state();
+        let name = match state {
+            DatabaseState::Open { .. } => "open",
+            DatabaseState::Locked => "locked",
+            DatabaseState::Failed { .. } => "failed",
+        };
+        DatabaseStatus {
+            state: name.to_string(),
+            error: state.error(),
+        }
+    }
+
+    /// The key the database is encrypted with, if any.
+    pub fn key(&self) -> Result<Option<DatabaseKey>, String> {
+        match self.state() {
+            DatabaseState::Open { key, .. } | DatabaseState::Failed { key, .. } => Ok(key),
+            DatabaseState::Locked => Err(LOCKED_MESSAGE.to_string()),
+        }
+    }
+
+    /// Runs Diesel work on the blocking thread pool so it never stalls IPC handling.
+    pub async fn run<T, F>(&self, f: F) -> Result<T, String>
+    where
+        F: FnOnce(&mut SqliteConnection) -> Result<T, String> + Send + 'static,
+        T: Send + 'static,
+    {
+        run_on_pool(self.pool()?, f).await
+    }
+}
+
+/// Like `DbState::run`, for work that must stay on one database even if the
+/// profile is switched in between.
+pub async fn run_on_pool<T, F>(pool: DbPool, f: F) -> Result<T, String>
+where
+    F: FnOnce(&mut SqliteConnection) -> Result<T, String> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let mut conn = pool.get().map_err(|e| e.to_string())?;
+        f(&mut conn)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Runs `f` inside a transaction, for code that reports errors as `String`.
+pub fn in_transaction<T, F>(conn: &mut SqliteConnection, f: F) -> Result<T, String>
+where
+    F: FnOnce(&mut SqliteConnection) -> Result<T, String>,
+{
+    let mut error = None;
+    conn.transaction(|conn| {
+        f(conn).map_err(|e| {
+            error = Some(e);
+            diesel::result::Error::RollbackTransaction
+        })
+    })
+    .map_err(|e| error.take().unwrap_or_else(|| e.to_string()))
+}
+
+/// Runs `f` inside a transaction that is always rolled back, to find out what
+/// it would do without changing the database.
+pub fn in_rolled_back_transaction<T, F>(conn: &mut SqliteConnection, f: F) -> Result<T, String>
+where
+    F: FnOnce(&mut SqliteConnection) -> Result<T, String>,
+{
+    let mut result = Err("The transaction did not run".to_string());
+    let outcome = conn.transaction::<(), _, _>(|conn| {
+        result = f(conn);
+        Err(diesel::result::Error::RollbackTransaction)
+    });
+    match outcome {
+        Ok(()) | Err(diesel::result::Error::RollbackTransaction) => result,
+        Err(e) => Err(e.to_string()),
+    }
+}
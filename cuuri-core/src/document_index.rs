@@ -0,0 +1,403 @@
+use crate::app_type::IndexedFolderInfo;
+use crate::db_pool::{in_transaction, run_on_pool, DbPool};
+use crate::documents::extract_text_from_bytes;
+use crate::embeddings::{
+    cosine_similarity, decode_embedding, encode_embedding, request_embeddings,
+};
+use crate::models::{IndexedFolder, NewDocumentChunk, NewIndexedFile, NewIndexedFolder};
+use crate::provider_service::Provider;
+use crate::schema::{document_chunks, indexed_files, indexed_folders};
+use chrono::Utc;
+use diesel::dsl::count_star;
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// About 400 tokens, small enough for precise retrieval with useful context
+const CHUNK_CHARS: usize = 1600;
+// Lines repeated at the start of the next chunk so sentences are not cut apart
+const OVERLAP_LINES: usize = 3;
+const MAX_INDEXED_FILE_BYTES: u64 = 2 * 1024 * 1024;
+// Dependency and build directories; hidden directories are skipped as well
+const SKIPPED_DIRECTORIES: &[&str] = &["node_modules", "target"];
+
+pub struct TextChunk {
+    pub start_line: usize,
+    pub end_line: usize,
+    pub content: String,
+}
+
+/// A file read from an indexed folder, split into chunks.
+pub struct IndexedFile {
+    /// Path relative to the folder, with `/` separators.
+    pub path: String,
+    pub hash: String,
+    pub chunks: Vec<TextChunk>,
+}
+
+/// A chunk of an indexed file that matched a question.
+#[derive(Serialize, Clone)]
+pub struct RetrievedChunk {
+    pub folder_path: String,
+    pub file_path: String,
+    pub start_line: i32,
+    pub end_line: i32,
+    pub content: String,
+    pub score: f32,
+}
+
+/// Splits text into chunks of whole lines with a few lines of overlap.
+pub fn chunk_text(text: &str) -> Vec<TextChunk> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < lines.len() {
+        let mut end = start;
+        let mut size = 0;
+        while end < lines.len() && (end == start || size + lines[end].len() < CHUNK_CHARS) {
+            size += lines[end].len() + 1;
+            end += 1;
+        }
+
+        // A single huge line, as in minified files, is cut short
+        let content: String = lines[start..end]
+            .join("\n")
+            .chars()
+            .take(CHUNK_CHARS * 2)
+            .collect();
+        if !content.trim().is_empty() {
+            chunks.push(TextChunk {
+                start_line: start + 1,
+                end_line: end,
+                content,
+            });
+        }
+
+        if end >= lines.len() {
+            break;
+        }
+        start = end.saturating_sub(OVERLAP_LINES).max(start + 1);
+    }
+
+    chunks
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        // Symlinks are not followed, so links back up the tree cannot loop
+        if file_type.is_dir() {
+            if !name.starts_with('.') && !SKIPPED_DIRECTORIES.contains(&name.as_str()) {
+                collect_files(&entry.path(), files);
+            }
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+}
+
+/// Reads and chunks the text files under `root`. Returns the files and the
+/// number of files that were skipped as too large or not text.
+pub fn read_folder_files(root: &Path) -> (Vec<IndexedFile>, usize) {
+    let mut paths = Vec::new();
+    collect_files(root, &mut paths);
+    paths.sort();
+
+    let mut files = Vec::new();
+    let mut skipped = 0;
+    for path in paths {
+        let too_large = fs::metadata(&path)
+            .map(|metadata| metadata.len() > MAX_INDEXED_FILE_BYTES)
+            .unwrap_or(true);
+        let data = match fs::read(&path) {
+            Ok(data) if !too_large => data,
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+
+        let hash = format!("{:x}", Sha256::digest(&data));
+        let Ok(text) = extract_text_from_bytes(data) else {
+            skipped += 1;
+            continue;
+        };
+
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        files.push(IndexedFile {
+            path: relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            hash,
+            chunks: chunk_text(&text),
+        });
+    }
+
+    (files, skipped)
+}
+
+/// Returns the folder row for `path`, creating it if needed. A folder indexed
+/// with another embedding model loses its chunks so it is embedded again.
+pub fn find_or_create_folder(
+    conn: &mut SqliteConnection,
+    folder_path: &str,
+    model: &str,
+) -> Result<IndexedFolder, String> {
+    let existing = indexed_folders::table
+        .filter(indexed_folders::path.eq(folder_path))
+        .first::<IndexedFolder>(conn)
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    match existing {
+        Some(folder) if folder.embedding_model == model => Ok(folder),
+        Some(folder) => {
+            diesel::delete(document_chunks::table.filter(document_chunks::folder_id.eq(folder.id)))
+                .execute(conn)
+                .map_err(|e| e.to_string())?;
+            diesel::delete(indexed_files::table.filter(indexed_files::folder_id.eq(folder.id)))
+                .execute(conn)
+                .map_err(|e| e.to_string())?;
+            diesel::update(indexed_folders::table.filter(indexed_folders::id.eq(folder.id)))
+                .set(indexed_folders::embedding_model.eq(model))
+                .execute(conn)
+                .map_err(|e| e.to_string())?;
+            Ok(IndexedFolder {
+                embedding_model: model.to_string(),
+                ..folder
+            })
+        }
+        None => {
+            let new_folder = NewIndexedFolder {
+                path: folder_path,
+                embedding_model: model,
+                created_at: Utc::now().naive_utc(),
+            };
+            diesel::insert_into(indexed_folders::table)
+                .values(&new_folder)
+                .execute(conn)
+                .map_err(|e| e.to_string())?;
+            indexed_folders::table
+                .filter(indexed_folders::path.eq(folder_path))
+                .first::<IndexedFolder>(conn)
+                .map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Hash of each file currently indexed in the folder, by relative path.
+pub fn stored_file_hashes(
+    conn: &mut SqliteConnection,
+    folder_id: i32,
+) -> Result<HashMap<String, String>, String> {
+    indexed_files::table
+        .filter(indexed_files::folder_id.eq(folder_id))
+        .select((indexed_files::file_path, indexed_files::file_hash))
+        .load::<(String, String)>(conn)
+        .map(|rows| rows.into_iter().collect())
+        .map_err(|e| e.to_string())
+}
+
+pub fn remove_indexed_files(
+    conn: &mut SqliteConnection,
+    folder_id: i32,
+    file_paths: &[String],
+) -> Result<(), String> {
+    diesel::delete(
+        document_chunks::table
+            .filter(document_chunks::folder_id.eq(folder_id))
+            .filter(document_chunks::file_path.eq_any(file_paths)),
+    )
+    .execute(conn)
+    .map_err(|e| e.to_string())?;
+    diesel::delete(
+        indexed_files::table
+            .filter(indexed_files::folder_id.eq(folder_id))
+            .filter(indexed_files::file_path.eq_any(file_paths)),
+    )
+    .execute(conn)
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+/// Replaces the stored chunks of one file and records its hash. A file
+/// without any text keeps no chunks but is still recorded.
+pub fn save_file_chunks(
+    conn: &mut SqliteConnection,
+    folder_id: i32,
+    file: &IndexedFile,
+    embeddings: &[Vec<f32>],
+) -> Result<(), String> {
+    let now = Utc::now().naive_utc();
+    let encoded: Vec<Vec<u8>> = embeddings.iter().map(|v| encode_embedding(v)).collect();
+    let rows: Vec<NewDocumentChunk> = file
+        .chunks
+        .iter()
+        .zip(&encoded)
+        .enumerate()
+        .map(|(index, (chunk, embedding))| NewDocumentChunk {
+            folder_id,
+            file_path: &file.path,
+            file_hash: &file.hash,
+            chunk_index: index as i32,
+            start_line: chunk.start_line as i32,
+            end_line: chunk.end_line as i32,
+            content: &chunk.content,
+            embedding,
+            created_at: now,
+        })
+        .collect();
+
+    // The hash is only recorded together with the chunks it describes
+    in_transaction(conn, |conn| {
+        remove_indexed_files(conn, folder_id, std::slice::from_ref(&file.path))?;
+
+        diesel::insert_into(document_chunks::table)
+            .values(&rows)
+            .execute(conn)
+            .map_err(|e| e.to_string())?;
+
+        diesel::insert_into(indexed_files::table)
+            .values(&NewIndexedFile {
+                folder_id,
+                file_path: &file.path,
+                file_hash: &file.hash,
+            })
+            .execute(conn)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    })
+}
+
+pub fn mark_folder_indexed(conn: &mut SqliteConnection, folder_id: i32) -> Result<(), String> {
+    diesel::update(indexed_folders::table.filter(indexed_folders::id.eq(folder_id)))
+        .set(indexed_folders::indexed_at.eq(Some(Utc::now().naive_utc())))
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+pub fn to_indexed_folder_info(
+    conn: &mut SqliteConnection,
+    folder: IndexedFolder,
+) -> Result<IndexedFolderInfo, String> {
+    let file_count = indexed_files::table
+        .filter(indexed_files::folder_id.eq(folder.id))
+        .select(count_star())
+        .first::<i64>(conn)
+        .map_err(|e| e.to_string())?;
+    let chunk_count = document_chunks::table
+        .filter(document_chunks::folder_id.eq(folder.id))
+        .select(count_star())
+        .first::<i64>(conn)
+        .map_err(|e| e.to_string())?;
+
+    Ok(IndexedFolderInfo {
+        id: folder.id,
+        path: folder.path,
+        embedding_model: folder.embedding_model,
+        file_count,
+        chunk_count,
+        created_at: folder.created_at.to_string(),
+        indexed_at: folder.indexed_at.map(|time| time.to_string()),
+    })
+}
+
+/// The `k` chunks of the given folders closest to `query`. Folders indexed
+/// with another model are left out, as their vectors are not comparable.
+pub fn find_similar_chunks(
+    conn: &mut SqliteConnection,
+    folder_ids: &[i32],
+    model: &str,
+    query: &[f32],
+    k: usize,
+) -> Result<Vec<RetrievedChunk>, String> {
+    let rows = document_chunks::table
+        .inner_join(indexed_folders::table)
+        .filter(document_chunks::folder_id.eq_any(folder_ids))
+        .filter(indexed_folders::embedding_model.eq(model))
+        .select((
+            indexed_folders::path,
+            document_chunks::file_path,
+            document_chunks::start_line,
+            document_chunks::end_line,
+            document_chunks::content,
+            document_chunks::embedding,
+        ))
+        .load::<(String, String, i32, i32, String, Vec<u8>)>(conn)
+        .map_err(|e| e.to_string())?;
+
+    let mut chunks: Vec<RetrievedChunk> = rows
+        .into_iter()
+        .map(
+            |(folder_path, file_path, start_line, end_line, content, embedding)| RetrievedChunk {
+                score: cosine_similarity(query, &decode_embedding(&embedding)),
+                folder_path,
+                file_path,
+                start_line,
+                end_line,
+                content,
+            },
+        )
+        .collect();
+    chunks.sort_by(|a, b| b.score.total_cmp(&a.score));
+    chunks.truncate(k);
+    Ok(chunks)
+}
+
+/// Finds the chunks of the selected folders that best match the question.
+pub async fn retrieve_context(
+    pool: DbPool,
+    provider: &Provider,
+    model: &str,
+    folder_ids: Vec<i32>,
+    top_k: usize,
+    question: &str,
+) -> Result<Vec<RetrievedChunk>, String> {
+    if folder_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let query = request_embeddings(provider, model, &[question.to_string()])
+        .await?
+        .pop()
+        .unwrap_or_default();
+    let model = model.to_string();
+    run_on_pool(pool, move |conn| {
+        find_similar_chunks(conn, &folder_ids, &model, &query, top_k)
+    })
+    .await
+}
+
+/// A system message with the numbered excerpts the answer should cite.
+pub fn build_context_message(chunks: &[RetrievedChunk]) -> serde_json::Value {
+    let mut text = String::from(
+        "The following numbered excerpts from the user's documents may help answer the next question. \
+         When you use an excerpt, cite it by its number, e.g. [1], and list the cited files at the end of the answer.\n",
+    );
+    for (index, chunk) in chunks.iter().enumerate() {
+        text.push_str(&format!(
+            "\n[{}] {} (lines {}-{})\n{}\n",
+            index + 1,
+            chunk.file_path,
+            chunk.start_line,
+            chunk.end_line,
+            chunk.content
+        ));
+    }
+    json!({ "role": "system", "content": text })
+}
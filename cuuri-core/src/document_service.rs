@@ -0,0 +1,172 @@
+use crate::app_type::IndexedFolderInfo;
+use crate::cuuri::Cuuri;
+use crate::db_pool::DbState;
+use crate::document_index::{
+    find_or_create_folder, mark_folder_indexed, read_folder_files, remove_indexed_files,
+    save_file_chunks, stored_file_hashes, to_indexed_folder_info, IndexedFile,
+};
+use crate::embeddings::request_embeddings;
+use crate::models::IndexedFolder;
+use crate::provider_service::Provider;
+use crate::schema::indexed_folders;
+use diesel::prelude::*;
+use serde::Serialize;
+use std::fs;
+
+// Chunks embedded per request when indexing many small files
+const CHUNKS_PER_REQUEST: usize = 64;
+
+/// How far indexing a folder has got.
+#[derive(Serialize, Clone)]
+pub struct IndexProgress {
+    pub folder_id: i32,
+    pub files_done: usize,
+    pub files_total: usize,
+    /// Files left out because they are not text or too large.
+    pub files_skipped: usize,
+}
+
+async fn embed_and_save(
+    db: &DbState,
+    provider: &Provider,
+    model: &str,
+    folder_id: i32,
+    files: Vec<IndexedFile>,
+) -> Result<(), String> {
+    let inputs: Vec<String> = files
+        .iter()
+        .flat_map(|file| file.chunks.iter().map(|chunk| chunk.content.clone()))
+        .collect();
+    let mut embeddings = request_embeddings(provider, model, &inputs)
+        .await?
+        .into_iter();
+
+    db.run(move |conn| {
+        for file in &files {
+            let file_embeddings: Vec<Vec<f32>> =
+                embeddings.by_ref().take(file.chunks.len()).collect();
+            save_file_chunks(conn, folder_id, file, &file_embeddings)?;
+        }
+        Ok(())
+    })
+    .await
+}
+
+impl Cuuri {
+    /// Indexes the text files of a folder for retrieval, reporting the files
+    /// to embed and then each batch of embedded files to `progress`. Indexing
+    /// a folder again only embeds the files that changed since the last run.
+    pub async fn index_folder(
+        &self,
+        path: String,
+        progress: impl Fn(IndexProgress),
+    ) -> Result<IndexedFolderInfo, String> {
+        let config = self.config();
+        if config.openai_api_key.is_empty() {
+            return Err("An OpenAI API key is required to index folders".to_string());
+        }
+
+        let root = fs::canonicalize(&path).map_err(|e| format!("Cannot access {}: {}", path, e))?;
+        if !root.is_dir() {
+            return Err(format!("{} is not a folder", root.display()));
+        }
+        let folder_path = root.to_string_lossy().to_string();
+
+        let (files, files_skipped) = tokio::task::spawn_blocking(move || read_folder_files(&root))
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let db = self.db();
+        let provider = Provider::from_config(&config);
+        let model = config.embedding_model.clone();
+        let folder_model = model.clone();
+        let (folder, stored) = db
+            .run(move |conn| {
+                let folder = find_or_create_folder(conn, &folder_path, &folder_model)?;
+                let stored = stored_file_hashes(conn, folder.id)?;
+                Ok((folder, stored))
+            })
+            .await?;
+        let folder_id = folder.id;
+
+        // Drop files that were deleted since the last run
+        let removed: Vec<String> = stored
+            .keys()
+            .filter(|stored_path| !files.iter().any(|file| &file.path == *stored_path))
+            .cloned()
+            .collect();
+        db.run(move |conn| remove_indexed_files(conn, folder_id, &removed))
+            .await?;
+
+        let changed: Vec<IndexedFile> = files
+            .into_iter()
+            .filter(|file| stored.get(&file.path) != Some(&file.hash))
+            .collect();
+        let files_total = changed.len();
+        progress(IndexProgress {
+            folder_id,
+            files_done: 0,
+            files_total,
+            files_skipped,
+        });
+
+        let mut batch = Vec::new();
+        let mut batch_chunks = 0;
+        for (index, file) in changed.into_iter().enumerate() {
+            // Files without text are saved too, which clears their old chunks
+            batch_chunks += file.chunks.len();
+            batch.push(file);
+
+            let is_last = index + 1 == files_total;
+            if batch_chunks >= CHUNKS_PER_REQUEST || is_last {
+                let files = std::mem::take(&mut batch);
+                batch_chunks = 0;
+                embed_and_save(db, &provider, &model, folder_id, files).await?;
+                progress(IndexProgress {
+                    folder_id,
+                    files_done: index + 1,
+                    files_total,
+                    files_skipped,
+                });
+            }
+        }
+
+        db.run(move |conn| {
+            mark_folder_indexed(conn, folder_id)?;
+            let folder = indexed_folders::table
+                .find(folder_id)
+                .first::<IndexedFolder>(conn)
+                .map_err(|e| e.to_string())?;
+            to_indexed_folder_info(conn, folder)
+        })
+        .await
+    }
+
+    pub async fn indexed_folders(&self) -> Result<Vec<IndexedFolderInfo>, String> {
+        self.db()
+            .run(|conn| {
+                let folders = indexed_folders::table
+                    .order(indexed_folders::path.asc())
+                    .load::<IndexedFolder>(conn)
+                    .map_err(|e| e.to_string())?;
+
+                folders
+                    .into_iter()
+                    .map(|folder| to_indexed_folder_info(conn, folder))
+                    .collect()
+            })
+            .await
+    }
+
+    /// Forgets an indexed folder and its chunks. The files themselves are not touched.
+    pub async fn remove_indexed_folder(&self, folder_id: i32) -> Result<bool, String> {
+        self.db()
+            .run(move |conn| {
+                diesel::delete(indexed_folders::table.find(folder_id))
+                    .execute(conn)
+                    .map(|deleted| deleted > 0)
+                    .map_err(|e| e.to_string())
+            })
+            .await
+    }
+}
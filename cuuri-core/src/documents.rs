@@ -0,0 +1,182 @@
+use crate::attachments::{insert_attachment, read_attachment_blob, store_attachment_blob, KIND_DOCUMENT};
+use diesel::SqliteConnection;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::Path;
+
+// Rough average for English text and code; good enough for budgeting
+const CHARS_PER_TOKEN: usize = 4;
+
+/// A document whose text has been extracted and whose bytes are already stored.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ExtractedDocument {
+    pub file_name: String,
+    pub mime_type: String,
+    pub content_hash: String,
+    pub text: String,
+    pub token_count: usize,
+}
+
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+/// The types document attachments are stored as, see `mime_type_for`.
+pub const DOCUMENT_MIME_TYPES: [&str; 6] = [
+    "application/pdf",
+    "text/markdown",
+    "application/json",
+    "text/html",
+    "text/csv",
+    "text/plain",
+];
+
+fn mime_type_for(file_name: &str) -> &'static str {
+    let extension = Path::new(file_name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "pdf" => "application/pdf",
+        "md" | "markdown" => "text/markdown",
+        "json" => "application/json",
+        "html" | "htm" => "text/html",
+        "csv" => "text/csv",
+        _ => "text/plain",
+    }
+}
+
+fn extract_pdf_text(data: &[u8]) -> Result<String, String> {
+    // The PDF parser panics on some malformed files instead of returning an error
+    std::panic::catch_unwind(|| pdf_extract::extract_text_from_mem(data))
+        .map_err(|_| "Failed to read PDF: the file appears to be malformed".to_string())?
+        .map_err(|e| format!("Failed to read PDF: {}", e))
+}
+
+fn extract_plain_text(data: Vec<u8>) -> Result<String, String> {
+    // NUL bytes do not occur in text files but are common in binary formats
+    if data.contains(&0) {
+        return Err(
+            "Unsupported file type: only text, source code, Markdown and PDF files are accepted"
+                .to_string(),
+        );
+    }
+    let text = String::from_utf8(data)
+        .map_err(|_| "Unsupported text encoding: the file must be UTF-8".to_string())?;
+    Ok(text.trim_start_matches('\u{feff}').to_string())
+}
+
+/// Extracts the text of a PDF or UTF-8 text file.
+pub fn extract_text_from_bytes(data: Vec<u8>) -> Result<String, String> {
+    if data.starts_with(b"%PDF-") {
+        extract_pdf_text(&data)
+    } else {
+        extract_plain_text(data)
+    }
+}
+
+/// Extracts the text of a document and stores its bytes as an attachment blob in `dir`.
+pub fn extract_document_from_bytes(
+    dir: &Path,
+    file_name: &str,
+    data: Vec<u8>,
+) -> Result<ExtractedDocument, String> {
+    let is_pdf = data.starts_with(b"%PDF-");
+    let mime_type = if is_pdf {
+        "application/pdf"
+    } else {
+        mime_type_for(file_name)
+    };
+
+    // Unsupported or broken files must not leave a blob behind
+    let text = extract_text_from_bytes(data.clone())?;
+    let content_hash = store_attachment_blob(dir, &data)?;
+
+    Ok(ExtractedDocument {
+        file_name: file_name.to_string(),
+        mime_type: mime_type.to_string(),
+        content_hash,
+        token_count: estimate_tokens(&text),
+        text,
+    })
+}
+
+/// Rebuilds documents sent with a question from their stored blobs in `dir`,
+/// so only the file name is taken from the frontend.
+pub fn load_stored_documents(
+    dir: &Path,
+    documents: Vec<ExtractedDocument>,
+) -> Result<Vec<ExtractedDocument>, String> {
+    documents
+        .into_iter()
+        .map(|document| {
+            // The blob was written by extract_document; make sure it is still there
+            let data = read_attachment_blob(dir, &document.content_hash)?;
+            let is_pdf = data.starts_with(b"%PDF-");
+            let text = extract_text_from_bytes(data)?;
+            Ok(ExtractedDocument {
+                mime_type: if is_pdf {
+                    "application/pdf".to_string()
+                } else {
+                    mime_type_for(&document.file_name).to_string()
+                },
+                token_count: estimate_tokens(&text),
+                text,
+                ..document
+            })
+        })
+        .collect()
+}
+
+/// Stores documents as returned by `load_stored_documents`.
+pub fn save_document_attachments(
+    conn: &mut SqliteConnection,
+    chat_id: i32,
+    documents: &[ExtractedDocument],
+) -> Result<(), String> {
+    for document in documents {
+        insert_attachment(
+            conn,
+            chat_id,
+            KIND_DOCUMENT,
+            &document.mime_type,
+            Some(&document.file_name),
+            &document.content_hash,
+            Some(&document.text),
+        )?;
+    }
+    Ok(())
+}
+
+/// Builds one text part per document, each with a file-name header, keeping
+/// the combined size within `token_budget`.
+pub fn build_document_parts<'a, I>(documents: I, token_budget: usize) -> Vec<serde_json::Value>
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let mut remaining = token_budget;
+
+    documents
+        .into_iter()
+        .map(|(file_name, text)| {
+            let tokens = estimate_tokens(text);
+            let body = if tokens <= remaining {
+                remaining -= tokens;
+                text.to_string()
+            } else if remaining == 0 {
+                "[omitted: the token budget for attached files is used up]".to_string()
+            } else {
+                let kept: String = text.chars().take(remaining * CHARS_PER_TOKEN).collect();
+                remaining = 0;
+                format!("{}\n[truncated to fit the token budget for attached files]", kept)
+            };
+
+            json!({
+                "type": "text",
+                "text": format!("--- File: {} ---\n{}\n--- End of file: {} ---", file_name, body, file_name)
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,86 @@
+use crate::provider_service::Provider;
+use serde_json::json;
+
+// Inputs per request, well below the provider limit of 2048
+const EMBEDDING_BATCH_SIZE: usize = 64;
+
+/// Embeds `inputs` with the provider's embeddings endpoint, in input order.
+pub async fn request_embeddings(
+    provider: &Provider,
+    model: &str,
+    inputs: &[String],
+) -> Result<Vec<Vec<f32>>, String> {
+    let mut embeddings = Vec::with_capacity(inputs.len());
+
+    for batch in inputs.chunks(EMBEDDING_BATCH_SIZE) {
+        let res = provider
+            .post("embeddings")
+            .json(&json!({ "model": model, "input": batch }))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to request embeddings: {}", e))?;
+
+        if !res.status().is_success() {
+            let http_status = res.status();
+            let body = res.text().await.unwrap_or_default();
+            return Err(format!(
+                "Embedding request failed: HTTP {} {}",
+                http_status, body
+            ));
+        }
+
+        let json: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+        let mut data: Vec<(u64, Vec<f32>)> = json["data"]
+            .as_array()
+            .ok_or("Invalid embedding response format")?
+            .iter()
+            .map(|item| {
+                let vector = item["embedding"]
+                    .as_array()
+                    .map(|values| {
+                        values
+                            .iter()
+                            .filter_map(|v| v.as_f64())
+                            .map(|v| v as f32)
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                (item["index"].as_u64().unwrap_or(0), vector)
+            })
+            .collect();
+        if data.len() != batch.len() {
+            return Err("Invalid embedding response format".to_string());
+        }
+        data.sort_by_key(|(index, _)| *index);
+        embeddings.extend(data.into_iter().map(|(_, vector)| vector));
+    }
+
+    Ok(embeddings)
+}
+
+pub fn encode_embedding(vector: &[f32]) -> Vec<u8> {
+    vector
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+}
+
+pub fn decode_embedding(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+    dot / (norm_a * norm_b)
+}
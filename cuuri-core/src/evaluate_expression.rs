@@ -0,0 +1,218 @@
+// Bounds the recursion of the parser; expressions come from the model
+const MAX_DEPTH: usize = 200;
+
+/// Evaluates an arithmetic expression such as `2 * (3 + 4) ^ 2 / sqrt(16)`.
+///
+/// Supports `+ - * / % ^`, parentheses, unary minus, the constants `pi` and
+/// `e`, and the functions `sqrt abs sin cos tan asin acos atan ln log exp
+/// floor ceil round`.
+pub fn evaluate_expression(expression: &str) -> Result<f64, String> {
+    let mut parser = Parser {
+        chars: expression.chars().filter(|c| !c.is_whitespace()).collect(),
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.parse_sum()?;
+    if parser.pos < parser.chars.len() {
+        return Err(format!(
+            "Unexpected character '{}' at position {}",
+            parser.chars[parser.pos], parser.pos
+        ));
+    }
+    if !value.is_finite() {
+        return Err("The result is not a finite number".to_string());
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    // sum := product (('+' | '-') product)*
+    fn parse_sum(&mut self) -> Result<f64, String> {
+        let mut value = self.parse_product()?;
+        loop {
+            if self.eat('+') {
+                value += self.parse_product()?;
+            } else if self.eat('-') {
+                value -= self.parse_product()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // product := unary (('*' | '/' | '%') unary)*
+    fn parse_product(&mut self) -> Result<f64, String> {
+        let mut value = self.parse_unary()?;
+        loop {
+            if self.eat('*') {
+                value *= self.parse_unary()?;
+            } else if self.eat('/') {
+                let divisor = self.parse_unary()?;
+                if divisor == 0.0 {
+                    return Err("Division by zero".to_string());
+                }
+                value /= divisor;
+            } else if self.eat('%') {
+                let divisor = self.parse_unary()?;
+                if divisor == 0.0 {
+                    return Err("Division by zero".to_string());
+                }
+                value %= divisor;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    // Every nested expression goes through here
+    fn parse_unary(&mut self) -> Result<f64, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err("The expression is nested too deeply".to_string());
+        }
+        self.depth += 1;
+        let value = self.parse_signed();
+        self.depth -= 1;
+        value
+    }
+
+    // unary := ('-' | '+') unary | power
+    fn parse_signed(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            return Ok(-self.parse_unary()?);
+        }
+        if self.eat('+') {
+            return self.parse_unary();
+        }
+        self.parse_power()
+    }
+
+    // power := atom ('^' unary)?, right associative
+    fn parse_power(&mut self) -> Result<f64, String> {
+        let base = self.parse_atom()?;
+        if self.eat('^') {
+            let exponent = self.parse_unary()?;
+            return Ok(base.powf(exponent));
+        }
+        Ok(base)
+    }
+
+    // atom := number | '(' sum ')' | name | name '(' sum ')'
+    fn parse_atom(&mut self) -> Result<f64, String> {
+        match self.peek() {
+            Some('(') => {
+                self.pos += 1;
+                let value = self.parse_sum()?;
+                if !self.eat(')') {
+                    return Err("Missing closing parenthesis".to_string());
+                }
+                Ok(value)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => self.parse_number(),
+            Some(c) if c.is_ascii_alphabetic() => self.parse_name(),
+            Some(c) => Err(format!(
+                "Unexpected character '{}' at position {}",
+                c, self.pos
+            )),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<f64, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            self.pos += 1;
+        }
+        let text: String = self.chars[start..self.pos].iter().collect();
+        text.parse::<f64>()
+            .map_err(|_| format!("Invalid number '{}'", text))
+    }
+
+    fn parse_name(&mut self) -> Result<f64, String> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.pos += 1;
+        }
+        let name: String = self.chars[start..self.pos].iter().collect();
+
+        if !self.eat('(') {
+            return match name.as_str() {
+                "pi" => Ok(std::f64::consts::PI),
+                "e" => Ok(std::f64::consts::E),
+                _ => Err(format!("Unknown constant '{}'", name)),
+            };
+        }
+
+        let argument = self.parse_sum()?;
+        if !self.eat(')') {
+            return Err("Missing closing parenthesis".to_string());
+        }
+
+        let function: fn(f64) -> f64 = match name.as_str() {
+            "sqrt" => f64::sqrt,
+            "abs" => f64::abs,
+            "sin" => f64::sin,
+            "cos" => f64::cos,
+            "tan" => f64::tan,
+            "asin" => f64::asin,
+            "acos" => f64::acos,
+            "atan" => f64::atan,
+            "ln" => f64::ln,
+            "log" => f64::log10,
+            "exp" => f64::exp,
+            "floor" => f64::floor,
+            "ceil" => f64::ceil,
+            "round" => f64::round,
+            _ => return Err(format!("Unknown function '{}'", name)),
+        };
+        Ok(function(argument))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluates_arithmetic() {
+        assert_eq!(evaluate_expression("2 * (3 + 4) ^ 2 / sqrt(16)"), Ok(24.5));
+        assert_eq!(evaluate_expression("--3"), Ok(3.0));
+    }
+
+    #[test]
+    fn refuses_deep_nesting() {
+        let parentheses = format!("{}1{}", "(".repeat(50_000), ")".repeat(50_000));
+        let minus_signs = format!("{}1", "-".repeat(50_000));
+        let functions = format!("{}1{}", "abs(".repeat(50_000), ")".repeat(50_000));
+        for expression in [parentheses, minus_signs, functions] {
+            assert_eq!(
+                evaluate_expression(&expression),
+                Err("The expression is nested too deeply".to_string())
+            );
+        }
+    }
+
+    #[test]
+    fn allows_moderate_nesting() {
+        let expression = format!("{}1{}", "(".repeat(50), ")".repeat(50));
+        assert_eq!(evaluate_expression(&expression), Ok(1.0));
+    }
+}
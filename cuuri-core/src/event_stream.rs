@@ -0,0 +1,32 @@
+/// Splits a server-sent event stream, as returned by the chat completions
+/// endpoint with `stream: true`, into the JSON payloads of its `data:` lines.
+#[derive(Default)]
+pub struct EventStreamDecoder {
+    // Events and UTF-8 characters can be split across chunks, so only
+    // complete lines are parsed
+    buffer: Vec<u8>,
+}
+
+impl EventStreamDecoder {
+    /// Returns the events completed by `chunk`. The closing `[DONE]` marker
+    /// and lines that are not JSON are skipped.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<serde_json::Value> {
+        self.buffer.extend_from_slice(chunk);
+
+        let mut events = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(json_str) = line.trim().strip_prefix("data: ") else {
+                continue;
+            };
+            if json_str == "[DONE]" {
+                continue;
+            }
+            if let Ok(event) = serde_json::from_str(json_str) {
+                events.push(event);
+            }
+        }
+        events
+    }
+}
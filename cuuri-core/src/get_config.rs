@@ -1,11 +1,10 @@
 use crate::config::Config;
+use crate::get_config_path::get_config_path;
 use std::path::PathBuf;
 
-pub async fn get_config() -> Result<Config, String> {
-    // Attempt to get the home directory
-    let mut config_path: PathBuf = dirs::home_dir().ok_or("Failed to get home directory".to_string())?;
-    
-    config_path.push(".cuuri/config.toml");
+pub fn get_config() -> Result<Config, String> {
+    // Resolve the config file path, honoring CUURI_CONFIG and --config
+    let config_path: PathBuf = get_config_path().map_err(|e| e.to_string())?;
 
     // Check if the config file exists
     if !config_path.exists() {
@@ -16,8 +15,11 @@ pub async fn get_config() -> Result<Config, String> {
     let config_path_str = config_path.to_str().ok_or("Failed to convert config path to string".to_string())?;
     
     // Load the configuration from file and handle potential errors
-    let config = Config::from_file(config_path_str)
+    let mut config = Config::from_file(config_path_str)
         .map_err(|e| format!("Failed to load configuration: {}", e))?;
 
+    // Environment variables and command line flags take precedence over the file
+    config.apply_overrides();
+
     Ok(config)
 }
\ No newline at end of file
@@ -0,0 +1,26 @@
+use crate::config_overrides::{config_path_override, Resolved};
+use crate::get_data_dir::resolve_data_dir;
+use crate::profile::get_profile_dir;
+use std::io;
+use std::path::PathBuf;
+
+pub fn resolve_config_path() -> Result<Resolved<PathBuf>, io::Error> {
+    if let Some(resolved) = config_path_override() {
+        return Ok(Resolved {
+            value: PathBuf::from(resolved.value),
+            source: resolved.source,
+        });
+    }
+
+    // Without an explicit path, the config file belongs to the active profile
+    let data_dir = resolve_data_dir()?;
+
+    Ok(Resolved {
+        value: get_profile_dir()?.join("config.toml"),
+        source: data_dir.source,
+    })
+}
+
+pub fn get_config_path() -> Result<PathBuf, io::Error> {
+    resolve_config_path().map(|resolved| resolved.value)
+}
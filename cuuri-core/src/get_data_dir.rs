@@ -0,0 +1,36 @@
+use crate::config_overrides::{data_dir_override, ConfigSource, Resolved};
+use std::io;
+use std::path::PathBuf;
+
+pub fn resolve_data_dir() -> Result<Resolved<PathBuf>, io::Error> {
+    if let Some(resolved) = data_dir_override() {
+        return Ok(Resolved {
+            value: PathBuf::from(resolved.value),
+            source: resolved.source,
+        });
+    }
+
+    // Keep using the directory of earlier releases when it is already there
+    if let Some(home_dir) = dirs::home_dir() {
+        let legacy_dir = home_dir.join(".cuuri");
+        if legacy_dir.is_dir() {
+            return Ok(Resolved {
+                value: legacy_dir,
+                source: ConfigSource::Default,
+            });
+        }
+    }
+
+    // $XDG_DATA_HOME/cuuri on Linux, the platform equivalent elsewhere
+    let data_dir = dirs::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Failed to get data directory"))?;
+
+    Ok(Resolved {
+        value: data_dir.join("cuuri"),
+        source: ConfigSource::Default,
+    })
+}
+
+pub fn get_data_dir() -> Result<PathBuf, io::Error> {
+    resolve_data_dir().map(|resolved| resolved.value)
+}
@@ -1,11 +1,15 @@
+use crate::profile::get_profile_dir;
 use std::fs;
 use std::io;
+use std::path::PathBuf;
+
+pub fn get_database_file() -> Result<PathBuf, io::Error> {
+    Ok(get_profile_dir()?.join("chat.db"))
+}
 
 pub fn get_database_path() -> Result<String, io::Error> {
-    // ホームディレクトリの取得
-    let mut db_path = dirs::home_dir()
-        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Failed to get home directory"))?;
-    db_path.push(".cuuri/chat.db");
+    // アクティブなプロファイルのディレクトリの取得
+    let db_path = get_database_file()?;
 
     // 親ディレクトリの作成
     if let Some(parent) = db_path.parent() {
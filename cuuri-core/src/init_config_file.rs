@@ -0,0 +1,30 @@
+use crate::config::CURRENT_CONFIG_VERSION;
+use crate::config_document::upgrade_config_file;
+use crate::get_config_path::get_config_path;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub fn init_config_file() -> Result<(), io::Error> {
+    let config_path = get_config_path()?;
+    init_config_file_at(&config_path)
+}
+
+pub fn init_config_file_at(config_path: &Path) -> Result<(), io::Error> {
+    if !config_path.exists() {
+        if let Some(parent) = config_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        
+        let mut file = fs::File::create(config_path)?;
+        writeln!(file, "config_version = {}", CURRENT_CONFIG_VERSION)?;
+        writeln!(file, "openai_api_key = \"\"")?;
+        writeln!(file, "default_model = \"gpt-3.5-turbo\"")?;
+        log::info!("Configuration file created at {}", config_path.display());
+    } else {
+        // Bring files written by older versions up to date
+        upgrade_config_file(config_path).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,244 @@
+use crate::app_type::SkippedImportItem;
+use crate::attachments::KIND_IMAGE;
+use crate::chat_record::ChatStatus;
+use crate::conversation_import::{
+    ImportedAttachment, ImportedChat, ImportedConversation, ImportedToolCall,
+};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+pub const SOURCE_JSONL: &str = "jsonl";
+
+/// Decodes a `data:<mime type>;base64,<data>` image URL.
+fn decode_data_url(url: &str) -> Option<(String, Vec<u8>)> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let mime_type = header.strip_suffix(";base64")?;
+    let data = STANDARD.decode(data).ok()?;
+    Some((mime_type.to_string(), data))
+}
+
+/// The text of a message's content, which is either a string or a list of
+/// parts. Inline images are added to `attachments` when given; other images
+/// and files are counted in `skipped_files`.
+fn content_text(
+    content: Option<&Value>,
+    created_at: NaiveDateTime,
+    mut attachments: Option<&mut Vec<ImportedAttachment>>,
+    skipped_files: &mut usize,
+) -> String {
+    let parts = match content {
+        Some(Value::String(text)) => return text.clone(),
+        Some(Value::Array(parts)) => parts,
+        _ => return String::new(),
+    };
+
+    let mut texts = Vec::new();
+    for part in parts {
+        match part.get("type").and_then(Value::as_str) {
+            Some("text") => {
+                if let Some(text) = part.get("text").and_then(Value::as_str) {
+                    texts.push(text);
+                }
+            }
+            Some("image_url") => {
+                let url = part
+                    .pointer("/image_url/url")
+                    .or_else(|| part.get("image_url"))
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                match (decode_data_url(url), attachments.as_deref_mut()) {
+                    (Some((mime_type, data)), Some(attachments)) => {
+                        attachments.push(ImportedAttachment {
+                            kind: KIND_IMAGE.to_string(),
+                            mime_type,
+                            file_name: None,
+                            extracted_text: None,
+                            created_at,
+                            data,
+                        })
+                    }
+                    _ => *skipped_files += 1,
+                }
+            }
+            _ => *skipped_files += 1,
+        }
+    }
+    texts.join("\n\n")
+}
+
+/// Turns one line's `messages` into question and answer pairs. Tool calls and
+/// their results are kept with the question they helped answer.
+fn convert_messages(
+    messages: &[Value],
+    model: Option<&str>,
+    created_at: NaiveDateTime,
+    skip: &mut impl FnMut(String),
+) -> Vec<ImportedChat> {
+    let mut chats: Vec<ImportedChat> = Vec::new();
+    let mut skipped_messages = 0;
+    let mut skipped_files = 0;
+
+    for message in messages {
+        let role = message.get("role").and_then(Value::as_str).unwrap_or("");
+        let content = message.get("content");
+        match (role, chats.last_mut()) {
+            ("user", _) => {
+                let mut chat = ImportedChat::new(String::new(), created_at);
+                chat.question = content_text(
+                    content,
+                    created_at,
+                    Some(&mut chat.attachments),
+                    &mut skipped_files,
+                );
+                if chat.question.is_empty() && chat.attachments.is_empty() {
+                    continue;
+                }
+                chats.push(chat);
+            }
+            ("assistant", Some(chat)) => {
+                let text = content_text(content, created_at, None, &mut skipped_files);
+                if !text.is_empty() {
+                    if !chat.answer.is_empty() {
+                        chat.answer.push_str("\n\n");
+                    }
+                    chat.answer.push_str(&text);
+                    chat.status = ChatStatus::Complete;
+                    chat.model = model.map(str::to_string);
+                }
+
+                let calls = message
+                    .get("tool_calls")
+                    .and_then(Value::as_array)
+                    .cloned()
+                    .unwrap_or_default();
+                let round = chat
+                    .tool_calls
+                    .last()
+                    .map(|call| call.round + 1)
+                    .unwrap_or(0);
+                for call in calls {
+                    let field = |pointer: &str| {
+                        call.pointer(pointer)
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string()
+                    };
+                    chat.tool_calls.push(ImportedToolCall {
+                        round,
+                        call_id: field("/id"),
+                        name: field("/function/name"),
+                        arguments: field("/function/arguments"),
+                        result: String::new(),
+                        is_error: false,
+                        created_at,
+                    });
+                }
+            }
+            ("tool", Some(chat)) => {
+                let call_id = message
+                    .get("tool_call_id")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default();
+                let result = content_text(content, created_at, None, &mut skipped_files);
+                match chat
+                    .tool_calls
+                    .iter_mut()
+                    .find(|call| call.call_id == call_id)
+                {
+                    Some(call) => call.result = result,
+                    None => skipped_messages += 1,
+                }
+            }
+            // System prompts, and replies before the first question
+            _ => skipped_messages += 1,
+        }
+    }
+
+    if skipped_messages > 0 {
+        skip(format!(
+            "{} system prompts, unmatched tool results or replies before the first question were not imported",
+            skipped_messages
+        ));
+    }
+    if skipped_files > 0 {
+        skip(format!(
+            "{} images or files that are not embedded in the file were not imported",
+            skipped_files
+        ));
+    }
+    chats
+}
+
+/// Reads a JSONL file with one conversation per line, written either as an
+/// OpenAI `messages` array or as an object with a `messages` field, as in
+/// fine-tuning data. An object may also give a `title` and a `model`.
+pub fn parse_jsonl_conversations(
+    data: &[u8],
+) -> Result<(Vec<ImportedConversation>, Vec<SkippedImportItem>), String> {
+    let text = std::str::from_utf8(data).map_err(|_| "The file is not UTF-8 text".to_string())?;
+    let now = Utc::now().naive_utc();
+
+    let mut conversations = Vec::new();
+    let mut skipped = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let value: Option<Value> = serde_json::from_str(line).ok();
+        let title = value
+            .as_ref()
+            .and_then(|value| value.get("title"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let label = title
+            .clone()
+            .unwrap_or_else(|| format!("Line {}", index + 1));
+        let mut skip = |reason: String| {
+            skipped.push(SkippedImportItem {
+                conversation: label.clone(),
+                reason,
+            })
+        };
+
+        let messages = match &value {
+            Some(Value::Array(messages)) => messages,
+            Some(value) => match value.get("messages").and_then(Value::as_array) {
+                Some(messages) => messages,
+                None => {
+                    skip("The line has no `messages` array".to_string());
+                    continue;
+                }
+            },
+            None => {
+                skip("The line is not valid JSON".to_string());
+                continue;
+            }
+        };
+        let model = value
+            .as_ref()
+            .and_then(|value| value.get("model"))
+            .and_then(Value::as_str);
+
+        let chats = convert_messages(messages, model, now, &mut skip);
+        if chats.is_empty() {
+            skip("The conversation has no questions to import".to_string());
+            continue;
+        }
+        conversations.push(ImportedConversation {
+            source: SOURCE_JSONL.to_string(),
+            // The lines carry no ids, so a conversation is known by its content
+            source_id: format!("{:x}", Sha256::digest(line.as_bytes())),
+            title,
+            // Also without times, an imported line never counts as updated
+            updated_at: DateTime::UNIX_EPOCH.naive_utc(),
+            chats,
+        });
+    }
+
+    Ok((conversations, skipped))
+}
@@ -0,0 +1,60 @@
+//! The services behind Cuuri: sessions, messages, providers, configuration
+//! and storage. The app, the command-line client and tests all go through
+//! [`Cuuri`].
+
+mod active_streams;
+pub mod app_type;
+mod attachments;
+mod builtin_tools;
+mod chat_context;
+mod chat_embeddings;
+pub mod chat_events;
+pub mod chat_exchange;
+pub mod chat_history;
+pub mod chat_record;
+mod chatgpt_import;
+pub mod config;
+mod config_document;
+pub mod config_overrides;
+mod config_service;
+mod config_state;
+pub mod conversation_export;
+mod conversation_import;
+pub mod cuuri;
+mod cuuri_import;
+mod database_backup;
+mod database_encryption;
+pub mod db_pool;
+pub mod document_index;
+pub mod document_service;
+pub mod documents;
+mod embeddings;
+mod evaluate_expression;
+mod event_stream;
+pub mod get_config;
+pub mod get_config_path;
+mod get_data_dir;
+mod get_database_path;
+pub mod init_config_file;
+mod jsonl_import;
+pub mod mcp_client;
+mod mcp_servers;
+mod message_service;
+mod models;
+pub mod open_chat_database;
+pub mod prepare_image;
+mod profile;
+pub mod provider_service;
+mod proxy_chats;
+mod proxy_server;
+mod run_migrations;
+mod schema;
+mod session_service;
+pub mod settings;
+pub mod stderr_log;
+mod storage_service;
+pub mod tool_approvals;
+pub mod tool_calls;
+mod tool_registry;
+
+pub use cuuri::Cuuri;
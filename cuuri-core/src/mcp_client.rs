@@ -0,0 +1,487 @@
+use crate::config::McpServerConfig;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::oneshot;
+
+/// The MCP revision this client implements.
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+// JSON-RPC error code for requests the client does not handle
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// A tool advertised by an MCP server.
+#[derive(Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct McpTool {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default = "empty_input_schema")]
+    pub input_schema: serde_json::Value,
+}
+
+fn empty_input_schema() -> serde_json::Value {
+    json!({ "type": "object", "properties": {} })
+}
+
+type PendingRequests = Arc<Mutex<HashMap<u64, oneshot::Sender<serde_json::Value>>>>;
+
+struct StdioTransport {
+    child: tokio::sync::Mutex<Child>,
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    pending: PendingRequests,
+}
+
+struct HttpTransport {
+    client: reqwest::Client,
+    url: String,
+    headers: BTreeMap<String, String>,
+    session_id: Mutex<Option<String>>,
+    protocol_version: Mutex<Option<String>>,
+}
+
+enum Transport {
+    Stdio(StdioTransport),
+    Http(HttpTransport),
+}
+
+/// A connection to one MCP server, initialized and ready for requests.
+pub struct McpClient {
+    name: String,
+    transport: Transport,
+    next_id: AtomicU64,
+}
+
+impl McpClient {
+    /// Launches or connects to the server and performs the initialization handshake.
+    pub async fn connect(name: &str, config: &McpServerConfig) -> Result<McpClient, String> {
+        let transport = match (&config.command, &config.url) {
+            (Some(command), None) => Transport::Stdio(spawn_stdio_server(name, command, config)?),
+            (None, Some(url)) => Transport::Http(HttpTransport {
+                client: reqwest::Client::new(),
+                url: url.clone(),
+                headers: config.headers.clone(),
+                session_id: Mutex::new(None),
+                protocol_version: Mutex::new(None),
+            }),
+            _ => {
+                return Err(format!(
+                    "MCP server `{}` needs either `command` or `url`, but not both",
+                    name
+                ))
+            }
+        };
+
+        let client = McpClient {
+            name: name.to_string(),
+            transport,
+            next_id: AtomicU64::new(1),
+        };
+
+        let result = client
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "Cuuri", "version": env!("CARGO_PKG_VERSION") }
+                }),
+            )
+            .await?;
+        if let Transport::Http(http) = &client.transport {
+            // Later HTTP requests must state the negotiated version
+            if let Ok(mut version) = http.protocol_version.lock() {
+                *version = result["protocolVersion"].as_str().map(str::to_string);
+            }
+        }
+        client.notify("notifications/initialized").await?;
+
+        Ok(client)
+    }
+
+    pub async fn list_tools(&self) -> Result<Vec<McpTool>, String> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let result = self.request("tools/list", params).await?;
+            let page: Vec<McpTool> =
+                serde_json::from_value(result["tools"].clone()).map_err(|e| {
+                    format!(
+                        "MCP server `{}` sent an invalid tool list: {}",
+                        self.name, e
+                    )
+                })?;
+            tools.extend(page);
+
+            cursor = result["nextCursor"].as_str().map(str::to_string);
+            if cursor.is_none() {
+                return Ok(tools);
+            }
+        }
+    }
+
+    /// Calls a tool and flattens its content into text for the model.
+    pub async fn call_tool(
+        &self,
+        tool: &str,
+        arguments: serde_json::Value,
+    ) -> Result<String, String> {
+        let result = self
+            .request(
+                "tools/call",
+                json!({ "name": tool, "arguments": arguments }),
+            )
+            .await?;
+
+        let mut text = format_tool_content(&result["content"]);
+        if text.is_empty() && !result["structuredContent"].is_null() {
+            text = result["structuredContent"].to_string();
+        }
+
+        if result["isError"].as_bool().unwrap_or(false) {
+            Err(text)
+        } else {
+            Ok(text)
+        }
+    }
+
+    /// Stops a stdio server, or ends the session of an HTTP server.
+    pub async fn shutdown(&self) {
+        match &self.transport {
+            Transport::Stdio(stdio) => {
+                let _ = stdio.child.lock().await.kill().await;
+            }
+            Transport::Http(http) => {
+                let session_id = http.session_id.lock().ok().and_then(|id| id.clone());
+                if let Some(session_id) = session_id {
+                    let _ = http
+                        .client
+                        .delete(&http.url)
+                        .header("Mcp-Session-Id", session_id)
+                        .send()
+                        .await;
+                }
+            }
+        }
+    }
+
+    async fn request(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        });
+
+        let response = match &self.transport {
+            Transport::Stdio(stdio) => self.stdio_request(stdio, id, &message).await?,
+            Transport::Http(http) => self.http_post(http, &message, Some(id)).await?,
+        };
+
+        if let Some(error) = response.get("error") {
+            return Err(format!(
+                "MCP server `{}` returned an error for `{}`: {}",
+                self.name,
+                method,
+                error["message"].as_str().unwrap_or("unknown error")
+            ));
+        }
+        Ok(response["result"].clone())
+    }
+
+    async fn notify(&self, method: &str) -> Result<(), String> {
+        let message = json!({ "jsonrpc": "2.0", "method": method });
+        match &self.transport {
+            Transport::Stdio(stdio) => write_message(&stdio.stdin, &message).await,
+            Transport::Http(http) => self.http_post(http, &message, None).await.map(|_| ()),
+        }
+        .map_err(|e| format!("Failed to notify MCP server `{}`: {}", self.name, e))
+    }
+
+    async fn stdio_request(
+        &self,
+        stdio: &StdioTransport,
+        id: u64,
+        message: &serde_json::Value,
+    ) -> Result<serde_json::Value, String> {
+        let (sender, receiver) = oneshot::channel();
+        if let Ok(mut pending) = stdio.pending.lock() {
+            pending.insert(id, sender);
+        }
+
+        let forget = |pending: &PendingRequests| {
+            if let Ok(mut pending) = pending.lock() {
+                pending.remove(&id);
+            }
+        };
+
+        if let Err(e) = write_message(&stdio.stdin, message).await {
+            forget(&stdio.pending);
+            return Err(format!(
+                "Failed to write to MCP server `{}`: {}",
+                self.name, e
+            ));
+        }
+
+        match tokio::time::timeout(REQUEST_TIMEOUT, receiver).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(format!("MCP server `{}` closed the connection", self.name)),
+            Err(_) => {
+                forget(&stdio.pending);
+                Err(format!(
+                    "MCP server `{}` did not respond in time",
+                    self.name
+                ))
+            }
+        }
+    }
+
+    /// Posts one message. For requests, returns the matching response from
+    /// either a JSON body or an SSE stream.
+    async fn http_post(
+        &self,
+        http: &HttpTransport,
+        message: &serde_json::Value,
+        id: Option<u64>,
+    ) -> Result<serde_json::Value, String> {
+        let mut builder = http
+            .client
+            .post(&http.url)
+            .header("Accept", "application/json, text/event-stream")
+            .timeout(REQUEST_TIMEOUT)
+            .json(message);
+        for (key, value) in &http.headers {
+            builder = builder.header(key, value);
+        }
+        if let Some(session_id) = http.session_id.lock().ok().and_then(|id| id.clone()) {
+            builder = builder.header("Mcp-Session-Id", session_id);
+        }
+        if let Some(version) = http.protocol_version.lock().ok().and_then(|v| v.clone()) {
+            builder = builder.header("MCP-Protocol-Version", version);
+        }
+
+        let res = builder
+            .send()
+            .await
+            .map_err(|e| format!("Failed to reach MCP server `{}`: {}", self.name, e))?;
+        if !res.status().is_success() {
+            let http_status = res.status();
+            let body = res.text().await.unwrap_or_default();
+            return Err(format!(
+                "MCP server `{}` rejected the request: HTTP {} {}",
+                self.name, http_status, body
+            ));
+        }
+
+        if let Some(session_id) = res.headers().get("mcp-session-id") {
+            if let (Ok(session_id), Ok(mut current)) = (session_id.to_str(), http.session_id.lock())
+            {
+                *current = Some(session_id.to_string());
+            }
+        }
+
+        let Some(id) = id else {
+            return Ok(serde_json::Value::Null);
+        };
+
+        let is_event_stream = res
+            .headers()
+            .get("content-type")
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"));
+        let response = if is_event_stream {
+            read_event_stream_response(res, id).await?
+        } else {
+            let body: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+            // A batch may carry the response along with other messages
+            match body {
+                serde_json::Value::Array(messages) => messages
+                    .into_iter()
+                    .find(|message| is_response_to(message, id)),
+                message => Some(message),
+            }
+        };
+
+        response.ok_or_else(|| format!("MCP server `{}` did not answer request {}", self.name, id))
+    }
+}
+
+fn spawn_stdio_server(
+    name: &str,
+    command: &str,
+    config: &McpServerConfig,
+) -> Result<StdioTransport, String> {
+    let mut child = Command::new(command)
+        .args(&config.args)
+        .envs(&config.env)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start MCP server `{}`: {}", name, e))?;
+
+    let (Some(stdin), Some(stdout), Some(stderr)) =
+        (child.stdin.take(), child.stdout.take(), child.stderr.take())
+    else {
+        return Err(format!("Failed to open the pipes of MCP server `{}`", name));
+    };
+
+    let stdin = Arc::new(tokio::sync::Mutex::new(stdin));
+    let pending = PendingRequests::default();
+    tokio::spawn(read_stdio_messages(stdout, stdin.clone(), pending.clone()));
+
+    // Servers log to stderr; pass it on to our own log
+    let server_name = name.to_string();
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            log::info!("[mcp:{}] {}", server_name, line);
+        }
+    });
+
+    Ok(StdioTransport {
+        child: tokio::sync::Mutex::new(child),
+        stdin,
+        pending,
+    })
+}
+
+/// Routes responses to the waiting requests and answers the server's own
+/// requests. Pending requests fail once the server exits.
+async fn read_stdio_messages(
+    stdout: ChildStdout,
+    stdin: Arc<tokio::sync::Mutex<ChildStdin>>,
+    pending: PendingRequests,
+) {
+    let mut lines = BufReader::new(stdout).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Ok(message) = serde_json::from_str::<serde_json::Value>(&line) else {
+            continue;
+        };
+
+        if message.get("method").is_none() {
+            let sender = message["id"]
+                .as_u64()
+                .and_then(|id| pending.lock().ok()?.remove(&id));
+            if let Some(sender) = sender {
+                let _ = sender.send(message);
+            }
+        } else if !message["id"].is_null() {
+            let _ = write_message(&stdin, &answer_server_request(&message)).await;
+        }
+    }
+
+    if let Ok(mut pending) = pending.lock() {
+        pending.clear();
+    }
+}
+
+fn answer_server_request(request: &serde_json::Value) -> serde_json::Value {
+    if request["method"] == "ping" {
+        return json!({ "jsonrpc": "2.0", "id": request["id"], "result": {} });
+    }
+    json!({
+        "jsonrpc": "2.0",
+        "id": request["id"],
+        "error": { "code": METHOD_NOT_FOUND, "message": "Method not supported by Cuuri" }
+    })
+}
+
+async fn write_message(
+    stdin: &tokio::sync::Mutex<ChildStdin>,
+    message: &serde_json::Value,
+) -> Result<(), String> {
+    let mut line = message.to_string();
+    line.push('\n');
+    let mut stdin = stdin.lock().await;
+    stdin
+        .write_all(line.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    stdin.flush().await.map_err(|e| e.to_string())
+}
+
+fn is_response_to(message: &serde_json::Value, id: u64) -> bool {
+    message.get("method").is_none() && message["id"].as_u64() == Some(id)
+}
+
+/// Reads server-sent events until the response to request `id` arrives.
+async fn read_event_stream_response(
+    mut res: reqwest::Response,
+    id: u64,
+) -> Result<Option<serde_json::Value>, String> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut data = String::new();
+    while let Some(chunk) = res.chunk().await.map_err(|e| e.to_string())? {
+        buffer.extend_from_slice(&chunk);
+
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+
+            if let Some(value) = line.strip_prefix("data:") {
+                if !data.is_empty() {
+                    data.push('\n');
+                }
+                data.push_str(value.strip_prefix(' ').unwrap_or(value));
+            } else if line.is_empty() && !data.is_empty() {
+                // A blank line ends the event
+                if let Ok(message) = serde_json::from_str::<serde_json::Value>(&data) {
+                    if is_response_to(&message, id) {
+                        return Ok(Some(message));
+                    }
+                }
+                data.clear();
+            }
+        }
+    }
+    Ok(None)
+}
+
+fn format_tool_content(content: &serde_json::Value) -> String {
+    let Some(items) = content.as_array() else {
+        return String::new();
+    };
+
+    items
+        .iter()
+        .map(|item| match item["type"].as_str() {
+            Some("text") => item["text"].as_str().unwrap_or("").to_string(),
+            Some("resource") => match item["resource"]["text"].as_str() {
+                Some(text) => text.to_string(),
+                None => format!(
+                    "[resource: {}]",
+                    item["resource"]["uri"].as_str().unwrap_or("")
+                ),
+            },
+            Some("resource_link") => format!("[resource: {}]", item["uri"].as_str().unwrap_or("")),
+            Some(kind) => format!(
+                "[{}: {}]",
+                kind,
+                item["mimeType"].as_str().unwrap_or("unknown")
+            ),
+            None => item.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,214 @@
+use crate::app_type::McpServerStatus;
+use crate::config::McpServerConfig;
+use crate::mcp_client::McpClient;
+use crate::tool_registry::{Tool, ToolRegistry};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+// OpenAI function names are limited to this length
+const MAX_TOOL_NAME_LEN: usize = 64;
+
+struct ConnectedServer {
+    client: Arc<McpClient>,
+    tool_names: Vec<String>,
+}
+
+/// The MCP servers from the config, connected and with their tools registered.
+#[derive(Default)]
+pub struct McpServers {
+    // Also serializes restarts, which await while holding it
+    started_with: tokio::sync::Mutex<Option<BTreeMap<String, McpServerConfig>>>,
+    connected: RwLock<Vec<ConnectedServer>>,
+    statuses: RwLock<Vec<McpServerStatus>>,
+}
+
+/// Name of an MCP tool in the registry, e.g. `docs__search`. A name that
+/// had to be changed to fit gets a hash of the original server and tool
+/// names, e.g. `my_docs__search_3f2a9c1b`, so it always refers to the same
+/// tool whatever other servers are configured.
+pub fn mcp_tool_name(server: &str, tool: &str) -> String {
+    let full = format!("{}__{}", server, tool);
+    let sanitized: String = full
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if sanitized == full && full.len() <= MAX_TOOL_NAME_LEN {
+        return full;
+    }
+
+    let digest = format!("{:x}", Sha256::digest(format!("{}\0{}", server, tool)));
+    let suffix = format!("_{}", &digest[..8]);
+    sanitized
+        .chars()
+        .take(MAX_TOOL_NAME_LEN - suffix.len())
+        .chain(suffix.chars())
+        .collect()
+}
+
+impl McpServers {
+    pub fn statuses(&self) -> Vec<McpServerStatus> {
+        self.statuses
+            .read()
+            .map(|statuses| statuses.clone())
+            .unwrap_or_default()
+    }
+
+    /// Connects to the configured servers and registers their tools, replacing
+    /// the servers started before. Does nothing if the server list is unchanged.
+    pub async fn sync(
+        &self,
+        configs: &BTreeMap<String, McpServerConfig>,
+        registry: &ToolRegistry,
+    ) -> bool {
+        let mut started_with = self.started_with.lock().await;
+        if started_with.as_ref() == Some(configs) {
+            return false;
+        }
+
+        let previous = self
+            .connected
+            .write()
+            .map(|mut connected| std::mem::take(&mut *connected))
+            .unwrap_or_default();
+        for server in previous {
+            for tool_name in &server.tool_names {
+                registry.unregister(tool_name);
+            }
+            server.client.shutdown().await;
+        }
+
+        let mut statuses = Vec::new();
+        let mut connected = Vec::new();
+        for (name, config) in configs {
+            let mut status = McpServerStatus {
+                name: name.clone(),
+                transport: if config.url.is_some() {
+                    "http"
+                } else {
+                    "stdio"
+                }
+                .to_string(),
+                enabled: config.enabled,
+                connected: false,
+                tools: Vec::new(),
+                error: None,
+            };
+
+            if config.enabled {
+                match connect_server(name, config, registry).await {
+                    Ok(server) => {
+                        status.connected = true;
+                        status.tools = server.tool_names.clone();
+                        connected.push(server);
+                    }
+                    Err(e) => {
+                        log::warn!("{}", e);
+                        status.error = Some(e);
+                    }
+                }
+            }
+            statuses.push(status);
+        }
+
+        if let Ok(mut current) = self.connected.write() {
+            *current = connected;
+        }
+        if let Ok(mut current) = self.statuses.write() {
+            *current = statuses;
+        }
+        *started_with = Some(configs.clone());
+        true
+    }
+}
+
+async fn connect_server(
+    name: &str,
+    config: &McpServerConfig,
+    registry: &ToolRegistry,
+) -> Result<ConnectedServer, String> {
+    let client = McpClient::connect(name, config).await?;
+    let tools = match client.list_tools().await {
+        Ok(tools) => tools,
+        Err(e) => {
+            client.shutdown().await;
+            return Err(e);
+        }
+    };
+
+    let client = Arc::new(client);
+    let mut tool_names = Vec::new();
+    for tool in tools {
+        let registered_name = mcp_tool_name(name, &tool.name);
+        // Only possible with `__` in a server name; the first tool keeps it
+        if registry.get(&registered_name).is_some() {
+            log::warn!(
+                "Skipped tool `{}` of MCP server `{}`: `{}` is already taken",
+                tool.name,
+                name,
+                registered_name
+            );
+            continue;
+        }
+        let description = format!(
+            "{} (from the `{}` MCP server)",
+            tool.description.as_deref().unwrap_or(&tool.name),
+            name
+        );
+
+        // What a server's tools do is unknown, so every call is confirmed
+        let tool_client = client.clone();
+        let tool_name = tool.name.clone();
+        registry.register(
+            Tool::new(
+                &registered_name,
+                &description,
+                tool.input_schema,
+                move |arguments, _context| {
+                    let client = tool_client.clone();
+                    let tool_name = tool_name.clone();
+                    async move { client.call_tool(&tool_name, arguments).await }
+                },
+            )
+            .requiring_approval(),
+        );
+        tool_names.push(registered_name);
+    }
+
+    Ok(ConnectedServer { client, tool_names })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitizes_tool_names() {
+        assert_eq!(mcp_tool_name("docs", "search-v2"), "docs__search-v2");
+        let name = mcp_tool_name("my docs", "search.v2");
+        assert!(name.starts_with("my_docs__search_v2_"), "{}", name);
+    }
+
+    #[test]
+    fn changed_names_depend_only_on_their_server_and_tool() {
+        let spaced = mcp_tool_name("my docs", "search");
+        let dotted = mcp_tool_name("my.docs", "search");
+        assert!(spaced.starts_with("my_docs__search_"), "{}", spaced);
+        assert_ne!(spaced, dotted);
+        assert_eq!(spaced, mcp_tool_name("my docs", "search"));
+    }
+
+    #[test]
+    fn shortened_names_stay_within_the_limit() {
+        let long_tool = "x".repeat(100);
+        let name = mcp_tool_name("docs", &long_tool);
+        assert_eq!(name.len(), MAX_TOOL_NAME_LEN);
+        assert_ne!(name, mcp_tool_name("docs", &format!("{}y", long_tool)));
+    }
+}
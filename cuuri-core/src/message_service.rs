@@ -0,0 +1,131 @@
+use crate::app_type::ChatResponse;
+use crate::attachments::attachment_dir;
+use crate::chat_events::ChatEvents;
+use crate::chat_exchange::{complete_chat, stream_chat, ChatRequest};
+use crate::cuuri::Cuuri;
+use crate::documents::{extract_document_from_bytes, ExtractedDocument};
+use crate::tool_approvals::ApprovalDecision;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use std::fs;
+use std::path::Path;
+use tokio::sync::oneshot;
+
+impl Cuuri {
+    /// Sends a question and streams the answer to `events`. The question is
+    /// stored first and the answer as it arrives, so whatever was received
+    /// is kept when the answer fails or is cancelled.
+    pub async fn stream_message(
+        &self,
+        request: ChatRequest,
+        events: &dyn ChatEvents,
+    ) -> Result<ChatResponse, String> {
+        let provider = self.provider(Some(&request.api_key));
+        let response = stream_chat(
+            self.db(),
+            self.config(),
+            self.streams(),
+            self.tools(),
+            &provider,
+            events,
+            request,
+        )
+        .await?;
+        self.wake_chat_embedder();
+        Ok(response)
+    }
+
+    /// Like `stream_message`, but waits for the whole answer. It can be
+    /// cancelled with `cancel_message` all the same.
+    pub async fn complete_message(
+        &self,
+        request: ChatRequest,
+        events: &dyn ChatEvents,
+    ) -> Result<ChatResponse, String> {
+        let provider = self.provider(Some(&request.api_key));
+        let response = complete_chat(
+            self.db(),
+            self.config(),
+            self.streams(),
+            self.tools(),
+            &provider,
+            events,
+            request,
+        )
+        .await?;
+        self.wake_chat_embedder();
+        Ok(response)
+    }
+
+    /// Stops the answer being given in `session_id`, returning whether there was one.
+    pub fn cancel_message(&self, session_id: &str) -> bool {
+        self.streams().cancel(session_id)
+    }
+
+    /// Waits for `approve_tool_call` or `deny_tool_call` on `request_id`.
+    pub fn register_tool_approval(
+        &self,
+        request_id: &str,
+        tool_name: &str,
+    ) -> oneshot::Receiver<ApprovalDecision> {
+        self.approvals().register(request_id, tool_name)
+    }
+
+    /// Forgets an approval request nobody waits for anymore.
+    pub fn forget_tool_approval(&self, request_id: &str) {
+        self.approvals().remove(request_id);
+    }
+
+    /// Lets a waiting tool call run, returning the name of the tool.
+    pub fn approve_tool_call(&self, request_id: &str) -> Result<String, String> {
+        self.approvals()
+            .resolve(request_id, ApprovalDecision::Approved)
+    }
+
+    /// Refuses a waiting tool call; the model is told it was denied, with the
+    /// optional reason.
+    pub fn deny_tool_call(&self, request_id: &str, reason: Option<String>) -> Result<(), String> {
+        self.approvals()
+            .resolve(request_id, ApprovalDecision::Denied(reason))
+            .map(|_| ())
+    }
+
+    /// Extracts the text of a file to attach to a question, given either its
+    /// path or its bytes.
+    pub async fn extract_document(
+        &self,
+        file_path: Option<String>,
+        file_name: Option<String>,
+        base64_data: Option<String>,
+    ) -> Result<ExtractedDocument, String> {
+        let dir = self.db().run(attachment_dir).await?;
+        tokio::task::spawn_blocking(move || {
+            let (name, data) = match (file_path, base64_data) {
+                (Some(path), _) => {
+                    let data =
+                        fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+                    let name = file_name.unwrap_or_else(|| {
+                        Path::new(&path)
+                            .file_name()
+                            .map(|n| n.to_string_lossy().to_string())
+                            .unwrap_or(path.clone())
+                    });
+                    (name, data)
+                }
+                (None, Some(encoded)) => {
+                    let data = STANDARD
+                        .decode(encoded)
+                        .map_err(|e| format!("Invalid file data: {}", e))?;
+                    (file_name.unwrap_or_else(|| "attachment".to_string()), data)
+                }
+                (None, None) => {
+                    return Err("Either a file path or file data is required".to_string())
+                }
+            };
+
+            extract_document_from_bytes(&dir, &name, data)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+}
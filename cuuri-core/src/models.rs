@@ -0,0 +1,141 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+#[derive(Queryable, Insertable)]
+#[diesel(table_name = crate::schema::chat_histories)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ChatHistory {
+    pub id: i32,
+    pub session_id: String,
+    pub question: String,
+    pub answer: String,
+    pub created_at: NaiveDateTime,
+    pub status: String,
+    pub model: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::chat_histories)]
+pub struct NewChatHistory<'a> {
+    pub session_id: &'a String,
+    pub question: &'a String,
+    pub answer: &'a String,
+    pub created_at: NaiveDateTime,
+    pub status: &'a str,
+    pub model: Option<&'a str>,
+}
+
+#[derive(Queryable, Clone)]
+#[diesel(table_name = crate::schema::attachments)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct Attachment {
+    pub id: i32,
+    pub chat_history_id: i32,
+    pub kind: String,
+    pub mime_type: String,
+    pub file_name: Option<String>,
+    pub content_hash: String,
+    pub created_at: NaiveDateTime,
+    pub extracted_text: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::attachments)]
+pub struct NewAttachment<'a> {
+    pub chat_history_id: i32,
+    pub kind: &'a str,
+    pub mime_type: &'a str,
+    pub file_name: Option<&'a str>,
+    pub content_hash: &'a str,
+    pub created_at: NaiveDateTime,
+    pub extracted_text: Option<&'a str>,
+}
+
+#[derive(Queryable, Clone)]
+#[diesel(table_name = crate::schema::tool_calls)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct ToolCallRecord {
+    pub id: i32,
+    pub chat_history_id: i32,
+    pub round: i32,
+    pub call_id: String,
+    pub name: String,
+    pub arguments: String,
+    pub result: String,
+    pub is_error: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::tool_calls)]
+pub struct NewToolCallRecord<'a> {
+    pub chat_history_id: i32,
+    pub round: i32,
+    pub call_id: &'a str,
+    pub name: &'a str,
+    pub arguments: &'a str,
+    pub result: &'a str,
+    pub is_error: bool,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Queryable, Clone)]
+#[diesel(table_name = crate::schema::indexed_folders)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct IndexedFolder {
+    pub id: i32,
+    pub path: String,
+    pub embedding_model: String,
+    pub created_at: NaiveDateTime,
+    pub indexed_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::indexed_folders)]
+pub struct NewIndexedFolder<'a> {
+    pub path: &'a str,
+    pub embedding_model: &'a str,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::indexed_files)]
+pub struct NewIndexedFile<'a> {
+    pub folder_id: i32,
+    pub file_path: &'a str,
+    pub file_hash: &'a str,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::document_chunks)]
+pub struct NewDocumentChunk<'a> {
+    pub folder_id: i32,
+    pub file_path: &'a str,
+    pub file_hash: &'a str,
+    pub chunk_index: i32,
+    pub start_line: i32,
+    pub end_line: i32,
+    pub content: &'a str,
+    pub embedding: &'a [u8],
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::chat_embeddings)]
+pub struct NewChatEmbedding<'a> {
+    pub chat_history_id: i32,
+    pub embedding_model: &'a str,
+    pub embedding: &'a [u8],
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = crate::schema::imported_sessions)]
+pub struct NewImportedSession<'a> {
+    pub session_id: &'a str,
+    pub source: &'a str,
+    pub source_id: &'a str,
+    pub title: Option<&'a str>,
+    pub source_updated_at: NaiveDateTime,
+    pub imported_at: NaiveDateTime,
+}
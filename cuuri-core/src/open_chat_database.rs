@@ -0,0 +1,78 @@
+use crate::chat_record::recover_pending_chats;
+use crate::config::Config;
+use crate::database_encryption::{find_database_key, DatabaseKey};
+use crate::db_pool::{create_pool, DatabaseState, DbPool};
+use crate::get_database_path::get_database_file;
+use crate::profile::get_active_profile_name;
+use crate::run_migrations::migrate_database;
+use std::fs;
+use std::path::Path;
+
+fn open_pool(config: &Config, path: &Path, key: Option<&DatabaseKey>) -> Result<DbPool, String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    let database_url = format!("sqlite://{}", path.to_string_lossy());
+    let pool = create_pool(&database_url, key.cloned())?;
+    let mut connection = pool
+        .get()
+        .map_err(|e| format!("Failed to open the chat database: {}", e))?;
+    migrate_database(&mut connection, config, path, key)?;
+    Ok(pool)
+}
+
+/// Opens the active profile's database and brings it up to date. Without a
+/// `key`, the one in the OS keyring or `CUURI_DB_PASSPHRASE` is used, and an
+/// encrypted database without either stays locked. Failures are returned as
+/// a state, so the app can still start and offer a way to recover.
+pub fn open_chat_database(config: &Config, key: Option<DatabaseKey>) -> DatabaseState {
+    let file = match get_database_file() {
+        Ok(file) => file,
+        Err(e) => return failed(format!("Failed to get database path: {}", e), key),
+    };
+    let key = match key {
+        Some(key) => Some(key),
+        None => match find_database_key(&get_active_profile_name(), &file) {
+            Ok(key) => key,
+            Err(e) => {
+                log::warn!("{}", e);
+                return DatabaseState::Locked;
+            }
+        },
+    };
+
+    open_chat_database_at(config, &file, key)
+}
+
+/// Opens the database at `path` instead of the active profile's, e.g. a
+/// temporary one in tests, creating and migrating it as needed.
+pub fn open_chat_database_at(
+    config: &Config,
+    path: &Path,
+    key: Option<DatabaseKey>,
+) -> DatabaseState {
+    match open_pool(config, path, key.as_ref()) {
+        Ok(pool) => DatabaseState::Open { pool, key },
+        Err(message) => failed(message, key),
+    }
+}
+
+fn failed(message: String, key: Option<DatabaseKey>) -> DatabaseState {
+    log::error!("{}", message);
+    DatabaseState::Failed { message, key }
+}
+
+/// Marks answers that were still arriving when the app last stopped as
+/// failed. Only safe while nothing is streaming into the database.
+pub fn recover_interrupted_chats(pool: &DbPool) {
+    let recovered = pool
+        .get()
+        .map_err(|e| e.to_string())
+        .and_then(|mut connection| recover_pending_chats(&mut connection));
+    match recovered {
+        Ok(0) => {}
+        Ok(count) => log::info!("Marked {} interrupted chat(s) as failed", count),
+        Err(e) => log::error!("Failed to recover pending chats: {}", e),
+    }
+}
@@ -0,0 +1,158 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use serde::Deserialize;
+use std::io::Cursor;
+
+// Provider limits: high detail images are scaled to fit 2048x2048 and then
+// to a shortest side of 768; low detail images are read at 512x512
+const MAX_LONG_SIDE: u32 = 2048;
+const MAX_SHORT_SIDE: u32 = 768;
+const LOW_DETAIL_SIDE: u32 = 512;
+const JPEG_QUALITY: u8 = 85;
+
+/// The `detail` level requested for images sent to the model.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageDetail {
+    #[default]
+    Auto,
+    Low,
+    High,
+}
+
+impl ImageDetail {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ImageDetail::Auto => "auto",
+            ImageDetail::Low => "low",
+            ImageDetail::High => "high",
+        }
+    }
+}
+
+/// An image whose format has been verified and whose size fits the provider limits.
+pub struct PreparedImage {
+    pub mime_type: &'static str,
+    pub data: Vec<u8>,
+}
+
+fn mime_type_of(format: ImageFormat) -> Option<&'static str> {
+    match format {
+        ImageFormat::Png => Some("image/png"),
+        ImageFormat::Jpeg => Some("image/jpeg"),
+        ImageFormat::Gif => Some("image/gif"),
+        ImageFormat::WebP => Some("image/webp"),
+        _ => None,
+    }
+}
+
+fn target_size(width: u32, height: u32, detail: ImageDetail) -> (u32, u32) {
+    let long_side = width.max(height) as f64;
+    let short_side = width.min(height) as f64;
+
+    let scale = match detail {
+        ImageDetail::Low => (LOW_DETAIL_SIDE as f64 / long_side).min(1.0),
+        ImageDetail::Auto | ImageDetail::High => {
+            let fit_long = (MAX_LONG_SIDE as f64 / long_side).min(1.0);
+            (MAX_SHORT_SIDE as f64 / (short_side * fit_long)).min(1.0) * fit_long
+        }
+    };
+
+    if scale >= 1.0 {
+        return (width, height);
+    }
+    (
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+fn encode(image: &DynamicImage, source_format: ImageFormat) -> Result<PreparedImage, String> {
+    let mut data = Vec::new();
+
+    // Photos stay JPEG; everything else becomes PNG, which every provider accepts
+    if source_format == ImageFormat::Jpeg {
+        let encoder = JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY);
+        image
+            .to_rgb8()
+            .write_with_encoder(encoder)
+            .map_err(|e| format!("Failed to encode image: {}", e))?;
+        return Ok(PreparedImage {
+            mime_type: "image/jpeg",
+            data,
+        });
+    }
+
+    image
+        .write_to(&mut Cursor::new(&mut data), ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode image: {}", e))?;
+    Ok(PreparedImage {
+        mime_type: "image/png",
+        data,
+    })
+}
+
+/// Detects the real format of `data` and downscales it if it exceeds the limits for `detail`.
+pub fn prepare_image(data: Vec<u8>, detail: ImageDetail) -> Result<PreparedImage, String> {
+    let format = image::guess_format(&data)
+        .map_err(|_| "Unsupported image: the file is not a recognized image format".to_string())?;
+    let mime_type = mime_type_of(format).ok_or_else(|| {
+        format!(
+            "Unsupported image format {:?}: only PNG, JPEG, GIF and WebP are accepted",
+            format
+        )
+    })?;
+
+    let image = image::load_from_memory_with_format(&data, format)
+        .map_err(|e| format!("Failed to decode {} image: {}", mime_type, e))?;
+
+    let (width, height) = image.dimensions();
+    let (target_width, target_height) = target_size(width, height, detail);
+    if (target_width, target_height) == (width, height) {
+        // Small enough already, so send the original bytes untouched
+        return Ok(PreparedImage { mime_type, data });
+    }
+
+    let resized = image.resize(target_width, target_height, FilterType::Lanczos3);
+    encode(&resized, format)
+}
+
+/// Decodes and prepares images received from the frontend as base64 strings.
+pub fn prepare_base64_images(
+    base64_images: &[String],
+    detail: ImageDetail,
+) -> Result<Vec<PreparedImage>, String> {
+    base64_images
+        .iter()
+        .map(|image_data| {
+            let data = STANDARD
+                .decode(image_data)
+                .map_err(|e| format!("Invalid image data: {}", e))?;
+            prepare_image(data, detail)
+        })
+        .collect()
+}
+
+impl PreparedImage {
+    pub fn to_data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime_type, STANDARD.encode(&self.data))
+    }
+}
+
+/// Runs `prepare_base64_images` off the async runtime, since decoding and resizing are CPU bound.
+pub async fn prepare_images(
+    base64_images: Option<Vec<String>>,
+    detail: ImageDetail,
+) -> Result<Vec<PreparedImage>, String> {
+    let base64_images = base64_images.unwrap_or_default();
+    if base64_images.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    tokio::task::spawn_blocking(move || prepare_base64_images(&base64_images, detail))
+        .await
+        .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,118 @@
+use crate::config_overrides::{profile_override, ConfigSource, Resolved};
+use crate::get_data_dir::get_data_dir;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+pub const DEFAULT_PROFILE: &str = "default";
+
+const ACTIVE_PROFILE_FILE: &str = "active_profile";
+const PROFILES_DIR: &str = "profiles";
+
+// Set when the user switches profiles while the app is running
+static SWITCHED_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+pub fn validate_profile_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > 64 {
+        return Err("Profile name must be between 1 and 64 characters".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid profile name `{}`: only letters, digits, '-' and '_' are allowed",
+            name
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the directory holding the config file and database of a profile.
+///
+/// The default profile lives directly in the data directory so that
+/// installations from before profiles existed keep their data.
+pub fn get_profile_dir_for(name: &str) -> Result<PathBuf, io::Error> {
+    let data_dir = get_data_dir()?;
+    if name == DEFAULT_PROFILE {
+        return Ok(data_dir);
+    }
+    Ok(data_dir.join(PROFILES_DIR).join(name))
+}
+
+fn read_persisted_profile() -> Option<String> {
+    let path = get_data_dir().ok()?.join(ACTIVE_PROFILE_FILE);
+    let name = fs::read_to_string(path).ok()?.trim().to_string();
+    validate_profile_name(&name).ok().map(|_| name)
+}
+
+pub fn resolve_active_profile() -> Resolved<String> {
+    if let Some(name) = SWITCHED_PROFILE.read().ok().and_then(|p| p.clone()) {
+        return Resolved {
+            value: name,
+            source: ConfigSource::ConfigFile,
+        };
+    }
+
+    if let Some(resolved) = profile_override() {
+        return resolved;
+    }
+
+    match read_persisted_profile() {
+        Some(name) => Resolved {
+            value: name,
+            source: ConfigSource::ConfigFile,
+        },
+        None => Resolved {
+            value: DEFAULT_PROFILE.to_string(),
+            source: ConfigSource::Default,
+        },
+    }
+}
+
+pub fn get_active_profile_name() -> String {
+    resolve_active_profile().value
+}
+
+pub fn get_profile_dir() -> Result<PathBuf, io::Error> {
+    get_profile_dir_for(&get_active_profile_name())
+}
+
+pub fn list_profile_names() -> Result<Vec<String>, io::Error> {
+    let mut names = vec![DEFAULT_PROFILE.to_string()];
+
+    let profiles_dir = get_data_dir()?.join(PROFILES_DIR);
+    if profiles_dir.is_dir() {
+        let mut named: Vec<String> = fs::read_dir(profiles_dir)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| validate_profile_name(name).is_ok() && name != DEFAULT_PROFILE)
+            .collect();
+        named.sort();
+        names.extend(named);
+    }
+
+    Ok(names)
+}
+
+/// Makes `name` the active profile until the app stops, without saving it,
+/// and returns the one it replaces. `None` goes back to the saved profile.
+pub fn replace_switched_profile(name: Option<String>) -> Option<String> {
+    match SWITCHED_PROFILE.write() {
+        Ok(mut switched) => std::mem::replace(&mut *switched, name),
+        Err(_) => None,
+    }
+}
+
+pub fn set_active_profile(name: &str) -> Result<(), io::Error> {
+    let data_dir = get_data_dir()?;
+    fs::create_dir_all(&data_dir)?;
+    fs::write(data_dir.join(ACTIVE_PROFILE_FILE), name)?;
+
+    if let Ok(mut switched) = SWITCHED_PROFILE.write() {
+        *switched = Some(name.to_string());
+    }
+    Ok(())
+}
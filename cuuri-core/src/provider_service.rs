@@ -0,0 +1,92 @@
+use crate::config::Config;
+use crate::cuuri::Cuuri;
+
+pub const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+
+/// An OpenAI-compatible API and the key to call it with.
+#[derive(Clone)]
+pub struct Provider {
+    base_url: String,
+    api_key: String,
+    client: reqwest::Client,
+}
+
+impl Provider {
+    pub fn new(base_url: &str, api_key: &str) -> Self {
+        Provider {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: api_key.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn openai(api_key: &str) -> Self {
+        Provider::new(OPENAI_API_BASE, api_key)
+    }
+
+    /// The configured provider and key.
+    pub fn from_config(config: &Config) -> Self {
+        Provider::with_key(config, &config.openai_api_key)
+    }
+
+    /// The configured provider, called with `api_key`.
+    pub fn with_key(config: &Config, api_key: &str) -> Self {
+        match &config.openai_base_url {
+            Some(base_url) => Provider::new(base_url, api_key),
+            None => Provider::openai(api_key),
+        }
+    }
+
+    pub fn has_api_key(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
+    /// A GET request to `path`, e.g. `models`, with the key attached.
+    pub fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .get(format!("{}/{}", self.base_url, path))
+            .bearer_auth(&self.api_key)
+    }
+
+    /// A POST request to `path`, e.g. `chat/completions`, with the key attached.
+    pub fn post(&self, path: &str) -> reqwest::RequestBuilder {
+        self.client
+            .post(format!("{}/{}", self.base_url, path))
+            .bearer_auth(&self.api_key)
+    }
+
+    /// The ids of the models the key can use, sorted.
+    pub async fn list_models(&self) -> Result<Vec<String>, String> {
+        let res = self.get("models").send().await.map_err(|e| e.to_string())?;
+
+        if !res.status().is_success() {
+            return Err(format!("Failed to fetch models: HTTP {}", res.status()));
+        }
+
+        let json: serde_json::Value = res.json().await.map_err(|e| e.to_string())?;
+        let mut models: Vec<String> = json["data"]
+            .as_array()
+            .ok_or("Invalid response format")?
+            .iter()
+            .filter_map(|model| model["id"].as_str().map(|s| s.to_string()))
+            .collect();
+
+        models.sort();
+
+        Ok(models)
+    }
+}
+
+impl Cuuri {
+    /// The provider, called with `api_key`, or the configured key without one.
+    pub fn provider(&self, api_key: Option<&str>) -> Provider {
+        match api_key {
+            Some(api_key) => Provider::with_key(&self.config(), api_key),
+            None => Provider::from_config(&self.config()),
+        }
+    }
+
+    pub async fn available_models(&self, api_key: &str) -> Result<Vec<String>, String> {
+        self.provider(Some(api_key)).list_models().await
+    }
+}
@@ -0,0 +1,137 @@
+use crate::chat_record::{insert_chat, ChatStatus};
+use crate::models::NewChatHistory;
+use chrono::Utc;
+use diesel::SqliteConnection;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+// How many conversations can be continued in their session
+const MAX_TRACKED_CONVERSATIONS: usize = 1000;
+
+/// The text of a message, whose `content` is a string or a list of parts.
+pub fn message_text(message: &serde_json::Value) -> String {
+    match &message["content"] {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// The last question of a request sent through the proxy, and the
+/// conversation before it.
+pub struct ProxiedQuestion {
+    pub question: String,
+    // The user and assistant messages with text, which identify the
+    // conversation; system prompts and tool messages vary between requests
+    turns: Vec<(String, String)>,
+}
+
+impl ProxiedQuestion {
+    /// Finds the question in the `messages` of a chat completion request.
+    /// Requests without a user message have nothing to record.
+    pub fn from_messages(messages: &[serde_json::Value]) -> Option<Self> {
+        let mut turns: Vec<(String, String)> = messages
+            .iter()
+            .filter_map(|message| {
+                let role = message["role"].as_str()?;
+                let text = message_text(message);
+                match role {
+                    "user" | "assistant" if !text.is_empty() => Some((role.to_string(), text)),
+                    _ => None,
+                }
+            })
+            .collect();
+
+        let last_question = turns.iter().rposition(|(role, _)| role == "user")?;
+        turns.truncate(last_question + 1);
+        Some(ProxiedQuestion {
+            question: turns[last_question].1.clone(),
+            turns,
+        })
+    }
+
+    fn key_before(&self) -> String {
+        conversation_key(&self.turns[..self.turns.len() - 1])
+    }
+
+    fn key_after(&self, answer: &str) -> String {
+        let mut turns = self.turns.clone();
+        turns.push(("assistant".to_string(), answer.to_string()));
+        conversation_key(&turns)
+    }
+}
+
+fn conversation_key(turns: &[(String, String)]) -> String {
+    let mut hasher = Sha256::new();
+    for (role, text) in turns {
+        hasher.update(role.as_bytes());
+        hasher.update([0]);
+        hasher.update((text.len() as u64).to_le_bytes());
+        hasher.update(text.as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Sessions of the conversations recorded through the proxy. Clients send
+/// the whole conversation with every request, so a request that repeats a
+/// recorded conversation continues its session.
+#[derive(Default)]
+pub struct ProxySessions {
+    sessions: Mutex<(HashMap<String, String>, VecDeque<String>)>,
+}
+
+impl ProxySessions {
+    /// The session `question` continues, or a new one.
+    pub fn session_for(&self, question: &ProxiedQuestion) -> String {
+        let key = question.key_before();
+        self.sessions
+            .lock()
+            .ok()
+            .and_then(|sessions| sessions.0.get(&key).cloned())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+    }
+
+    /// Lets the next request of the conversation continue `session_id`.
+    pub fn remember(&self, question: &ProxiedQuestion, answer: &str, session_id: &str) {
+        let key = question.key_after(answer);
+        if let Ok(mut sessions) = self.sessions.lock() {
+            let (by_key, order) = &mut *sessions;
+            if by_key.insert(key.clone(), session_id.to_string()).is_none() {
+                order.push_back(key);
+            }
+            while order.len() > MAX_TRACKED_CONVERSATIONS {
+                if let Some(oldest) = order.pop_front() {
+                    by_key.remove(&oldest);
+                }
+            }
+        }
+    }
+}
+
+/// Stores a question and answer that went through the proxy.
+pub fn record_proxied_chat(
+    conn: &mut SqliteConnection,
+    session_id: &String,
+    question: &String,
+    answer: &String,
+    model: &str,
+    status: ChatStatus,
+) -> Result<(), String> {
+    insert_chat(
+        conn,
+        &NewChatHistory {
+            session_id,
+            question,
+            answer,
+            created_at: Utc::now().naive_utc(),
+            status: status.as_str(),
+            model: Some(model),
+        },
+    )
+    .map(|_| ())
+}
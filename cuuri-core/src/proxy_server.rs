@@ -0,0 +1,578 @@
+use crate::active_streams::CancelHandle;
+use crate::app_type::ProxyStatus;
+use crate::chat_record::ChatStatus;
+use crate::config::Config;
+use crate::cuuri::Cuuri;
+use crate::event_stream::EventStreamDecoder;
+use crate::profile::get_profile_dir;
+use crate::provider_service::Provider;
+use crate::proxy_chats::{record_proxied_chat, ProxiedQuestion, ProxySessions};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full, Limited};
+use hyper::body::{Body, Bytes, Frame, Incoming};
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::json;
+use std::convert::Infallible;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+
+const PROXY_TOKEN_FILE: &str = "proxy_token";
+// Requests carry the whole conversation, including any images
+const MAX_REQUEST_BYTES: usize = 64 * 1024 * 1024;
+
+type ProxyBody = BoxBody<Bytes, Infallible>;
+
+/// Streams the chunks of an answer to the client as they arrive.
+struct ChannelBody(mpsc::Receiver<Bytes>);
+
+impl Body for ChannelBody {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+        self.0
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|chunk| Ok(Frame::data(chunk))))
+    }
+}
+
+struct RunningProxy {
+    port: u16,
+    token: String,
+    shutdown: oneshot::Sender<()>,
+}
+
+/// The local server that lets other programs use the configured provider
+/// through Cuuri, so their chats end up in the history.
+#[derive(Default)]
+pub struct ProxyServer {
+    // Also serializes restarts, which await while holding it
+    running: tokio::sync::Mutex<Option<RunningProxy>>,
+    status: RwLock<ProxyStatus>,
+    sessions: Arc<ProxySessions>,
+}
+
+struct ProxyContext {
+    cuuri: Arc<Cuuri>,
+    token: String,
+    sessions: Arc<ProxySessions>,
+}
+
+fn proxy_token_file() -> Result<PathBuf, String> {
+    Ok(get_profile_dir()
+        .map_err(|e| e.to_string())?
+        .join(PROXY_TOKEN_FILE))
+}
+
+fn write_proxy_token(path: &Path, token: &str) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // The token lets anyone spend the API key, so only the owner may read it
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .and_then(|mut file| file.write_all(token.as_bytes()))
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+fn generate_proxy_token() -> Result<String, String> {
+    let mut bytes = [0u8; 24];
+    getrandom::fill(&mut bytes).map_err(|e| format!("Failed to generate a token: {}", e))?;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Ok(format!("cuuri-{}", hex))
+}
+
+/// The token of the active profile, generated on first use.
+pub fn load_proxy_token() -> Result<String, String> {
+    let path = proxy_token_file()?;
+    if let Ok(token) = fs::read_to_string(&path) {
+        let token = token.trim();
+        if !token.is_empty() {
+            return Ok(token.to_string());
+        }
+    }
+    let token = generate_proxy_token()?;
+    write_proxy_token(&path, &token)?;
+    Ok(token)
+}
+
+/// Replaces the token, so clients given the old one are shut out.
+pub fn replace_proxy_token() -> Result<String, String> {
+    let token = generate_proxy_token()?;
+    write_proxy_token(&proxy_token_file()?, &token)?;
+    Ok(token)
+}
+
+impl ProxyServer {
+    pub fn status(&self) -> ProxyStatus {
+        self.status
+            .read()
+            .map(|status| status.clone())
+            .unwrap_or_default()
+    }
+
+    fn set_status(&self, status: ProxyStatus) {
+        if let Ok(mut current) = self.status.write() {
+            *current = status;
+        }
+    }
+
+    /// Starts, stops or restarts the server to match `config` and the
+    /// active profile's token.
+    pub async fn sync(&self, cuuri: &Arc<Cuuri>, config: &Config) {
+        let mut running = self.running.lock().await;
+        let token = if config.proxy_enabled {
+            match load_proxy_token() {
+                Ok(token) => Some(token),
+                Err(error) => {
+                    if let Some(previous) = running.take() {
+                        let _ = previous.shutdown.send(());
+                    }
+                    self.set_status(ProxyStatus {
+                        enabled: true,
+                        error: Some(error),
+                        ..ProxyStatus::default()
+                    });
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        if let (Some(current), Some(token)) = (running.as_ref(), token.as_ref()) {
+            if current.port == config.proxy_port && current.token == *token {
+                return;
+            }
+        }
+        if let Some(previous) = running.take() {
+            let _ = previous.shutdown.send(());
+        }
+        let Some(token) = token else {
+            self.set_status(ProxyStatus::default());
+            return;
+        };
+
+        let port = config.proxy_port;
+        // Only programs on this machine can connect
+        let listener = match TcpListener::bind(("127.0.0.1", port)).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                let error = format!("Failed to listen on port {}: {}", port, e);
+                log::error!("{}", error);
+                self.set_status(ProxyStatus {
+                    enabled: true,
+                    token: Some(token),
+                    error: Some(error),
+                    ..ProxyStatus::default()
+                });
+                return;
+            }
+        };
+
+        let (shutdown, stopped) = oneshot::channel();
+        let context = Arc::new(ProxyContext {
+            cuuri: cuuri.clone(),
+            token: token.clone(),
+            sessions: self.sessions.clone(),
+        });
+        tokio::spawn(serve(listener, context, stopped));
+
+        *running = Some(RunningProxy {
+            port,
+            token: token.clone(),
+            shutdown,
+        });
+        self.set_status(ProxyStatus {
+            enabled: true,
+            running: true,
+            base_url: Some(format!("http://127.0.0.1:{}/v1", port)),
+            token: Some(token),
+            error: None,
+        });
+    }
+}
+
+async fn serve(
+    listener: TcpListener,
+    context: Arc<ProxyContext>,
+    mut stopped: oneshot::Receiver<()>,
+) {
+    loop {
+        let stream = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    log::warn!("Proxy server failed to accept a connection: {}", e);
+                    continue;
+                }
+            },
+            _ = &mut stopped => return,
+        };
+
+        let context = context.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle_request(context.clone(), request));
+            if let Err(e) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                log::warn!("Proxy connection failed: {}", e);
+            }
+        });
+    }
+}
+
+fn full_body(bytes: impl Into<Bytes>) -> ProxyBody {
+    Full::new(bytes.into()).boxed()
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<ProxyBody> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(full_body(body.to_string()))
+        .unwrap_or_default()
+}
+
+/// An error in the format OpenAI clients expect.
+fn error_response(status: StatusCode, message: &str, code: &str) -> Response<ProxyBody> {
+    json_response(
+        status,
+        json!({ "error": { "message": message, "type": "cuuri_proxy_error", "code": code } }),
+    )
+}
+
+/// Hands a provider response to the client unchanged.
+fn relay_response(
+    status: reqwest::StatusCode,
+    content_type: Option<String>,
+    body: Bytes,
+) -> Response<ProxyBody> {
+    Response::builder()
+        .status(status.as_u16())
+        .header(
+            CONTENT_TYPE,
+            content_type.unwrap_or_else(|| "application/json".to_string()),
+        )
+        .body(full_body(body))
+        .unwrap_or_default()
+}
+
+fn content_type(res: &reqwest::Response) -> Option<String> {
+    res.headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+}
+
+// Compares every byte, so the time taken does not reveal how much of a
+// guessed token was right
+fn token_matches(given: &str, token: &str) -> bool {
+    given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |difference, (a, b)| difference | (a ^ b))
+            == 0
+}
+
+async fn handle_request(
+    context: Arc<ProxyContext>,
+    request: Request<Incoming>,
+) -> Result<Response<ProxyBody>, Infallible> {
+    let authorized = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| token_matches(given.trim(), &context.token));
+    if !authorized {
+        return Ok(error_response(
+            StatusCode::UNAUTHORIZED,
+            "Use the token shown in Cuuri as the API key",
+            "invalid_api_key",
+        ));
+    }
+
+    let config = context.cuuri.config();
+    if config.openai_api_key.is_empty() {
+        return Ok(error_response(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "No OpenAI API key is set in Cuuri",
+            "missing_api_key",
+        ));
+    }
+
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::GET, "/v1/models") => list_models(&config).await,
+        (&Method::POST, "/v1/chat/completions") => {
+            chat_completions(context, &config, request).await
+        }
+        (_, "/v1/models" | "/v1/chat/completions") => error_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "Method not allowed",
+            "method_not_allowed",
+        ),
+        (_, path) => error_response(
+            StatusCode::NOT_FOUND,
+            &format!("Unknown endpoint {}", path),
+            "unknown_url",
+        ),
+    };
+    Ok(response)
+}
+
+async fn list_models(config: &Config) -> Response<ProxyBody> {
+    let res = match Provider::from_config(config).get("models").send().await {
+        Ok(res) => res,
+        Err(e) => {
+            return error_response(
+                StatusCode::BAD_GATEWAY,
+                &format!("Failed to reach the provider: {}", e),
+                "provider_unreachable",
+            )
+        }
+    };
+
+    let status = res.status();
+    let content_type = content_type(&res);
+    match res.bytes().await {
+        Ok(body) => relay_response(status, content_type, body),
+        Err(e) => error_response(
+            StatusCode::BAD_GATEWAY,
+            &format!("Failed to read the provider response: {}", e),
+            "provider_unreachable",
+        ),
+    }
+}
+
+/// What was asked through the proxy, recorded once the answer is known.
+/// Requests that only make the model call the client's tools have no answer
+/// and are not recorded.
+struct Exchange {
+    context: Arc<ProxyContext>,
+    question: ProxiedQuestion,
+    session_id: String,
+    model: String,
+    // Tracked with the app's answers, so the database is not swapped or
+    // restored under it
+    stream_id: String,
+    stream: Arc<CancelHandle>,
+}
+
+impl Exchange {
+    fn new(context: Arc<ProxyContext>, question: ProxiedQuestion, model: String) -> Self {
+        // Its own id, so it never supersedes an answer the app streams in the same session
+        let stream_id = format!("proxy-{}", uuid::Uuid::new_v4());
+        let stream = context.cuuri.streams().register(&stream_id);
+        Exchange {
+            session_id: context.sessions.session_for(&question),
+            question,
+            model,
+            stream_id,
+            stream,
+            context,
+        }
+    }
+
+    async fn record(self, answer: String, status: ChatStatus) {
+        self.save(answer, status).await;
+        self.context
+            .cuuri
+            .streams()
+            .remove(&self.stream_id, &self.stream);
+    }
+
+    async fn save(&self, answer: String, status: ChatStatus) {
+        if status == ChatStatus::Complete && answer.is_empty() {
+            return;
+        }
+
+        let session_id = self.session_id.clone();
+        let question = self.question.question.clone();
+        let recorded_answer = answer.clone();
+        let model = self.model.clone();
+        let result = self
+            .context
+            .cuuri
+            .db()
+            .run(move |conn| {
+                record_proxied_chat(
+                    conn,
+                    &session_id,
+                    &question,
+                    &recorded_answer,
+                    &model,
+                    status,
+                )
+            })
+            .await;
+        if let Err(e) = result {
+            log::error!("Failed to record a proxied chat: {}", e);
+            return;
+        }
+
+        if status == ChatStatus::Complete {
+            self.context
+                .sessions
+                .remember(&self.question, &answer, &self.session_id);
+            self.context.cuuri.wake_chat_embedder();
+        }
+    }
+}
+
+async fn chat_completions(
+    context: Arc<ProxyContext>,
+    config: &Config,
+    request: Request<Incoming>,
+) -> Response<ProxyBody> {
+    let body = match Limited::new(request.into_body(), MAX_REQUEST_BYTES)
+        .collect()
+        .await
+    {
+        Ok(body) => body.to_bytes(),
+        Err(e) => {
+            return error_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                &format!("Failed to read the request: {}", e),
+                "invalid_request",
+            )
+        }
+    };
+    let request_body: serde_json::Value = match serde_json::from_slice(&body) {
+        Ok(request_body) => request_body,
+        Err(e) => {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("The request is not valid JSON: {}", e),
+                "invalid_request",
+            )
+        }
+    };
+    let Some(messages) = request_body["messages"].as_array() else {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            "The request has no `messages`",
+            "invalid_request",
+        );
+    };
+
+    let exchange = ProxiedQuestion::from_messages(messages).map(|question| {
+        let model = request_body["model"].as_str().unwrap_or("").to_string();
+        Exchange::new(context.clone(), question, model)
+    });
+    let stream = request_body["stream"].as_bool().unwrap_or(false);
+
+    let res = match Provider::from_config(config)
+        .post("chat/completions")
+        .json(&request_body)
+        .send()
+        .await
+    {
+        Ok(res) => res,
+        Err(e) => {
+            if let Some(exchange) = exchange {
+                exchange.record(String::new(), ChatStatus::Failed).await;
+            }
+            return error_response(
+                StatusCode::BAD_GATEWAY,
+                &format!("Failed to reach the provider: {}", e),
+                "provider_unreachable",
+            );
+        }
+    };
+
+    let status = res.status();
+    let content_type = content_type(&res);
+    if stream && status.is_success() {
+        return relay_stream(res, exchange);
+    }
+
+    let body = match res.bytes().await {
+        Ok(body) => body,
+        Err(e) => {
+            if let Some(exchange) = exchange {
+                exchange.record(String::new(), ChatStatus::Failed).await;
+            }
+            return error_response(
+                StatusCode::BAD_GATEWAY,
+                &format!("Failed to read the provider response: {}", e),
+                "provider_unreachable",
+            );
+        }
+    };
+    if let Some(exchange) = exchange {
+        if status.is_success() {
+            let answer = serde_json::from_slice::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|json| {
+                    json["choices"][0]["message"]["content"]
+                        .as_str()
+                        .map(str::to_string)
+                })
+                .unwrap_or_default();
+            exchange.record(answer, ChatStatus::Complete).await;
+        } else {
+            exchange.record(String::new(), ChatStatus::Failed).await;
+        }
+    }
+    relay_response(status, content_type, body)
+}
+
+/// Passes the event stream on as it arrives, collecting the answer to record.
+fn relay_stream(mut res: reqwest::Response, exchange: Option<Exchange>) -> Response<ProxyBody> {
+    let (sender, receiver) = mpsc::channel::<Bytes>(32);
+
+    tokio::spawn(async move {
+        let mut decoder = EventStreamDecoder::default();
+        let mut answer = String::new();
+        let status = loop {
+            let chunk = match res.chunk().await {
+                Ok(Some(chunk)) => chunk,
+                Ok(None) => break ChatStatus::Complete,
+                Err(e) => {
+                    log::warn!("Proxied stream failed: {}", e);
+                    break ChatStatus::Failed;
+                }
+            };
+            for event in decoder.push(&chunk) {
+                if let Some(token) = event["choices"][0]["delta"]["content"].as_str() {
+                    answer.push_str(token);
+                }
+            }
+            // The client went away
+            if sender.send(chunk).await.is_err() {
+                break ChatStatus::Cancelled;
+            }
+        };
+        drop(sender);
+
+        if let Some(exchange) = exchange {
+            exchange.record(answer, status).await;
+        }
+    });
+
+    Response::builder()
+        .header(CONTENT_TYPE, "text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .body(ChannelBody(receiver).boxed())
+        .unwrap_or_default()
+}
@@ -0,0 +1,80 @@
+use crate::config::Config;
+use crate::database_backup::{snapshot_database, PRE_MIGRATION_PREFIX};
+use crate::database_encryption::DatabaseKey;
+use diesel::migration::MigrationSource;
+use diesel::prelude::SqliteConnection;
+use diesel::sqlite::Sqlite;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use std::collections::HashSet;
+use std::path::Path;
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
+
+pub fn run_migrations(connection: &mut SqliteConnection) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    connection.run_pending_migrations(MIGRATIONS).map(|_| ())
+}
+
+/// The versions in `applied` that this build does not know, which means a
+/// newer version of Cuuri has migrated the database.
+pub fn unknown_migrations(applied: impl IntoIterator<Item = String>) -> Vec<String> {
+    let known: HashSet<String> = MigrationSource::<Sqlite>::migrations(&MIGRATIONS)
+        .map(|migrations| {
+            migrations
+                .iter()
+                .map(|migration| migration.name().version().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    applied
+        .into_iter()
+        .filter(|version| !known.contains(version))
+        .collect()
+}
+
+/// Brings the database at `path` up to date. A database migrated by a newer
+/// version of Cuuri is left alone, and an existing database is backed up
+/// before any migration runs.
+pub fn migrate_database(
+    connection: &mut SqliteConnection,
+    config: &Config,
+    path: &Path,
+    key: Option<&DatabaseKey>,
+) -> Result<(), String> {
+    let applied = connection
+        .applied_migrations()
+        .map_err(|e| format!("Failed to read the version of the chat database: {}", e))?;
+    let unknown = unknown_migrations(applied.iter().map(|version| version.to_string()));
+    if !unknown.is_empty() {
+        return Err(format!(
+            "The chat database was updated by a newer version of Cuuri (migrations {}). Update Cuuri, or restore a backup made with this version.",
+            unknown.join(", ")
+        ));
+    }
+
+    let pending = connection
+        .pending_migrations(MIGRATIONS)
+        .map_err(|e| format!("Failed to read the version of the chat database: {}", e))?;
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    // A new database has nothing to lose
+    if applied.is_empty() {
+        return run_migrations(connection)
+            .map_err(|e| format!("Failed to set up the chat database: {}", e));
+    }
+
+    let backup = snapshot_database(config, path, PRE_MIGRATION_PREFIX, key).map_err(|e| {
+        format!(
+            "The chat database was not updated, as backing it up first failed: {}",
+            e
+        )
+    })?;
+    run_migrations(connection).map_err(|e| {
+        format!(
+            "Failed to update the chat database: {}. A copy from before the update was saved to {}.",
+            e,
+            backup.display()
+        )
+    })
+}
@@ -0,0 +1,218 @@
+use crate::app_type::{ImportReport, RawDatabaseChatEntry, SemanticSearchResult};
+use crate::chat_embeddings::search_chats;
+use crate::chat_history::{list_session_ids, list_sessions, load_chat_entries, SessionSummary};
+use crate::chatgpt_import::parse_chatgpt_export;
+use crate::conversation_export::{
+    export_session_file, load_session, load_sessions, render_json, render_session,
+    session_file_stem, write_session_export, ExportFormat,
+};
+use crate::conversation_import::{apply_import, save_imported_conversation};
+use crate::cuuri::Cuuri;
+use crate::cuuri_import::{parse_cuuri_export, save_exported_session};
+use crate::embeddings::request_embeddings;
+use crate::jsonl_import::parse_jsonl_conversations;
+use crate::provider_service::Provider;
+use diesel::SqliteConnection;
+use std::fs;
+use std::path::PathBuf;
+
+const DEFAULT_RESULT_COUNT: usize = 10;
+
+impl Cuuri {
+    pub async fn session_ids(&self) -> Result<Vec<String>, String> {
+        self.db().run(list_session_ids).await
+    }
+
+    /// The sessions with their first question, the most recently active first.
+    pub async fn list_sessions(&self) -> Result<Vec<SessionSummary>, String> {
+        self.db().run(list_sessions).await
+    }
+
+    /// The questions and answers of `session_id`, or of every session.
+    pub async fn chat_history(
+        &self,
+        session_id: Option<String>,
+    ) -> Result<Vec<RawDatabaseChatEntry>, String> {
+        self.db()
+            .run(move |conn| load_chat_entries(conn, session_id.as_deref()))
+            .await
+    }
+
+    /// Writes one conversation to `path`. Markdown exports link images stored
+    /// next to the file unless `embed_images` is set.
+    pub async fn export_session(
+        &self,
+        session_id: String,
+        format: ExportFormat,
+        path: PathBuf,
+        embed_images: bool,
+    ) -> Result<(), String> {
+        self.db()
+            .run(move |conn| export_session_file(conn, &session_id, format, &path, embed_images))
+            .await
+    }
+
+    /// One conversation as the content of an export file.
+    pub async fn render_session(
+        &self,
+        session_id: String,
+        format: ExportFormat,
+    ) -> Result<String, String> {
+        self.db()
+            .run(move |conn| render_session(load_session(conn, &session_id)?, format))
+            .await
+    }
+
+    /// Exports every conversation into `dir`, returning the files written. JSON
+    /// goes into a single file that can be imported again; Markdown and HTML get
+    /// one file per conversation.
+    pub async fn export_all(
+        &self,
+        format: ExportFormat,
+        dir: PathBuf,
+        embed_images: bool,
+    ) -> Result<Vec<String>, String> {
+        self.db()
+            .run(move |conn| {
+                fs::create_dir_all(&dir)
+                    .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+                let sessions = load_sessions(conn, None)?;
+
+                if format == ExportFormat::Json {
+                    let path = dir.join("cuuri-export.json");
+                    fs::write(&path, render_json(sessions)?)
+                        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+                    return Ok(vec![path.to_string_lossy().to_string()]);
+                }
+
+                let mut written = Vec::new();
+                for session in sessions {
+                    let path = dir.join(format!(
+                        "{}.{}",
+                        session_file_stem(&session.session_id),
+                        format.extension()
+                    ));
+                    write_session_export(session, format, &path, embed_images)?;
+                    written.push(path.to_string_lossy().to_string());
+                }
+                Ok(written)
+            })
+            .await
+    }
+
+    // Reads the file at `path` and imports it with `import`
+    async fn import_file<F>(
+        &self,
+        path: String,
+        dry_run: bool,
+        import: F,
+    ) -> Result<ImportReport, String>
+    where
+        F: FnOnce(&mut SqliteConnection, &[u8]) -> Result<ImportReport, String> + Send + 'static,
+    {
+        let report = self
+            .db()
+            .run(move |conn| {
+                let data =
+                    fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+                import(conn, &data)
+            })
+            .await?;
+
+        if !dry_run && report.chats_imported > 0 {
+            self.wake_chat_embedder();
+        }
+        Ok(report)
+    }
+
+    /// Imports the `conversations.json` from a ChatGPT data export. Importing a
+    /// newer export of the same account again only adds the new messages. With
+    /// `dry_run`, nothing is written and the report shows what would be imported.
+    pub async fn import_chatgpt_conversations(
+        &self,
+        path: String,
+        dry_run: bool,
+    ) -> Result<ImportReport, String> {
+        self.import_file(path, dry_run, move |conn, data| {
+            let (conversations, skipped) = parse_chatgpt_export(data)?;
+
+            apply_import(conn, dry_run, skipped, |conn, report| {
+                for conversation in conversations {
+                    save_imported_conversation(conn, conversation, dry_run, report)?;
+                }
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    /// Imports a JSON export from Cuuri, e.g. to move conversations to another
+    /// machine.
+    pub async fn import_cuuri_export(
+        &self,
+        path: String,
+        dry_run: bool,
+    ) -> Result<ImportReport, String> {
+        self.import_file(path, dry_run, move |conn, data| {
+            let document = parse_cuuri_export(data)?;
+
+            apply_import(conn, dry_run, Vec::new(), |conn, report| {
+                for session in document.sessions {
+                    save_exported_session(conn, session, dry_run, report)?;
+                }
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    /// Imports a JSONL file of OpenAI-format `messages` arrays, one conversation
+    /// per line.
+    pub async fn import_jsonl_conversations(
+        &self,
+        path: String,
+        dry_run: bool,
+    ) -> Result<ImportReport, String> {
+        self.import_file(path, dry_run, move |conn, data| {
+            let (conversations, skipped) = parse_jsonl_conversations(data)?;
+
+            apply_import(conn, dry_run, skipped, |conn, report| {
+                for conversation in conversations {
+                    save_imported_conversation(conn, conversation, dry_run, report)?;
+                }
+                Ok(())
+            })
+        })
+        .await
+    }
+
+    /// Finds past questions and answers by meaning rather than exact words.
+    /// Questions answered very recently may not be embedded yet.
+    pub async fn semantic_search(
+        &self,
+        query: String,
+        k: Option<usize>,
+    ) -> Result<Vec<SemanticSearchResult>, String> {
+        let config = self.config();
+        if config.openai_api_key.is_empty() {
+            return Err("An OpenAI API key is required for semantic search".to_string());
+        }
+        if query.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query_embedding = request_embeddings(
+            &Provider::from_config(&config),
+            &config.embedding_model,
+            &[query],
+        )
+        .await?
+        .pop()
+        .unwrap_or_default();
+        let k = k.unwrap_or(DEFAULT_RESULT_COUNT);
+        let model = config.embedding_model;
+        self.db()
+            .run(move |conn| search_chats(conn, &model, &query_embedding, k))
+            .await
+    }
+}
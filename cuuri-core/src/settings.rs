@@ -0,0 +1,282 @@
+use crate::config::{Config, McpServerConfig};
+use crate::config_document::set_config_value;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use toml_edit::{value, Array, DocumentMut, InlineTable, Item, Table};
+
+/// Shows only the start and the last few characters of a secret.
+pub fn mask_secret(secret: &str) -> String {
+    let chars: Vec<char> = secret.chars().collect();
+    if chars.len() <= 8 {
+        return "*".repeat(chars.len());
+    }
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}...{}", chars[..3].iter().collect::<String>(), tail)
+}
+
+fn string_table(values: &BTreeMap<String, String>) -> InlineTable {
+    values
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect()
+}
+
+/// The `[mcp_servers.<name>]` tables, leaving out values that are the default.
+fn mcp_servers_table(servers: &BTreeMap<String, McpServerConfig>) -> Table {
+    let mut table = Table::new();
+    table.set_implicit(true);
+    for (name, server) in servers {
+        let mut entry = Table::new();
+        if let Some(command) = &server.command {
+            entry["command"] = value(command.as_str());
+        }
+        if !server.args.is_empty() {
+            entry["args"] = value(server.args.iter().map(String::as_str).collect::<Array>());
+        }
+        if !server.env.is_empty() {
+            entry["env"] = value(string_table(&server.env));
+        }
+        if let Some(url) = &server.url {
+            entry["url"] = value(url.as_str());
+        }
+        if !server.headers.is_empty() {
+            entry["headers"] = value(string_table(&server.headers));
+        }
+        if !server.enabled {
+            entry["enabled"] = value(false);
+        }
+        table.insert(name, Item::Table(entry));
+    }
+    table
+}
+
+/// User-editable settings, as stored in the config file.
+#[derive(Serialize, Clone)]
+pub struct Settings {
+    pub openai_api_key: String,
+    pub openai_base_url: Option<String>,
+    pub default_model: Option<String>,
+    pub document_token_budget: usize,
+    pub enable_tools: bool,
+    pub tool_allowed_directories: Vec<String>,
+    pub always_allowed_tools: Vec<String>,
+    pub embedding_model: String,
+    pub retrieval_top_k: usize,
+    pub backup_interval_hours: u64,
+    pub backup_retention: usize,
+    pub backup_directory: Option<String>,
+    pub mcp_servers: BTreeMap<String, McpServerConfig>,
+    pub proxy_enabled: bool,
+    pub proxy_port: u16,
+}
+
+impl From<Config> for Settings {
+    fn from(config: Config) -> Self {
+        Settings {
+            openai_api_key: config.openai_api_key,
+            openai_base_url: config.openai_base_url,
+            default_model: config.default_model,
+            document_token_budget: config.document_token_budget,
+            enable_tools: config.enable_tools,
+            tool_allowed_directories: config.tool_allowed_directories,
+            always_allowed_tools: config.always_allowed_tools,
+            embedding_model: config.embedding_model,
+            retrieval_top_k: config.retrieval_top_k,
+            backup_interval_hours: config.backup_interval_hours,
+            backup_retention: config.backup_retention,
+            backup_directory: config.backup_directory,
+            mcp_servers: config.mcp_servers,
+            proxy_enabled: config.proxy_enabled,
+            proxy_port: config.proxy_port,
+        }
+    }
+}
+
+/// A partial update of `Settings`; fields that are not set are left unchanged.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct SettingsPatch {
+    pub openai_api_key: Option<String>,
+    /// An empty string goes back to OpenAI.
+    pub openai_base_url: Option<String>,
+    /// An empty string removes the default model.
+    pub default_model: Option<String>,
+    pub document_token_budget: Option<usize>,
+    pub enable_tools: Option<bool>,
+    pub tool_allowed_directories: Option<Vec<String>>,
+    pub always_allowed_tools: Option<Vec<String>>,
+    pub embedding_model: Option<String>,
+    pub retrieval_top_k: Option<usize>,
+    /// 0 turns automatic backups off.
+    pub backup_interval_hours: Option<u64>,
+    pub backup_retention: Option<usize>,
+    /// An empty string restores the default directory.
+    pub backup_directory: Option<String>,
+    /// Replaces every MCP server; an empty map removes them all.
+    pub mcp_servers: Option<BTreeMap<String, McpServerConfig>>,
+    pub proxy_enabled: Option<bool>,
+    pub proxy_port: Option<u16>,
+}
+
+impl SettingsPatch {
+    pub fn apply_to(&self, document: &mut DocumentMut) -> Result<(), String> {
+        if let Some(api_key) = &self.openai_api_key {
+            if api_key.is_empty() {
+                return Err("Invalid value for `openai_api_key`: API key cannot be empty".to_string());
+            }
+            set_config_value(document, "openai_api_key", api_key.as_str());
+        }
+
+        if let Some(base_url) = &self.openai_base_url {
+            if base_url.is_empty() {
+                document.remove("openai_base_url");
+            } else if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+                return Err("Invalid value for `openai_base_url`: must be an http or https URL".to_string());
+            } else {
+                set_config_value(document, "openai_base_url", base_url.as_str());
+            }
+        }
+
+        if let Some(model) = &self.default_model {
+            if model.is_empty() {
+                document.remove("default_model");
+            } else {
+                set_config_value(document, "default_model", model.as_str());
+            }
+        }
+
+        if let Some(budget) = self.document_token_budget {
+            if budget == 0 {
+                return Err("Invalid value for `document_token_budget`: must be greater than 0".to_string());
+            }
+            set_config_value(document, "document_token_budget", budget as i64);
+        }
+
+        if let Some(enabled) = self.enable_tools {
+            set_config_value(document, "enable_tools", enabled);
+        }
+
+        if let Some(directories) = &self.tool_allowed_directories {
+            set_config_value(
+                document,
+                "tool_allowed_directories",
+                directories.iter().map(String::as_str).collect::<Array>(),
+            );
+        }
+
+        if let Some(tools) = &self.always_allowed_tools {
+            set_config_value(
+                document,
+                "always_allowed_tools",
+                tools.iter().map(String::as_str).collect::<Array>(),
+            );
+        }
+
+        if let Some(model) = &self.embedding_model {
+            if model.is_empty() {
+                return Err("Invalid value for `embedding_model`: model cannot be empty".to_string());
+            }
+            set_config_value(document, "embedding_model", model.as_str());
+        }
+
+        if let Some(top_k) = self.retrieval_top_k {
+            if top_k == 0 {
+                return Err("Invalid value for `retrieval_top_k`: must be greater than 0".to_string());
+            }
+            set_config_value(document, "retrieval_top_k", top_k as i64);
+        }
+
+        if let Some(hours) = self.backup_interval_hours {
+            set_config_value(document, "backup_interval_hours", hours as i64);
+        }
+
+        if let Some(retention) = self.backup_retention {
+            if retention == 0 {
+                return Err("Invalid value for `backup_retention`: must be greater than 0".to_string());
+            }
+            set_config_value(document, "backup_retention", retention as i64);
+        }
+
+        if let Some(directory) = &self.backup_directory {
+            if directory.is_empty() {
+                document.remove("backup_directory");
+            } else {
+                set_config_value(document, "backup_directory", directory.as_str());
+            }
+        }
+
+        if let Some(servers) = &self.mcp_servers {
+            for (name, server) in servers {
+                if server.command.is_some() == server.url.is_some() {
+                    return Err(format!(
+                        "Invalid value for `mcp_servers.{}`: needs either `command` or `url`, but not both",
+                        name
+                    ));
+                }
+            }
+            if servers.is_empty() {
+                document.remove("mcp_servers");
+            } else {
+                document["mcp_servers"] = Item::Table(mcp_servers_table(servers));
+            }
+        }
+
+        if let Some(enabled) = self.proxy_enabled {
+            set_config_value(document, "proxy_enabled", enabled);
+        }
+
+        if let Some(port) = self.proxy_port {
+            if port == 0 {
+                return Err("Invalid value for `proxy_port`: must be greater than 0".to_string());
+            }
+            set_config_value(document, "proxy_port", port as i64);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mcp_servers_are_written_as_tables() {
+        let server = McpServerConfig {
+            command: Some("npx".to_string()),
+            args: vec!["-y".to_string(), "docs-server".to_string()],
+            env: BTreeMap::from([("TOKEN".to_string(), "secret".to_string())]),
+            ..Default::default()
+        };
+        let patch = SettingsPatch {
+            mcp_servers: Some(BTreeMap::from([("docs".to_string(), server)])),
+            ..Default::default()
+        };
+
+        let mut document = DocumentMut::new();
+        patch.apply_to(&mut document).expect("apply the patch");
+
+        assert!(
+            document.to_string().contains("[mcp_servers.docs]"),
+            "{}",
+            document
+        );
+        let config = Config::from_document(&document).expect("a valid config");
+        assert!(config.mcp_servers["docs"] == patch.mcp_servers.unwrap()["docs"]);
+    }
+
+    #[test]
+    fn mcp_servers_need_a_command_or_a_url() {
+        let patch = SettingsPatch {
+            mcp_servers: Some(BTreeMap::from([(
+                "docs".to_string(),
+                McpServerConfig::default(),
+            )])),
+            ..Default::default()
+        };
+        let error = patch
+            .apply_to(&mut DocumentMut::new())
+            .expect_err("the server is refused");
+        assert!(error.contains("mcp_servers.docs"), "{}", error);
+    }
+}
@@ -0,0 +1,28 @@
+use log::{Log, Metadata, Record};
+
+pub use log::LevelFilter;
+
+/// Writes log records to stderr, for frontends that have no log of their own.
+struct StderrLog;
+
+impl Log for StderrLog {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Shows what the core logs at `level` or above on stderr. Nothing is shown
+/// unless a frontend calls this.
+pub fn log_to_stderr(level: LevelFilter) {
+    if log::set_logger(&StderrLog).is_ok() {
+        log::set_max_level(level);
+    }
+}
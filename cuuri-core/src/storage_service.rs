@@ -0,0 +1,204 @@
+use crate::app_type::{DatabaseEncryptionStatus, DatabaseStatus, IntegrityCheckReport};
+use crate::cuuri::Cuuri;
+use crate::database_backup::{
+    backup_database_file, check_database_file, restore_database_file, snapshot_database,
+    validate_backup_file, PRE_RESTORE_PREFIX,
+};
+use crate::database_encryption::{
+    encryption_status, rekey_active_database, verify_database_key, DatabaseKey,
+};
+use crate::db_pool::DatabaseState;
+use crate::get_database_path::get_database_file;
+use crate::open_chat_database::{open_chat_database, recover_interrupted_chats};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+impl Cuuri {
+    /// Whether the chat database could be opened, so a frontend can ask for
+    /// the passphrase or offer a way to recover.
+    pub fn database_status(&self) -> DatabaseStatus {
+        self.db().status()
+    }
+
+    /// Tries again to open a chat database that failed to open, e.g. after
+    /// freeing disk space. Returns whether it is open now and was not before.
+    pub async fn reopen_database(&self) -> Result<bool, String> {
+        if !matches!(self.db().state(), DatabaseState::Failed { .. }) {
+            return Ok(false);
+        }
+        if !self.streams().is_empty() {
+            return Err(
+                "Wait for the answers being received to finish before reopening".to_string(),
+            );
+        }
+
+        let key = self.db().key()?;
+        let config = self.config();
+        let state = tokio::task::spawn_blocking(move || {
+            let state = open_chat_database(&config, key);
+            // Nothing could stream while the database was closed
+            if let DatabaseState::Open { pool, .. } = &state {
+                recover_interrupted_chats(pool);
+            }
+            state
+        })
+        .await
+        .map_err(|e| e.to_string())?;
+        let opened = matches!(state, DatabaseState::Open { .. });
+        self.db().set(state);
+
+        if opened {
+            self.wake_chat_embedder();
+        }
+        Ok(opened)
+    }
+
+    /// Writes a copy of the chat database to `path`, which may be done while
+    /// it is in use. The copy of an encrypted database has the same key.
+    pub async fn backup_database(&self, path: PathBuf) -> Result<(), String> {
+        let key = self.db().key()?;
+        tokio::task::spawn_blocking(move || {
+            let source = get_database_file().map_err(|e| e.to_string())?;
+            backup_database_file(&source, &path, key.as_ref())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+
+    /// Replaces the chat database with the backup at `path`. The current
+    /// database is backed up first, and the path of that copy is returned so
+    /// the restore can be undone.
+    pub async fn restore_database(&self, path: PathBuf) -> Result<PathBuf, String> {
+        if !self.streams().is_empty() {
+            return Err(
+                "Wait for the answers being received to finish before restoring".to_string(),
+            );
+        }
+
+        let config = self.config();
+        let key = self.db().key()?;
+        let (snapshot, state) = tokio::task::spawn_blocking(move || {
+            validate_backup_file(&path, key.as_ref())?;
+            let target = get_database_file().map_err(|e| e.to_string())?;
+            let snapshot = snapshot_database(&config, &target, PRE_RESTORE_PREFIX, key.as_ref())?;
+            restore_database_file(&path, &target, key.as_ref())?;
+
+            // A backup made by an older version may lack the latest tables, and
+            // a database that failed to open is usable again
+            Ok::<_, String>((snapshot, open_chat_database(&config, key)))
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+        let error = state.error();
+        self.db().set(state);
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        self.wake_chat_embedder();
+        Ok(snapshot)
+    }
+
+    /// Runs `PRAGMA integrity_check` on the chat database.
+    pub async fn integrity_check(&self) -> Result<IntegrityCheckReport, String> {
+        let key = self.db().key()?;
+        tokio::task::spawn_blocking(move || {
+            let path = get_database_file().map_err(|e| e.to_string())?;
+            check_database_file(&path, key.as_ref())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+
+    pub fn database_encryption(&self) -> Result<DatabaseEncryptionStatus, String> {
+        encryption_status(self.db())
+    }
+
+    /// Encrypts the plain chat database in place, with `passphrase`, or without
+    /// one with a generated key kept in the OS keyring.
+    pub async fn encrypt_database(
+        self: &Arc<Self>,
+        passphrase: Option<String>,
+    ) -> Result<DatabaseEncryptionStatus, String> {
+        if self.database_encryption()?.encrypted {
+            return Err(
+                "The chat database is already encrypted; use change_database_key to change its key"
+                    .to_string(),
+            );
+        }
+        if !self.streams().is_empty() {
+            return Err(
+                "Wait for the answers being received to finish before encrypting".to_string(),
+            );
+        }
+
+        self.rekey_database(DatabaseKey::choose(passphrase)?).await
+    }
+
+    /// Re-encrypts the chat database with a new passphrase, or without one with
+    /// a new generated key kept in the OS keyring.
+    pub async fn change_database_key(
+        self: &Arc<Self>,
+        passphrase: Option<String>,
+    ) -> Result<DatabaseEncryptionStatus, String> {
+        if !self.database_encryption()?.encrypted {
+            return Err(
+                "The chat database is not encrypted; use encrypt_database first".to_string(),
+            );
+        }
+        if !self.streams().is_empty() {
+            return Err(
+                "Wait for the answers being received to finish before changing the key".to_string(),
+            );
+        }
+
+        self.rekey_database(DatabaseKey::choose(passphrase)?).await
+    }
+
+    async fn rekey_database(
+        self: &Arc<Self>,
+        key: DatabaseKey,
+    ) -> Result<DatabaseEncryptionStatus, String> {
+        let cuuri = self.clone();
+        tokio::task::spawn_blocking(move || rekey_active_database(cuuri.db(), key))
+            .await
+            .map_err(|e| e.to_string())??;
+        self.database_encryption()
+    }
+
+    /// Opens an encrypted chat database that has no key in the OS keyring.
+    /// Returns whether it was locked before.
+    pub async fn unlock_database(&self, passphrase: String) -> Result<bool, String> {
+        if !self.database_encryption()?.locked {
+            return Ok(false);
+        }
+        if !self.streams().is_empty() {
+            return Err(
+                "Wait for the answers being received to finish before unlocking".to_string(),
+            );
+        }
+
+        let key = DatabaseKey::passphrase(passphrase)?;
+        let database_file = get_database_file().map_err(|e| e.to_string())?;
+        let config = self.config();
+        let state = tokio::task::spawn_blocking(move || {
+            verify_database_key(&database_file, Some(&key))?;
+            let state = open_chat_database(&config, Some(key));
+            // Nothing could stream while the database was locked
+            if let DatabaseState::Open { pool, .. } = &state {
+                recover_interrupted_chats(pool);
+            }
+            Ok::<_, String>(state)
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+        let error = state.error();
+        self.db().set(state);
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        self.wake_chat_embedder();
+        Ok(true)
+    }
+}
@@ -0,0 +1,75 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+pub enum ApprovalDecision {
+    Approved,
+    Denied(Option<String>),
+}
+
+/// Payload of the "tool-approval-request" event.
+#[derive(Serialize, Clone)]
+pub struct ToolApprovalRequest {
+    pub request_id: String,
+    pub session_id: String,
+    pub call_id: String,
+    pub name: String,
+    /// The arguments as the JSON string produced by the model.
+    pub arguments: String,
+}
+
+struct PendingApproval {
+    tool_name: String,
+    sender: oneshot::Sender<ApprovalDecision>,
+}
+
+/// Tool calls waiting for the user to approve or deny them, keyed by request id.
+#[derive(Default)]
+pub struct ToolApprovals {
+    pending: Mutex<HashMap<String, PendingApproval>>,
+}
+
+impl ToolApprovals {
+    pub fn register(
+        &self,
+        request_id: &str,
+        tool_name: &str,
+    ) -> oneshot::Receiver<ApprovalDecision> {
+        let (sender, receiver) = oneshot::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(
+                request_id.to_string(),
+                PendingApproval {
+                    tool_name: tool_name.to_string(),
+                    sender,
+                },
+            );
+        }
+        receiver
+    }
+
+    pub fn remove(&self, request_id: &str) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(request_id);
+        }
+    }
+
+    /// Hands the decision to the waiting call, returning the name of its tool.
+    pub fn resolve(&self, request_id: &str, decision: ApprovalDecision) -> Result<String, String> {
+        let approval = self
+            .pending
+            .lock()
+            .ok()
+            .and_then(|mut pending| pending.remove(request_id))
+            .ok_or_else(|| {
+                format!(
+                    "No tool call is waiting for approval with id `{}`",
+                    request_id
+                )
+            })?;
+
+        let _ = approval.sender.send(decision);
+        Ok(approval.tool_name)
+    }
+}
@@ -0,0 +1,305 @@
+use crate::active_streams::CancelHandle;
+use crate::app_type::RawToolCall;
+use crate::chat_events::ChatEvents;
+use crate::models::{NewToolCallRecord, ToolCallRecord};
+use crate::schema::tool_calls;
+use crate::tool_approvals::{ApprovalDecision, ToolApprovalRequest};
+use crate::tool_registry::{ToolContext, ToolRegistry};
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+
+// Stops a model that keeps calling tools from looping forever
+pub const MAX_TOOL_ROUNDS: i32 = 8;
+
+/// A function call requested by the model.
+#[derive(Serialize, Clone, Default)]
+pub struct ToolCall {
+    pub id: String,
+    pub name: String,
+    /// The arguments as the JSON string produced by the model.
+    pub arguments: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ToolCallResult {
+    pub id: String,
+    pub name: String,
+    pub content: String,
+    pub is_error: bool,
+}
+
+/// Payload of the "tool-call-delta" event, emitted while a call is streamed.
+#[derive(Serialize, Clone)]
+pub struct ToolCallDelta {
+    pub index: usize,
+    pub id: Option<String>,
+    pub name: Option<String>,
+    pub arguments: String,
+}
+
+/// Assembles tool calls from the `delta.tool_calls` fragments of a stream.
+#[derive(Default)]
+pub struct ToolCallAccumulator {
+    calls: Vec<ToolCall>,
+}
+
+impl ToolCallAccumulator {
+    pub fn apply(&mut self, deltas: &serde_json::Value) -> Vec<ToolCallDelta> {
+        let Some(deltas) = deltas.as_array() else {
+            return Vec::new();
+        };
+
+        deltas
+            .iter()
+            .map(|delta| {
+                let index = delta["index"].as_u64().unwrap_or(0) as usize;
+                if self.calls.len() <= index {
+                    self.calls.resize_with(index + 1, ToolCall::default);
+                }
+
+                let call = &mut self.calls[index];
+                let id = delta["id"].as_str().map(str::to_string);
+                let name = delta["function"]["name"].as_str().map(str::to_string);
+                let arguments = delta["function"]["arguments"]
+                    .as_str()
+                    .unwrap_or("")
+                    .to_string();
+                if let Some(id) = &id {
+                    call.id = id.clone();
+                }
+                if let Some(name) = &name {
+                    call.name.push_str(name);
+                }
+                call.arguments.push_str(&arguments);
+
+                ToolCallDelta {
+                    index,
+                    id,
+                    name,
+                    arguments,
+                }
+            })
+            .collect()
+    }
+
+    pub fn into_calls(self) -> Vec<ToolCall> {
+        self.calls
+            .into_iter()
+            .filter(|call| !call.name.is_empty())
+            .collect()
+    }
+}
+
+/// Reads the tool calls of a non-streamed assistant message.
+pub fn parse_tool_calls(message: &serde_json::Value) -> Vec<ToolCall> {
+    message["tool_calls"]
+        .as_array()
+        .map(|calls| {
+            calls
+                .iter()
+                .map(|call| ToolCall {
+                    id: call["id"].as_str().unwrap_or("").to_string(),
+                    name: call["function"]["name"].as_str().unwrap_or("").to_string(),
+                    arguments: call["function"]["arguments"]
+                        .as_str()
+                        .unwrap_or("")
+                        .to_string(),
+                })
+                .filter(|call| !call.name.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn assistant_tool_call_message(content: &str, calls: &[ToolCall]) -> serde_json::Value {
+    let tool_calls: Vec<serde_json::Value> = calls
+        .iter()
+        .map(|call| {
+            json!({
+                "id": call.id,
+                "type": "function",
+                "function": { "name": call.name, "arguments": call.arguments }
+            })
+        })
+        .collect();
+
+    json!({
+        "role": "assistant",
+        "content": if content.is_empty() { serde_json::Value::Null } else { json!(content) },
+        "tool_calls": tool_calls
+    })
+}
+
+pub fn tool_result_message(call_id: &str, content: &str) -> serde_json::Value {
+    json!({
+        "role": "tool",
+        "tool_call_id": call_id,
+        "content": content
+    })
+}
+
+/// Asks the user before running a tool that needs approval, unless it is
+/// always allowed. Waiting stops when the response is cancelled.
+async fn wait_for_approval(
+    registry: &ToolRegistry,
+    context: &ToolContext,
+    call: &ToolCall,
+    events: &dyn ChatEvents,
+    cancel: &CancelHandle,
+) -> Result<(), String> {
+    let requires_approval = registry
+        .get(&call.name)
+        .is_some_and(|tool| tool.requires_approval);
+    let always_allowed = context.config.always_allowed_tools.contains(&call.name);
+    if !requires_approval || always_allowed {
+        return Ok(());
+    }
+
+    let request = events.request_approval(ToolApprovalRequest {
+        request_id: uuid::Uuid::new_v4().to_string(),
+        session_id: context.session_id.clone(),
+        call_id: call.id.clone(),
+        name: call.name.clone(),
+        arguments: call.arguments.clone(),
+    });
+    let decision = tokio::select! {
+        decision = request => decision,
+        _ = cancel.cancelled() => None,
+    };
+
+    match decision {
+        Some(ApprovalDecision::Approved) => Ok(()),
+        Some(ApprovalDecision::Denied(Some(reason))) => {
+            Err(format!("The user denied this tool call: {}", reason))
+        }
+        Some(ApprovalDecision::Denied(None)) => Err("The user denied this tool call".to_string()),
+        None => Err("The tool call was cancelled before it was approved".to_string()),
+    }
+}
+
+/// Runs the calls one after another. Failures and denials are reported back
+/// to the model as the tool result instead of aborting the answer.
+pub async fn execute_tool_calls(
+    registry: &ToolRegistry,
+    context: &ToolContext,
+    calls: &[ToolCall],
+    events: &dyn ChatEvents,
+    cancel: &CancelHandle,
+) -> Vec<ToolCallResult> {
+    let mut results = Vec::with_capacity(calls.len());
+    for call in calls {
+        events.tool_call(call);
+
+        let outcome = match wait_for_approval(registry, context, call, events, cancel).await {
+            Ok(()) => {
+                registry
+                    .call(&call.name, &call.arguments, context.clone())
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        let (content, is_error) = match outcome {
+            Ok(content) => (content, false),
+            Err(e) => (format!("Error: {}", e), true),
+        };
+        let result = ToolCallResult {
+            id: call.id.clone(),
+            name: call.name.clone(),
+            content,
+            is_error,
+        };
+
+        events.tool_result(&result);
+        results.push(result);
+    }
+    results
+}
+
+pub fn save_tool_calls(
+    conn: &mut SqliteConnection,
+    chat_id: i32,
+    round: i32,
+    calls: &[ToolCall],
+    results: &[ToolCallResult],
+) -> Result<(), String> {
+    let now = Utc::now().naive_utc();
+    let records: Vec<NewToolCallRecord> = calls
+        .iter()
+        .zip(results)
+        .map(|(call, result)| NewToolCallRecord {
+            chat_history_id: chat_id,
+            round,
+            call_id: &call.id,
+            name: &call.name,
+            arguments: &call.arguments,
+            result: &result.content,
+            is_error: result.is_error,
+            created_at: now,
+        })
+        .collect();
+
+    diesel::insert_into(tool_calls::table)
+        .values(&records)
+        .execute(conn)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Loads the tool calls of the given chat rows, grouped by chat row id.
+pub fn load_tool_calls(
+    conn: &mut SqliteConnection,
+    chat_ids: &[i32],
+) -> Result<HashMap<i32, Vec<ToolCallRecord>>, String> {
+    let rows = tool_calls::table
+        .filter(tool_calls::chat_history_id.eq_any(chat_ids))
+        .order((tool_calls::round.asc(), tool_calls::id.asc()))
+        .load::<ToolCallRecord>(conn)
+        .map_err(|e| e.to_string())?;
+
+    let mut grouped: HashMap<i32, Vec<ToolCallRecord>> = HashMap::new();
+    for record in rows {
+        grouped
+            .entry(record.chat_history_id)
+            .or_default()
+            .push(record);
+    }
+    Ok(grouped)
+}
+
+/// Rebuilds the assistant and tool messages of each stored round.
+pub fn build_tool_messages(records: &[ToolCallRecord]) -> Vec<serde_json::Value> {
+    let mut messages = Vec::new();
+    for round in records.chunk_by(|a, b| a.round == b.round) {
+        let calls: Vec<ToolCall> = round
+            .iter()
+            .map(|record| ToolCall {
+                id: record.call_id.clone(),
+                name: record.name.clone(),
+                arguments: record.arguments.clone(),
+            })
+            .collect();
+        messages.push(assistant_tool_call_message("", &calls));
+        messages.extend(
+            round
+                .iter()
+                .map(|record| tool_result_message(&record.call_id, &record.result)),
+        );
+    }
+    messages
+}
+
+pub fn to_raw_tool_call(record: &ToolCallRecord) -> RawToolCall {
+    RawToolCall {
+        id: record.id,
+        round: record.round,
+        call_id: record.call_id.clone(),
+        name: record.name.clone(),
+        arguments: record.arguments.clone(),
+        result: record.result.clone(),
+        is_error: record.is_error,
+        created_at: record.created_at.to_string(),
+    }
+}
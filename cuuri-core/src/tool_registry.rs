@@ -0,0 +1,118 @@
+use crate::config::Config;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+
+pub type ToolFuture = Pin<Box<dyn Future<Output = Result<String, String>> + Send>>;
+pub type ToolHandler = Arc<dyn Fn(serde_json::Value, ToolContext) -> ToolFuture + Send + Sync>;
+
+/// Per-request information available to tool handlers.
+#[derive(Clone)]
+pub struct ToolContext {
+    pub config: Config,
+    pub session_id: String,
+}
+
+#[derive(Clone)]
+pub struct Tool {
+    pub name: String,
+    pub description: String,
+    /// JSON schema of the arguments object.
+    pub parameters: serde_json::Value,
+    pub handler: ToolHandler,
+    /// Whether the user has to approve each call, for tools that touch the
+    /// filesystem or run commands.
+    pub requires_approval: bool,
+}
+
+impl Tool {
+    pub fn new<F, Fut>(
+        name: &str,
+        description: &str,
+        parameters: serde_json::Value,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(serde_json::Value, ToolContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<String, String>> + Send + 'static,
+    {
+        Tool {
+            name: name.to_string(),
+            description: description.to_string(),
+            parameters,
+            handler: Arc::new(move |arguments, context| Box::pin(handler(arguments, context))),
+            requires_approval: false,
+        }
+    }
+
+    pub fn requiring_approval(mut self) -> Self {
+        self.requires_approval = true;
+        self
+    }
+
+    /// The tool in the `tools` format of the chat completions API.
+    pub fn definition(&self) -> serde_json::Value {
+        json!({
+            "type": "function",
+            "function": {
+                "name": self.name,
+                "description": self.description,
+                "parameters": self.parameters,
+            }
+        })
+    }
+}
+
+/// Tools the model may call.
+#[derive(Default)]
+pub struct ToolRegistry {
+    tools: RwLock<BTreeMap<String, Tool>>,
+}
+
+impl ToolRegistry {
+    pub fn register(&self, tool: Tool) {
+        if let Ok(mut tools) = self.tools.write() {
+            tools.insert(tool.name.clone(), tool);
+        }
+    }
+
+    pub fn unregister(&self, name: &str) {
+        if let Ok(mut tools) = self.tools.write() {
+            tools.remove(name);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Tool> {
+        self.tools.read().ok()?.get(name).cloned()
+    }
+
+    pub fn definitions(&self) -> Vec<serde_json::Value> {
+        self.tools
+            .read()
+            .map(|tools| tools.values().map(Tool::definition).collect())
+            .unwrap_or_default()
+    }
+
+    /// Runs a tool with the raw JSON arguments string produced by the model.
+    pub async fn call(
+        &self,
+        name: &str,
+        arguments: &str,
+        context: ToolContext,
+    ) -> Result<String, String> {
+        let tool = self
+            .get(name)
+            .ok_or_else(|| format!("Unknown tool `{}`", name))?;
+
+        let arguments: serde_json::Value = if arguments.trim().is_empty() {
+            json!({})
+        } else {
+            serde_json::from_str(arguments)
+                .map_err(|e| format!("Invalid arguments for `{}`: {}", name, e))?
+        };
+
+        (tool.handler)(arguments, context).await
+    }
+}
@@ -0,0 +1,99 @@
+mod common;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use common::mock_provider::MockReply;
+use common::{message_text, RecordingEvents, TestApp};
+use cuuri_core::documents::ExtractedDocument;
+
+#[tokio::test]
+async fn document_text_is_read_from_the_stored_file() {
+    let app = TestApp::start().await;
+    app.provider
+        .push(MockReply::stream().token("Noted.").done());
+
+    let mut document = app
+        .cuuri
+        .extract_document(
+            None,
+            Some("notes.txt".to_string()),
+            Some(STANDARD.encode("The real notes")),
+        )
+        .await
+        .expect("extract the document");
+    let blob = app
+        .database_dir
+        .path()
+        .join("attachments")
+        .join(&document.content_hash);
+    assert!(blob.is_file(), "{} is missing", blob.display());
+    document.text = "Text made up by the frontend".to_string();
+
+    let mut request = app.request("session", "Summarize");
+    request.documents = Some(vec![document]);
+    app.cuuri
+        .stream_message(request, &RecordingEvents::default())
+        .await
+        .expect("answer");
+
+    let sent = message_text(&app.provider.chat_requests()[0]["messages"][0]);
+    assert!(sent.contains("The real notes"), "{}", sent);
+    assert!(!sent.contains("made up"), "{}", sent);
+}
+
+#[tokio::test]
+async fn unsupported_documents_are_not_stored() {
+    let app = TestApp::start().await;
+
+    app.cuuri
+        .extract_document(
+            None,
+            Some("broken.pdf".to_string()),
+            Some(STANDARD.encode(b"%PDF-1.7 not really a PDF")),
+        )
+        .await
+        .err()
+        .expect("the broken PDF is refused");
+    app.cuuri
+        .extract_document(
+            None,
+            Some("image.bin".to_string()),
+            Some(STANDARD.encode([0u8, 1, 2, 3])),
+        )
+        .await
+        .err()
+        .expect("the binary file is refused");
+
+    let attachments = app.database_dir.path().join("attachments");
+    let stored = std::fs::read_dir(&attachments)
+        .map(|entries| entries.count())
+        .unwrap_or(0);
+    assert_eq!(stored, 0);
+}
+
+#[tokio::test]
+async fn document_hashes_cannot_leave_the_attachment_directory() {
+    let app = TestApp::start().await;
+
+    let mut request = app.request("session", "Read this");
+    request.documents = Some(vec![ExtractedDocument {
+        file_name: "id_rsa".to_string(),
+        mime_type: "text/plain".to_string(),
+        content_hash: "../../../.ssh/id_rsa".to_string(),
+        text: String::new(),
+        token_count: 0,
+    }]);
+    let error = app
+        .cuuri
+        .stream_message(request, &RecordingEvents::default())
+        .await
+        .err()
+        .expect("the question is refused");
+
+    assert!(error.contains("Invalid attachment hash"), "{}", error);
+    assert!(app.provider.chat_requests().is_empty());
+    let history = app.history("session").await;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].status, "failed");
+    assert!(history[0].attachments.is_empty());
+}
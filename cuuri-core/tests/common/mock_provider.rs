@@ -0,0 +1,383 @@
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Body, Bytes, Frame, Incoming};
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use serde_json::json;
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+
+type MockBody = BoxBody<Bytes, io::Error>;
+
+/// A step of a scripted server-sent event stream.
+pub enum StreamStep {
+    Data(String),
+    Delay(Duration),
+    /// Drops the connection in the middle of the response.
+    Disconnect,
+}
+
+/// What the mock answers to the next chat completion request.
+pub enum MockReply {
+    Stream(Vec<StreamStep>),
+    Json(serde_json::Value),
+    Error {
+        status: StatusCode,
+        body: serde_json::Value,
+    },
+    Delayed(Duration, Box<MockReply>),
+}
+
+impl MockReply {
+    pub fn stream() -> ScriptedStream {
+        ScriptedStream(Vec::new())
+    }
+
+    /// A non-streaming answer.
+    pub fn answer(content: &str) -> Self {
+        MockReply::Json(json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": content },
+                "finish_reason": "stop"
+            }]
+        }))
+    }
+
+    /// A non-streaming answer asking for one tool call.
+    pub fn tool_call(id: &str, name: &str, arguments: &str) -> Self {
+        MockReply::Json(json!({
+            "id": "chatcmpl-mock",
+            "object": "chat.completion",
+            "choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": id,
+                        "type": "function",
+                        "function": { "name": name, "arguments": arguments }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        }))
+    }
+
+    /// An error response shaped like OpenAI's.
+    pub fn error(status: u16, message: &str) -> Self {
+        MockReply::Error {
+            status: StatusCode::from_u16(status).expect("valid status code"),
+            body: json!({ "error": { "message": message, "type": "mock_error" } }),
+        }
+    }
+
+    /// Waits before sending the response headers.
+    pub fn after(self, delay: Duration) -> Self {
+        MockReply::Delayed(delay, Box::new(self))
+    }
+}
+
+/// Builds the events of a streamed answer, in order.
+pub struct ScriptedStream(Vec<StreamStep>);
+
+impl ScriptedStream {
+    pub fn event(mut self, event: serde_json::Value) -> Self {
+        self.0.push(StreamStep::Data(event.to_string()));
+        self
+    }
+
+    pub fn token(self, content: &str) -> Self {
+        self.event(json!({
+            "object": "chat.completion.chunk",
+            "choices": [{ "index": 0, "delta": { "content": content } }]
+        }))
+    }
+
+    /// Streams a whole tool call in one event.
+    pub fn tool_call(self, index: usize, id: &str, name: &str, arguments: &str) -> Self {
+        self.event(json!({
+            "object": "chat.completion.chunk",
+            "choices": [{
+                "index": 0,
+                "delta": {
+                    "tool_calls": [{
+                        "index": index,
+                        "id": id,
+                        "type": "function",
+                        "function": { "name": name, "arguments": arguments }
+                    }]
+                }
+            }]
+        }))
+    }
+
+    /// An error reported after the response started, as OpenAI does.
+    pub fn error_event(self, message: &str) -> Self {
+        self.event(json!({ "error": { "message": message, "type": "mock_error" } }))
+    }
+
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.0.push(StreamStep::Delay(delay));
+        self
+    }
+
+    /// Ends the stream with the `[DONE]` marker.
+    pub fn done(mut self) -> MockReply {
+        self.0.push(StreamStep::Data("[DONE]".to_string()));
+        MockReply::Stream(self.0)
+    }
+
+    /// Ends the stream by dropping the connection.
+    pub fn disconnect(mut self) -> MockReply {
+        self.0.push(StreamStep::Disconnect);
+        MockReply::Stream(self.0)
+    }
+}
+
+/// A request received by the mock.
+#[derive(Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub path: String,
+    pub authorization: Option<String>,
+    pub body: serde_json::Value,
+}
+
+#[derive(Default)]
+struct MockState {
+    replies: Mutex<VecDeque<MockReply>>,
+    requests: Mutex<Vec<RecordedRequest>>,
+    models: Mutex<Vec<String>>,
+}
+
+/// An OpenAI-compatible server on 127.0.0.1 that answers chat completion
+/// requests with scripted replies, in the order they were queued. Every
+/// embedding it returns is the same vector.
+pub struct MockProvider {
+    address: SocketAddr,
+    state: Arc<MockState>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockProvider {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind the mock provider");
+        let address = listener.local_addr().expect("mock provider address");
+        let state = Arc::new(MockState::default());
+        let (shutdown, mut shutdown_rx) = oneshot::channel();
+
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let stream = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(_) => continue,
+                    },
+                    _ = &mut shutdown_rx => break,
+                };
+                let state = server_state.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| handle(state.clone(), req));
+                    // Scripted disconnects end connections with an error
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+        });
+
+        MockProvider {
+            address,
+            state,
+            shutdown: Some(shutdown),
+        }
+    }
+
+    /// The URL to use as `openai_base_url`.
+    pub fn base_url(&self) -> String {
+        format!("http://{}/v1", self.address)
+    }
+
+    pub fn push(&self, reply: MockReply) {
+        self.state.replies.lock().unwrap().push_back(reply);
+    }
+
+    pub fn set_models(&self, models: &[&str]) {
+        *self.state.models.lock().unwrap() = models.iter().map(|m| m.to_string()).collect();
+    }
+
+    /// The requests received so far, oldest first.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// The bodies of the chat completion requests received so far.
+    pub fn chat_requests(&self) -> Vec<serde_json::Value> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.path == "/v1/chat/completions")
+            .map(|request| request.body)
+            .collect()
+    }
+
+    /// Stops accepting connections, so further requests fail to connect.
+    pub fn stop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+impl Drop for MockProvider {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Sends the steps of a scripted stream as they are reached.
+struct ScriptBody(mpsc::Receiver<Result<Bytes, io::Error>>);
+
+impl Body for ScriptBody {
+    type Data = Bytes;
+    type Error = io::Error;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Bytes>, io::Error>>> {
+        self.0
+            .poll_recv(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(Frame::data)))
+    }
+}
+
+fn full_body(bytes: impl Into<Bytes>) -> MockBody {
+    Full::new(bytes.into())
+        .map_err(|never: Infallible| match never {})
+        .boxed()
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<MockBody> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(full_body(body.to_string()))
+        .unwrap()
+}
+
+fn stream_response(steps: Vec<StreamStep>) -> Response<MockBody> {
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        for step in steps {
+            let chunk = match step {
+                StreamStep::Data(data) => Ok(Bytes::from(format!("data: {}\n\n", data))),
+                StreamStep::Delay(delay) => {
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                StreamStep::Disconnect => Err(io::Error::other("scripted disconnect")),
+            };
+            if tx.send(chunk).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    Response::builder()
+        .header(CONTENT_TYPE, "text/event-stream")
+        .body(ScriptBody(rx).boxed())
+        .unwrap()
+}
+
+async fn reply_response(reply: MockReply) -> Response<MockBody> {
+    let mut reply = reply;
+    while let MockReply::Delayed(delay, inner) = reply {
+        tokio::time::sleep(delay).await;
+        reply = *inner;
+    }
+
+    match reply {
+        MockReply::Stream(steps) => stream_response(steps),
+        MockReply::Json(body) => json_response(StatusCode::OK, body),
+        MockReply::Error { status, body } => json_response(status, body),
+        MockReply::Delayed(..) => unreachable!(),
+    }
+}
+
+async fn handle(
+    state: Arc<MockState>,
+    req: Request<Incoming>,
+) -> Result<Response<MockBody>, Infallible> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let authorization = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string());
+    let bytes = req
+        .into_body()
+        .collect()
+        .await
+        .map(|body| body.to_bytes())
+        .unwrap_or_default();
+    let body = serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null);
+
+    state.requests.lock().unwrap().push(RecordedRequest {
+        method: method.clone(),
+        path: path.clone(),
+        authorization,
+        body,
+    });
+
+    let response = match (&method, path.as_str()) {
+        (&Method::GET, "/v1/models") => {
+            let models = state.models.lock().unwrap().clone();
+            let data: Vec<serde_json::Value> = models
+                .iter()
+                .map(|id| json!({ "id": id, "object": "model" }))
+                .collect();
+            json_response(StatusCode::OK, json!({ "object": "list", "data": data }))
+        }
+        (&Method::POST, "/v1/embeddings") => {
+            let inputs = state.requests.lock().unwrap().last().unwrap().body["input"].clone();
+            let data: Vec<serde_json::Value> = (0..inputs.as_array().map_or(0, Vec::len))
+                .map(|index| json!({ "index": index, "embedding": [1.0, 0.0] }))
+                .collect();
+            json_response(StatusCode::OK, json!({ "object": "list", "data": data }))
+        }
+        (&Method::POST, "/v1/chat/completions") => {
+            let reply = state.replies.lock().unwrap().pop_front();
+            match reply {
+                Some(reply) => reply_response(reply).await,
+                None => json_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json!({ "error": { "message": "No reply was scripted for this request" } }),
+                ),
+            }
+        }
+        _ => json_response(
+            StatusCode::NOT_FOUND,
+            json!({ "error": { "message": format!("Unknown endpoint {} {}", method, path) } }),
+        ),
+    };
+    Ok(response)
+}
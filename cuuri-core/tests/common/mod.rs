@@ -0,0 +1,150 @@
+//! A Cuuri with a temporary database, talking to a mock provider.
+
+#![allow(dead_code)]
+
+pub mod mock_provider;
+
+use cuuri_core::app_type::RawDatabaseChatEntry;
+use cuuri_core::chat_events::{ApprovalFuture, ChatEvents};
+use cuuri_core::chat_exchange::ChatRequest;
+use cuuri_core::config::Config;
+use cuuri_core::db_pool::DbState;
+use cuuri_core::open_chat_database::open_chat_database_at;
+use cuuri_core::tool_approvals::{ApprovalDecision, ToolApprovalRequest};
+use cuuri_core::tool_calls::{ToolCall, ToolCallResult};
+use cuuri_core::Cuuri;
+use mock_provider::MockProvider;
+use std::sync::Mutex;
+use tempfile::TempDir;
+use tokio::sync::Notify;
+
+pub const TEST_API_KEY: &str = "test-key";
+pub const TEST_MODEL: &str = "mock-model";
+
+pub struct TestApp {
+    pub cuuri: Cuuri,
+    pub provider: MockProvider,
+    /// Holds the database, its attachments and backups; removed with the app.
+    pub database_dir: TempDir,
+}
+
+impl TestApp {
+    /// A Cuuri with its own empty database, using `provider` for its answers.
+    pub async fn start() -> Self {
+        Self::start_with(Config::default()).await
+    }
+
+    /// Like `start`, with the rest of the settings taken from `config`.
+    /// Attachments are kept next to the database and backups go there too,
+    /// so nothing is written to the user's data directory.
+    pub async fn start_with(config: Config) -> Self {
+        let provider = MockProvider::start().await;
+        let database_dir = tempfile::tempdir().expect("create the database directory");
+        let backup_dir = database_dir.path().join("backups");
+
+        let config = Config {
+            openai_api_key: TEST_API_KEY.to_string(),
+            openai_base_url: Some(provider.base_url()),
+            default_model: Some(TEST_MODEL.to_string()),
+            backup_directory: Some(backup_dir.to_string_lossy().to_string()),
+            ..config
+        };
+        let state = open_chat_database_at(&config, &database_dir.path().join("chat.db"), None);
+        if let Some(error) = state.error() {
+            panic!("Failed to open the test database: {}", error);
+        }
+
+        TestApp {
+            cuuri: Cuuri::new(config, DbState::new(state)),
+            provider,
+            database_dir,
+        }
+    }
+
+    pub fn request(&self, session_id: &str, message: &str) -> ChatRequest {
+        ChatRequest {
+            session_id: session_id.to_string(),
+            message: message.to_string(),
+            base64_images: None,
+            image_detail: None,
+            documents: None,
+            folder_ids: None,
+            model: TEST_MODEL.to_string(),
+            api_key: TEST_API_KEY.to_string(),
+        }
+    }
+
+    pub async fn history(&self, session_id: &str) -> Vec<RawDatabaseChatEntry> {
+        self.cuuri
+            .chat_history(Some(session_id.to_string()))
+            .await
+            .expect("load the chat history")
+    }
+}
+
+/// Records what happens while an answer arrives. Tools that need approval
+/// are approved unless `unanswered_approvals` is set.
+#[derive(Default)]
+pub struct RecordingEvents {
+    pub tokens: Mutex<Vec<String>>,
+    pub tool_calls: Mutex<Vec<ToolCall>>,
+    pub tool_results: Mutex<Vec<ToolCallResult>>,
+    /// Notified for every token.
+    pub token_received: Notify,
+    /// Leaves approval requests waiting forever, like a closed window.
+    pub unanswered_approvals: bool,
+    /// Notified for every approval request.
+    pub approval_requested: Notify,
+}
+
+impl RecordingEvents {
+    pub fn text(&self) -> String {
+        self.tokens.lock().unwrap().concat()
+    }
+}
+
+impl ChatEvents for RecordingEvents {
+    fn token(&self, token: &str) {
+        self.tokens.lock().unwrap().push(token.to_string());
+        self.token_received.notify_one();
+    }
+
+    fn tool_call(&self, call: &ToolCall) {
+        self.tool_calls.lock().unwrap().push(call.clone());
+    }
+
+    fn tool_result(&self, result: &ToolCallResult) {
+        self.tool_results.lock().unwrap().push(result.clone());
+    }
+
+    fn request_approval(&self, _request: ToolApprovalRequest) -> ApprovalFuture {
+        self.approval_requested.notify_one();
+        if self.unanswered_approvals {
+            return Box::pin(std::future::pending());
+        }
+        Box::pin(async { Some(ApprovalDecision::Approved) })
+    }
+}
+
+/// The text of a message sent to the provider.
+pub fn message_text(message: &serde_json::Value) -> String {
+    match &message["content"] {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| part["text"].as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+/// The roles of the messages sent to the provider, in order.
+pub fn roles(body: &serde_json::Value) -> Vec<String> {
+    body["messages"]
+        .as_array()
+        .expect("messages")
+        .iter()
+        .map(|message| message["role"].as_str().unwrap_or_default().to_string())
+        .collect()
+}
@@ -0,0 +1,42 @@
+mod common;
+
+use common::TestApp;
+use std::fs;
+use std::sync::Mutex;
+
+#[tokio::test]
+async fn files_without_text_are_remembered_and_lose_their_chunks() {
+    let app = TestApp::start().await;
+    let folder = tempfile::tempdir().expect("create a folder");
+    fs::write(folder.path().join("notes.txt"), "Some notes").expect("write notes");
+    fs::write(folder.path().join("empty.txt"), "").expect("write an empty file");
+    let path = folder.path().to_string_lossy().to_string();
+
+    let info = app
+        .cuuri
+        .index_folder(path.clone(), |_| {})
+        .await
+        .expect("index the folder");
+    assert_eq!((info.file_count, info.chunk_count), (2, 1));
+
+    let progress = Mutex::new(Vec::new());
+    app.cuuri
+        .index_folder(path.clone(), |p| {
+            progress.lock().unwrap().push(p.files_total)
+        })
+        .await
+        .expect("index the folder again");
+    assert_eq!(
+        *progress.lock().unwrap(),
+        [0],
+        "unchanged files were indexed again"
+    );
+
+    fs::write(folder.path().join("notes.txt"), "  \n").expect("empty the notes");
+    let info = app
+        .cuuri
+        .index_folder(path, |_| {})
+        .await
+        .expect("index the emptied folder");
+    assert_eq!((info.file_count, info.chunk_count), (2, 0));
+}
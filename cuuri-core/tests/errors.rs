@@ -0,0 +1,143 @@
+mod common;
+
+use common::mock_provider::MockReply;
+use common::{RecordingEvents, TestApp, TEST_API_KEY};
+use std::time::Duration;
+
+#[tokio::test]
+async fn http_errors_fail_the_answer_but_keep_the_question() {
+    let app = TestApp::start().await;
+    app.provider
+        .push(MockReply::error(401, "Incorrect API key"));
+
+    let error = app
+        .cuuri
+        .stream_message(app.request("session", "Hi"), &RecordingEvents::default())
+        .await
+        .err()
+        .expect("the answer fails");
+
+    assert!(error.contains("HTTP 401"), "{}", error);
+    assert!(error.contains("Incorrect API key"), "{}", error);
+    let history = app.history("session").await;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].question, "Hi");
+    assert_eq!(history[0].answer, "");
+    assert_eq!(history[0].status, "failed");
+}
+
+#[tokio::test]
+async fn dropped_connections_keep_what_was_received() {
+    let app = TestApp::start().await;
+    app.provider.push(
+        MockReply::stream()
+            .token("Half an")
+            .delay(Duration::from_millis(20))
+            .disconnect(),
+    );
+
+    let events = RecordingEvents::default();
+    app.cuuri
+        .stream_message(app.request("session", "Hi"), &events)
+        .await
+        .err()
+        .expect("the answer fails");
+
+    assert_eq!(events.text(), "Half an");
+    let history = app.history("session").await;
+    assert_eq!(history[0].answer, "Half an");
+    assert_eq!(history[0].status, "failed");
+}
+
+#[tokio::test]
+async fn error_events_fail_the_answer() {
+    let app = TestApp::start().await;
+    app.provider.push(
+        MockReply::stream()
+            .token("Some")
+            .error_event("The server had an error while processing your request")
+            .done(),
+    );
+
+    let error = app
+        .cuuri
+        .stream_message(app.request("session", "Hi"), &RecordingEvents::default())
+        .await
+        .err()
+        .expect("the answer fails");
+
+    assert!(error.contains("The server had an error"), "{}", error);
+    let history = app.history("session").await;
+    assert_eq!(history[0].answer, "Some");
+    assert_eq!(history[0].status, "failed");
+}
+
+#[tokio::test]
+async fn unreachable_providers_fail_the_answer() {
+    let mut app = TestApp::start().await;
+    app.provider.stop();
+    // Let the server notice before connecting
+    tokio::task::yield_now().await;
+
+    let error = app
+        .cuuri
+        .stream_message(app.request("session", "Hi"), &RecordingEvents::default())
+        .await
+        .err()
+        .expect("the answer fails");
+
+    assert!(error.contains("Failed to send request"), "{}", error);
+    assert_eq!(app.history("session").await[0].status, "failed");
+}
+
+#[tokio::test]
+async fn retrieval_errors_keep_the_question() {
+    let mut app = TestApp::start().await;
+    app.provider.stop();
+    tokio::task::yield_now().await;
+
+    let mut request = app.request("session", "What do the docs say?");
+    request.folder_ids = Some(vec![1]);
+    let error = app
+        .cuuri
+        .stream_message(request, &RecordingEvents::default())
+        .await
+        .err()
+        .expect("the answer fails");
+
+    assert!(error.contains("embeddings"), "{}", error);
+    let history = app.history("session").await;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].question, "What do the docs say?");
+    assert_eq!(history[0].status, "failed");
+}
+
+#[tokio::test]
+async fn errors_without_streaming_fail_the_answer() {
+    let app = TestApp::start().await;
+    app.provider
+        .push(MockReply::error(429, "Rate limit reached"));
+
+    let error = app
+        .cuuri
+        .complete_message(app.request("session", "Hi"), &RecordingEvents::default())
+        .await
+        .err()
+        .expect("the answer fails");
+
+    assert!(error.contains("HTTP 429"), "{}", error);
+    assert!(error.contains("Rate limit reached"), "{}", error);
+    assert_eq!(app.history("session").await[0].status, "failed");
+}
+
+#[tokio::test]
+async fn model_list_errors_are_reported() {
+    let mut app = TestApp::start().await;
+    app.provider.stop();
+    tokio::task::yield_now().await;
+
+    app.cuuri
+        .available_models(TEST_API_KEY)
+        .await
+        .expect_err("listing models fails");
+}
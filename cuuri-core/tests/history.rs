@@ -0,0 +1,136 @@
+mod common;
+
+use common::mock_provider::MockReply;
+use common::{message_text, roles, RecordingEvents, TestApp};
+
+#[tokio::test]
+async fn later_questions_carry_the_session_so_far() {
+    let app = TestApp::start().await;
+    app.provider
+        .push(MockReply::stream().token("Paris.").done());
+    app.provider
+        .push(MockReply::stream().token("About 2 million.").done());
+
+    let events = RecordingEvents::default();
+    app.cuuri
+        .stream_message(app.request("session", "Capital of France?"), &events)
+        .await
+        .expect("first answer");
+    app.cuuri
+        .stream_message(app.request("session", "Its population?"), &events)
+        .await
+        .expect("second answer");
+
+    let requests = app.provider.chat_requests();
+    assert_eq!(roles(&requests[1]), ["user", "assistant", "user"]);
+    let messages = requests[1]["messages"].as_array().expect("messages");
+    assert_eq!(message_text(&messages[0]), "Capital of France?");
+    assert_eq!(message_text(&messages[1]), "Paris.");
+    assert_eq!(message_text(&messages[2]), "Its population?");
+
+    let history = app.history("session").await;
+    let questions: Vec<&str> = history
+        .iter()
+        .map(|entry| entry.question.as_str())
+        .collect();
+    assert_eq!(questions, ["Capital of France?", "Its population?"]);
+}
+
+#[tokio::test]
+async fn sessions_are_kept_apart() {
+    let app = TestApp::start().await;
+    for answer in ["One", "Two", "Three"] {
+        app.provider.push(MockReply::stream().token(answer).done());
+    }
+
+    let events = RecordingEvents::default();
+    for (session_id, question) in [("first", "Q1"), ("second", "Q2"), ("first", "Q3")] {
+        app.cuuri
+            .stream_message(app.request(session_id, question), &events)
+            .await
+            .expect("answer");
+    }
+
+    // The question in the second session starts a new conversation
+    let requests = app.provider.chat_requests();
+    assert_eq!(roles(&requests[1]), ["user"]);
+    assert_eq!(roles(&requests[2]), ["user", "assistant", "user"]);
+
+    let sessions = app.cuuri.list_sessions().await.expect("sessions");
+    assert_eq!(sessions.len(), 2);
+    let first = sessions
+        .iter()
+        .find(|session| session.session_id == "first")
+        .expect("first session");
+    assert_eq!(first.first_question, "Q1");
+    assert_eq!(first.question_count, 2);
+
+    let all = app.cuuri.chat_history(None).await.expect("history");
+    assert_eq!(all.len(), 3);
+    let mut ids = app.cuuri.session_ids().await.expect("session ids");
+    ids.sort();
+    assert_eq!(ids, ["first", "second"]);
+}
+
+#[tokio::test]
+async fn tool_calls_are_replayed_in_later_requests() {
+    let app = TestApp::start().await;
+    app.provider.push(
+        MockReply::stream()
+            .tool_call(0, "call_1", "calculator", r#"{"expression":"3 * 3"}"#)
+            .done(),
+    );
+    app.provider.push(MockReply::stream().token("Nine.").done());
+    app.provider.push(MockReply::stream().token("Yes.").done());
+
+    let events = RecordingEvents::default();
+    app.cuuri
+        .stream_message(app.request("session", "3 * 3?"), &events)
+        .await
+        .expect("first answer");
+    app.cuuri
+        .stream_message(app.request("session", "Sure?"), &events)
+        .await
+        .expect("second answer");
+
+    let requests = app.provider.chat_requests();
+    assert_eq!(
+        roles(&requests[2]),
+        ["user", "assistant", "tool", "assistant", "user"]
+    );
+    let messages = requests[2]["messages"].as_array().expect("messages");
+    assert_eq!(messages[1]["tool_calls"][0]["id"], "call_1");
+    assert_eq!(messages[2]["tool_call_id"], "call_1");
+    assert_eq!(message_text(&messages[3]), "Nine.");
+}
+
+#[tokio::test]
+async fn failed_answers_are_left_out_of_later_requests() {
+    let app = TestApp::start().await;
+    app.provider.push(MockReply::error(500, "Overloaded"));
+    app.provider.push(MockReply::stream().token("Hi!").done());
+
+    let events = RecordingEvents::default();
+    app.cuuri
+        .stream_message(app.request("session", "Hello"), &events)
+        .await
+        .err()
+        .expect("the first answer fails");
+    app.cuuri
+        .stream_message(app.request("session", "Hello again"), &events)
+        .await
+        .expect("second answer");
+
+    let requests = app.provider.chat_requests();
+    assert_eq!(roles(&requests[1]), ["user"]);
+    assert_eq!(message_text(&requests[1]["messages"][0]), "Hello again");
+
+    // Both questions are still in the history
+    let statuses: Vec<String> = app
+        .history("session")
+        .await
+        .into_iter()
+        .map(|entry| entry.status)
+        .collect();
+    assert_eq!(statuses, ["failed", "complete"]);
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::mock_provider::MockReply;
+use common::{RecordingEvents, TestApp};
+use cuuri_core::config::{Config, McpServerConfig};
+use cuuri_core::mcp_client::McpClient;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+
+/// The `fake_mcp_server` example, which `cargo test` builds next to the tests.
+fn fake_server() -> McpServerConfig {
+    let test_exe = env::current_exe().expect("locate the test");
+    let path: PathBuf = test_exe
+        .parent()
+        .and_then(|deps| deps.parent())
+        .expect("locate the target directory")
+        .join("examples")
+        .join(format!("fake_mcp_server{}", env::consts::EXE_SUFFIX));
+    assert!(
+        path.exists(),
+        "{} is missing; run the tests with `cargo test -p cuuri-core`, which builds the examples",
+        path.display()
+    );
+
+    McpServerConfig {
+        command: Some(path.to_string_lossy().to_string()),
+        ..McpServerConfig::default()
+    }
+}
+
+#[tokio::test]
+async fn talks_to_a_stdio_server() {
+    // Connecting performs the `initialize` handshake
+    let client = McpClient::connect("fake", &fake_server())
+        .await
+        .expect("initialize");
+
+    let tools = client.list_tools().await.expect("list the tools");
+    let names: Vec<&str> = tools.iter().map(|tool| tool.name.as_str()).collect();
+    assert_eq!(names, ["echo", "add"]);
+    assert_eq!(tools[1].input_schema["required"], json!(["a", "b"]));
+
+    let echoed = client
+        .call_tool("echo", json!({ "text": "Hello" }))
+        .await
+        .expect("call echo");
+    assert_eq!(echoed, "Hello");
+    let sum = client
+        .call_tool("add", json!({ "a": 2, "b": 3.5 }))
+        .await
+        .expect("call add");
+    assert_eq!(sum, "5.5");
+
+    let error = client
+        .call_tool("add", json!({ "a": "two" }))
+        .await
+        .expect_err("add needs numbers");
+    assert!(error.contains("must be numbers"), "{}", error);
+
+    client.shutdown().await;
+}
+
+#[tokio::test]
+async fn the_model_can_call_server_tools() {
+    let mut mcp_servers = BTreeMap::new();
+    mcp_servers.insert("fake".to_string(), fake_server());
+    let app = TestApp::start_with(Config {
+        mcp_servers,
+        ..Config::default()
+    })
+    .await;
+    assert!(app.cuuri.sync_mcp_servers().await);
+
+    let statuses = app.cuuri.mcp_server_statuses();
+    assert!(statuses[0].connected, "{:?}", statuses[0].error);
+    assert_eq!(statuses[0].tools, ["fake__echo", "fake__add"]);
+
+    app.provider.push(
+        MockReply::stream()
+            .tool_call(0, "call_1", "fake__add", r#"{"a":40,"b":2}"#)
+            .done(),
+    );
+    app.provider.push(MockReply::stream().token("42.").done());
+    let events = RecordingEvents::default();
+    app.cuuri
+        .stream_message(app.request("session", "40 + 2?"), &events)
+        .await
+        .expect("answer");
+
+    let results = events.tool_results.lock().unwrap().clone();
+    assert_eq!(results[0].content, "42");
+    assert!(!results[0].is_error);
+}
+
+#[tokio::test]
+async fn tool_names_that_sanitize_alike_stay_apart() {
+    // Both server names sanitize to `my_fake`
+    let mut mcp_servers = BTreeMap::new();
+    mcp_servers.insert("my fake".to_string(), fake_server());
+    mcp_servers.insert("my.fake".to_string(), fake_server());
+    let app = TestApp::start_with(Config {
+        mcp_servers,
+        ..Config::default()
+    })
+    .await;
+    app.cuuri.sync_mcp_servers().await;
+
+    let statuses = app.cuuri.mcp_server_statuses();
+    assert_eq!(statuses[0].tools.len(), 2);
+    assert_eq!(statuses[1].tools.len(), 2);
+    for tools in [&statuses[0].tools, &statuses[1].tools] {
+        assert!(tools[0].starts_with("my_fake__echo_"), "{:?}", tools);
+    }
+    assert_ne!(statuses[0].tools, statuses[1].tools);
+}
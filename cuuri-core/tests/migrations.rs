@@ -0,0 +1,53 @@
+use cuuri_core::config::Config;
+use cuuri_core::open_chat_database::open_chat_database_at;
+use rusqlite::Connection;
+use std::fs;
+use std::path::Path;
+
+/// Turns the database at `path` back into one from before the latest migration.
+fn undo_latest_migration(path: &Path) {
+    let conn = Connection::open(path).expect("open the database");
+    conn.execute_batch(
+        "DROP TABLE indexed_files;
+         DELETE FROM __diesel_schema_migrations
+         WHERE version = (SELECT MAX(version) FROM __diesel_schema_migrations);
+         INSERT INTO chat_histories (session_id, question, answer, created_at)
+         VALUES ('kept', 'Q', 'A', '2026-01-01 00:00:00');",
+    )
+    .expect("undo the latest migration");
+}
+
+#[test]
+fn the_opened_database_is_backed_up_before_migrating() {
+    let dir = tempfile::tempdir().expect("create a directory");
+    let database = dir.path().join("chat.db");
+    let backups = dir.path().join("backups");
+    let config = Config {
+        backup_directory: Some(backups.to_string_lossy().to_string()),
+        ..Config::default()
+    };
+
+    let state = open_chat_database_at(&config, &database, None);
+    assert!(state.error().is_none(), "{:?}", state.error());
+    drop(state);
+    undo_latest_migration(&database);
+
+    let state = open_chat_database_at(&config, &database, None);
+    assert!(state.error().is_none(), "{:?}", state.error());
+
+    let backup = fs::read_dir(&backups)
+        .expect("the backup directory")
+        .map(|entry| entry.expect("a backup").path())
+        .find(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with("pre-migration-"))
+        })
+        .expect("a backup from before the migration");
+    let conn = Connection::open(backup).expect("open the backup");
+    let session: String = conn
+        .query_row("SELECT session_id FROM chat_histories", [], |row| {
+            row.get(0)
+        })
+        .expect("the chat in the backup");
+    assert_eq!(session, "kept");
+}
@@ -0,0 +1,274 @@
+mod common;
+
+use common::mock_provider::MockReply;
+use common::{message_text, RecordingEvents, TestApp, TEST_API_KEY, TEST_MODEL};
+use cuuri_core::open_chat_database::open_chat_database_at;
+use std::time::Duration;
+
+#[tokio::test]
+async fn streams_tokens_and_stores_the_answer() {
+    let app = TestApp::start().await;
+    app.provider.push(
+        MockReply::stream()
+            .token("Hel")
+            .token("lo")
+            .delay(Duration::from_millis(20))
+            .token("!")
+            .done(),
+    );
+
+    let events = RecordingEvents::default();
+    let response = app
+        .cuuri
+        .stream_message(app.request("session", "Hi"), &events)
+        .await
+        .expect("answer");
+
+    assert_eq!(response.response, "Hello!");
+    assert_eq!(*events.tokens.lock().unwrap(), ["Hel", "lo", "!"]);
+
+    let history = app.history("session").await;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].question, "Hi");
+    assert_eq!(history[0].answer, "Hello!");
+    assert_eq!(history[0].status, "complete");
+    assert_eq!(history[0].model.as_deref(), Some(TEST_MODEL));
+}
+
+#[tokio::test]
+async fn sends_the_question_to_the_configured_provider() {
+    let app = TestApp::start().await;
+    app.provider.push(MockReply::stream().token("Yes").done());
+
+    app.cuuri
+        .stream_message(
+            app.request("session", "Are you there?"),
+            &RecordingEvents::default(),
+        )
+        .await
+        .expect("answer");
+
+    let requests = app.provider.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].path, "/v1/chat/completions");
+    assert_eq!(
+        requests[0].authorization.as_deref(),
+        Some(format!("Bearer {}", TEST_API_KEY).as_str())
+    );
+
+    let body = &requests[0].body;
+    assert_eq!(body["model"], TEST_MODEL);
+    assert_eq!(body["stream"], true);
+    let messages = body["messages"].as_array().expect("messages");
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["role"], "user");
+    assert_eq!(message_text(&messages[0]), "Are you there?");
+}
+
+#[tokio::test]
+async fn cancelling_keeps_the_partial_answer() {
+    let app = TestApp::start().await;
+    app.provider.push(
+        MockReply::stream()
+            .token("Partial")
+            .delay(Duration::from_secs(30))
+            .token(" never sent")
+            .done(),
+    );
+
+    let events = RecordingEvents::default();
+    let (response, cancelled) = tokio::join!(
+        app.cuuri
+            .stream_message(app.request("session", "Tell me a long story"), &events),
+        async {
+            events.token_received.notified().await;
+            app.cuuri.cancel_message("session")
+        }
+    );
+
+    assert!(cancelled);
+    assert_eq!(response.expect("answer").response, "Partial");
+    let history = app.history("session").await;
+    assert_eq!(history[0].answer, "Partial");
+    assert_eq!(history[0].status, "cancelled");
+}
+
+#[tokio::test]
+async fn waits_for_slow_providers() {
+    let app = TestApp::start().await;
+    app.provider.push(
+        MockReply::stream()
+            .token("Late")
+            .done()
+            .after(Duration::from_millis(300)),
+    );
+
+    let response = app
+        .cuuri
+        .stream_message(
+            app.request("session", "Hello?"),
+            &RecordingEvents::default(),
+        )
+        .await
+        .expect("answer");
+
+    assert_eq!(response.response, "Late");
+}
+
+#[tokio::test]
+async fn runs_requested_tools_and_sends_back_their_results() {
+    let app = TestApp::start().await;
+    app.provider.push(
+        MockReply::stream()
+            .tool_call(0, "call_1", "calculator", r#"{"expression":"6 * 7"}"#)
+            .done(),
+    );
+    app.provider
+        .push(MockReply::stream().token("It is 42.").done());
+
+    let events = RecordingEvents::default();
+    let response = app
+        .cuuri
+        .stream_message(app.request("session", "What is 6 * 7?"), &events)
+        .await
+        .expect("answer");
+
+    assert_eq!(response.response, "It is 42.");
+    let results = events.tool_results.lock().unwrap().clone();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].name, "calculator");
+    assert!(results[0].content.contains("42"), "{}", results[0].content);
+    assert!(!results[0].is_error);
+
+    // The second request carries the call and its result
+    let requests = app.provider.chat_requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0]["tools"]
+        .as_array()
+        .expect("tools")
+        .iter()
+        .any(|tool| tool["function"]["name"] == "calculator"));
+    let messages = requests[1]["messages"].as_array().expect("messages");
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[1]["role"], "assistant");
+    assert_eq!(messages[1]["tool_calls"][0]["id"], "call_1");
+    assert_eq!(messages[2]["role"], "tool");
+    assert_eq!(messages[2]["tool_call_id"], "call_1");
+    assert!(message_text(&messages[2]).contains("42"));
+
+    let history = app.history("session").await;
+    assert_eq!(history[0].answer, "It is 42.");
+    assert_eq!(history[0].tool_calls.len(), 1);
+    assert_eq!(history[0].tool_calls[0].name, "calculator");
+    assert_eq!(history[0].tool_calls[0].call_id, "call_1");
+}
+
+#[tokio::test]
+async fn complete_message_waits_for_the_whole_answer() {
+    let app = TestApp::start().await;
+    app.provider.push(MockReply::tool_call(
+        "call_1",
+        "calculator",
+        r#"{"expression":"2 + 2"}"#,
+    ));
+    app.provider.push(MockReply::answer("Four."));
+
+    let events = RecordingEvents::default();
+    let response = app
+        .cuuri
+        .complete_message(app.request("session", "2 + 2?"), &events)
+        .await
+        .expect("answer");
+
+    assert_eq!(response.response, "Four.");
+    assert!(events.tokens.lock().unwrap().is_empty());
+    let requests = app.provider.chat_requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0].get("stream").is_none());
+    assert_eq!(requests[1]["messages"][2]["role"], "tool");
+
+    let history = app.history("session").await;
+    assert_eq!(history[0].answer, "Four.");
+    assert_eq!(history[0].status, "complete");
+    assert_eq!(history[0].tool_calls.len(), 1);
+}
+
+#[tokio::test]
+async fn cancelling_stops_complete_message_waiting_for_approval() {
+    let app = TestApp::start().await;
+    app.provider.push(MockReply::tool_call(
+        "call_1",
+        "read_local_file",
+        r#"{"path":"/etc/hostname"}"#,
+    ));
+
+    let events = RecordingEvents {
+        unanswered_approvals: true,
+        ..RecordingEvents::default()
+    };
+    let (response, cancelled) = tokio::join!(
+        app.cuuri
+            .complete_message(app.request("session", "Read my hostname"), &events),
+        async {
+            events.approval_requested.notified().await;
+            app.cuuri.cancel_message("session")
+        }
+    );
+
+    assert!(cancelled);
+    assert_eq!(response.expect("answer").response, "");
+    let results = events.tool_results.lock().unwrap().clone();
+    assert!(results[0].is_error);
+    assert_eq!(app.provider.chat_requests().len(), 1);
+    let history = app.history("session").await;
+    assert_eq!(history[0].status, "cancelled");
+    assert_eq!(history[0].tool_calls.len(), 1);
+}
+
+#[tokio::test]
+async fn lists_the_models_of_the_configured_provider() {
+    let app = TestApp::start().await;
+    app.provider.set_models(&["mock-b", "mock-a"]);
+
+    let models = app
+        .cuuri
+        .available_models(TEST_API_KEY)
+        .await
+        .expect("models");
+
+    assert_eq!(models, ["mock-a", "mock-b"]);
+    assert_eq!(app.provider.requests()[0].path, "/v1/models");
+}
+
+#[tokio::test]
+async fn answers_stay_in_their_database_when_it_is_swapped() {
+    let app = TestApp::start().await;
+    app.provider.push(
+        MockReply::stream()
+            .token("Hello")
+            .delay(Duration::from_millis(100))
+            .token(" there")
+            .done(),
+    );
+    let original = app.cuuri.db().state();
+    let other_dir = tempfile::tempdir().expect("create another database directory");
+    let other = open_chat_database_at(&app.cuuri.config(), &other_dir.path().join("chat.db"), None);
+
+    let events = RecordingEvents::default();
+    let (response, ()) = tokio::join!(
+        app.cuuri
+            .stream_message(app.request("session", "Hi"), &events),
+        async {
+            events.token_received.notified().await;
+            app.cuuri.db().set(other.clone());
+        }
+    );
+    assert_eq!(response.expect("answer").response, "Hello there");
+
+    assert!(app.history("session").await.is_empty());
+    app.cuuri.db().set(original);
+    let history = app.history("session").await;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].answer, "Hello there");
+    assert_eq!(history[0].status, "complete");
+}
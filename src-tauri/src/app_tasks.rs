@@ -0,0 +1,60 @@
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Starts the services that run for as long as the app does.
+pub fn start_background_tasks(app: &AppHandle) {
+    let cuuri = app.state::<Arc<Cuuri>>().inner().clone();
+    let embedder = cuuri.clone();
+    tauri::async_runtime::spawn(async move { embedder.run_chat_embedder().await });
+    tauri::async_runtime::spawn(async move { cuuri.run_backup_scheduler().await });
+    restart_mcp_servers(app);
+    restart_proxy_server(app);
+}
+
+/// Re-reads the config file and tells the frontend about the outcome.
+///
+/// An invalid file keeps the previous configuration in place and emits `config-error`.
+pub fn reload_config(app: &AppHandle) {
+    match app.state::<Arc<Cuuri>>().reload_config() {
+        Ok(Some(_)) => {
+            // The file's values, like `update_settings` sends, so a key from
+            // the environment never reaches the window
+            emit_settings_changed(app);
+            restart_mcp_servers(app);
+            restart_proxy_server(app);
+        }
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("Failed to reload config file: {}", e);
+            let _ = app.emit("config-error", e);
+        }
+    }
+}
+
+/// Sends the settings in the config file to the window as `settings-changed`.
+pub fn emit_settings_changed(app: &AppHandle) {
+    match app.state::<Arc<Cuuri>>().settings() {
+        Ok(settings) => {
+            let _ = app.emit("settings-changed", settings);
+        }
+        Err(e) => eprintln!("Failed to read the settings: {}", e),
+    }
+}
+
+/// Brings the running MCP servers in line with the current config in the background.
+pub fn restart_mcp_servers(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let cuuri = app.state::<Arc<Cuuri>>();
+        if cuuri.sync_mcp_servers().await {
+            let _ = app.emit("mcp-servers-changed", cuuri.mcp_server_statuses());
+        }
+    });
+}
+
+/// Brings the proxy server in line with the current config in the background.
+pub fn restart_proxy_server(app: &AppHandle) {
+    let cuuri = app.state::<Arc<Cuuri>>().inner().clone();
+    tauri::async_runtime::spawn(async move { cuuri.sync_proxy_server().await });
+}
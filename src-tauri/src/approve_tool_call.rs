@@ -0,0 +1,23 @@
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+/// Lets a waiting tool call run. With `always`, the tool no longer asks for
+/// approval; the choice is saved in `always_allowed_tools`.
+#[tauri::command]
+pub async fn approve_tool_call(
+    app: AppHandle,
+    cuuri: State<'_, Arc<Cuuri>>,
+    request_id: String,
+    always: Option<bool>,
+) -> Result<(), String> {
+    let tool_name = cuuri.approve_tool_call(&request_id)?;
+
+    if always.unwrap_or(false) {
+        if let Some(settings) = cuuri.always_allow_tool(tool_name)? {
+            let _ = app.emit("settings-changed", settings);
+        }
+    }
+
+    Ok(())
+}
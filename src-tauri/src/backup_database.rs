@@ -0,0 +1,12 @@
+use cuuri_core::Cuuri;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::State;
+
+/// Writes a copy of the chat database to `path`, which may be done while
+/// the app is in use. The copy of an encrypted database has the same key.
+#[tauri::command]
+pub async fn backup_database(cuuri: State<'_, Arc<Cuuri>>, path: String) -> Result<String, String> {
+    cuuri.backup_database(PathBuf::from(&path)).await?;
+    Ok(path)
+}
@@ -0,0 +1,11 @@
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn cancel_chat_response(
+    cuuri: State<'_, Arc<Cuuri>>,
+    input_session_id: String,
+) -> Result<bool, String> {
+    Ok(cuuri.cancel_message(&input_session_id))
+}
@@ -0,0 +1,14 @@
+use cuuri_core::app_type::DatabaseEncryptionStatus;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Re-encrypts the chat database with a new passphrase, or without one with
+/// a new generated key kept in the OS keyring.
+#[tauri::command]
+pub async fn change_database_key(
+    cuuri: State<'_, Arc<Cuuri>>,
+    passphrase: Option<String>,
+) -> Result<DatabaseEncryptionStatus, String> {
+    cuuri.change_database_key(passphrase).await
+}
@@ -1,16 +0,0 @@
-use serde::{Deserialize, Serialize};
-use std::fs;
-
-#[derive(Deserialize, Serialize)]
-pub struct Config {
-    pub openai_api_key: String,
-    pub default_model: Option<String>,
-}
-
-impl Config {
-    pub fn from_file(path: &str) -> Result<Self, String> {
-        let content =
-            fs::read_to_string(path).map_err(|e| format!("Failed to read config file: {}", e))?;
-        toml::from_str(&content).map_err(|e| format!("Failed to parse config file: {}", e))
-    }
-}
@@ -0,0 +1,9 @@
+use cuuri_core::app_type::ProfileInfo;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn create_profile(cuuri: State<'_, Arc<Cuuri>>, name: String) -> Result<ProfileInfo, String> {
+    cuuri.create_profile(name)
+}
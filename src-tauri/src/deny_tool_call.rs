@@ -0,0 +1,14 @@
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Refuses a waiting tool call; the model is told it was denied, with the
+/// optional reason.
+#[tauri::command]
+pub fn deny_tool_call(
+    cuuri: State<'_, Arc<Cuuri>>,
+    request_id: String,
+    reason: Option<String>,
+) -> Result<(), String> {
+    cuuri.deny_tool_call(&request_id, reason)
+}
@@ -0,0 +1,14 @@
+use cuuri_core::app_type::DatabaseEncryptionStatus;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Encrypts the plain chat database in place, with `passphrase`, or without
+/// one with a generated key kept in the OS keyring.
+#[tauri::command]
+pub async fn encrypt_database(
+    cuuri: State<'_, Arc<Cuuri>>,
+    passphrase: Option<String>,
+) -> Result<DatabaseEncryptionStatus, String> {
+    cuuri.encrypt_database(passphrase).await
+}
@@ -0,0 +1,20 @@
+use cuuri_core::conversation_export::ExportFormat;
+use cuuri_core::Cuuri;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::State;
+
+/// Exports every conversation into `dir`, returning the files written. JSON
+/// goes into a single file that can be imported again; Markdown and HTML get
+/// one file per conversation.
+#[tauri::command]
+pub async fn export_all(
+    cuuri: State<'_, Arc<Cuuri>>,
+    format: ExportFormat,
+    dir: String,
+    embed_images: Option<bool>,
+) -> Result<Vec<String>, String> {
+    cuuri
+        .export_all(format, PathBuf::from(dir), embed_images.unwrap_or(false))
+        .await
+}
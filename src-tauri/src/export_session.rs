@@ -0,0 +1,26 @@
+use cuuri_core::conversation_export::ExportFormat;
+use cuuri_core::Cuuri;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::State;
+
+/// Writes one conversation to `path`. Markdown exports link images stored
+/// next to the file unless `embed_images` is set.
+#[tauri::command]
+pub async fn export_session(
+    cuuri: State<'_, Arc<Cuuri>>,
+    session_id: String,
+    format: ExportFormat,
+    path: String,
+    embed_images: Option<bool>,
+) -> Result<String, String> {
+    cuuri
+        .export_session(
+            session_id,
+            format,
+            PathBuf::from(&path),
+            embed_images.unwrap_or(false),
+        )
+        .await?;
+    Ok(path)
+}
@@ -0,0 +1,17 @@
+use cuuri_core::documents::ExtractedDocument;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Extracts the text of a file dropped into the chat, given either its path or its bytes.
+#[tauri::command]
+pub async fn extract_document(
+    cuuri: State<'_, Arc<Cuuri>>,
+    file_path: Option<String>,
+    file_name: Option<String>,
+    base64_data: Option<String>,
+) -> Result<ExtractedDocument, String> {
+    cuuri
+        .extract_document(file_path, file_name, base64_data)
+        .await
+}
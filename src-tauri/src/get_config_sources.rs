@@ -0,0 +1,11 @@
+use cuuri_core::app_type::ConfigValueSource;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub async fn get_config_sources(
+    cuuri: State<'_, Arc<Cuuri>>,
+) -> Result<Vec<ConfigValueSource>, String> {
+    cuuri.config_sources()
+}
@@ -0,0 +1,11 @@
+use cuuri_core::app_type::DatabaseEncryptionStatus;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub async fn get_database_encryption(
+    cuuri: State<'_, Arc<Cuuri>>,
+) -> Result<DatabaseEncryptionStatus, String> {
+    cuuri.database_encryption()
+}
@@ -0,0 +1,11 @@
+use cuuri_core::app_type::DatabaseStatus;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Reports whether the chat database could be opened, so the app can ask
+/// for the passphrase or offer a way to recover.
+#[tauri::command]
+pub async fn get_database_status(cuuri: State<'_, Arc<Cuuri>>) -> Result<DatabaseStatus, String> {
+    Ok(cuuri.database_status())
+}
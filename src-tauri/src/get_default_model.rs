@@ -1,10 +1,8 @@
-use crate::get_config::get_config;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
 
 #[tauri::command]
-pub async fn get_default_model() -> Result<Option<String>, String> {
-    let config = get_config()
-        .await
-        .map_err(|e| format!("Failed to load configuration: {}", e))?;
-
-    Ok(config.default_model)
+pub async fn get_default_model(cuuri: State<'_, Arc<Cuuri>>) -> Result<Option<String>, String> {
+    Ok(cuuri.config().default_model)
 }
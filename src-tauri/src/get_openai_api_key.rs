@@ -1,10 +1,8 @@
-use crate::get_config::get_config;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
 
 #[tauri::command]
-pub async fn get_openai_api_key() -> Result<String, String> {
-    let config = get_config()
-        .await
-        .map_err(|e| format!("Failed to load configuration: {}", e))?;
-
-    Ok(config.openai_api_key)
+pub async fn get_openai_api_key(cuuri: State<'_, Arc<Cuuri>>) -> Result<String, String> {
+    Ok(cuuri.config().openai_api_key)
 }
@@ -0,0 +1,9 @@
+use cuuri_core::app_type::ProxyStatus;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn get_proxy_status(cuuri: State<'_, Arc<Cuuri>>) -> ProxyStatus {
+    cuuri.proxy_status()
+}
@@ -0,0 +1,9 @@
+use cuuri_core::settings::Settings;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub async fn get_settings(cuuri: State<'_, Arc<Cuuri>>) -> Result<Settings, String> {
+    cuuri.settings()
+}
@@ -0,0 +1,18 @@
+use cuuri_core::app_type::ImportReport;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Imports the `conversations.json` from a ChatGPT data export. Importing a
+/// newer export of the same account again only adds the new messages. With
+/// `dry_run`, nothing is written and the report shows what would be imported.
+#[tauri::command]
+pub async fn import_chatgpt_conversations(
+    cuuri: State<'_, Arc<Cuuri>>,
+    path: String,
+    dry_run: Option<bool>,
+) -> Result<ImportReport, String> {
+    cuuri
+        .import_chatgpt_conversations(path, dry_run.unwrap_or(false))
+        .await
+}
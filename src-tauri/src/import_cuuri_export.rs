@@ -0,0 +1,18 @@
+use cuuri_core::app_type::ImportReport;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Imports a JSON export from Cuuri, e.g. to move conversations to another
+/// machine. With `dry_run`, nothing is written and the report shows what
+/// would be imported.
+#[tauri::command]
+pub async fn import_cuuri_export(
+    cuuri: State<'_, Arc<Cuuri>>,
+    path: String,
+    dry_run: Option<bool>,
+) -> Result<ImportReport, String> {
+    cuuri
+        .import_cuuri_export(path, dry_run.unwrap_or(false))
+        .await
+}
@@ -0,0 +1,18 @@
+use cuuri_core::app_type::ImportReport;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Imports a JSONL file of OpenAI-format `messages` arrays, one conversation
+/// per line. With `dry_run`, nothing is written and the report shows what
+/// would be imported.
+#[tauri::command]
+pub async fn import_jsonl_conversations(
+    cuuri: State<'_, Arc<Cuuri>>,
+    path: String,
+    dry_run: Option<bool>,
+) -> Result<ImportReport, String> {
+    cuuri
+        .import_jsonl_conversations(path, dry_run.unwrap_or(false))
+        .await
+}
@@ -0,0 +1,19 @@
+use cuuri_core::app_type::IndexedFolderInfo;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::{Emitter, State, Window};
+
+/// Indexes the text files of a folder for retrieval. Indexing a folder again
+/// only embeds the files that changed since the last run.
+#[tauri::command]
+pub async fn index_folder(
+    window: Window,
+    cuuri: State<'_, Arc<Cuuri>>,
+    path: String,
+) -> Result<IndexedFolderInfo, String> {
+    cuuri
+        .index_folder(path, |progress| {
+            let _ = window.emit("index-progress", progress);
+        })
+        .await
+}
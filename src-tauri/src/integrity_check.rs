@@ -0,0 +1,10 @@
+use cuuri_core::app_type::IntegrityCheckReport;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Runs `PRAGMA integrity_check` on the chat database.
+#[tauri::command]
+pub async fn integrity_check(cuuri: State<'_, Arc<Cuuri>>) -> Result<IntegrityCheckReport, String> {
+    cuuri.integrity_check().await
+}
@@ -1,67 +1,138 @@
-mod app_type;
-mod config;
-mod establish_connection;
+mod app_tasks;
+mod approve_tool_call;
+mod backup_database;
+mod cancel_chat_response;
+mod change_database_key;
+mod create_profile;
+mod deny_tool_call;
+mod encrypt_database;
+mod export_all;
+mod export_session;
+mod extract_document;
 mod generate_session_id;
+mod get_active_profile;
 mod get_available_models;
 mod get_chat_history;
 mod get_chat_history_by_session;
 mod get_chatgpt_response;
-mod get_config;
-mod get_database_path;
+mod get_config_sources;
+mod get_database_encryption;
+mod get_database_status;
 mod get_default_model;
 mod get_openai_api_key;
+mod get_proxy_status;
 mod get_session_id_list;
-mod init_config_file;
-mod models;
-mod run_migrations;
-mod schema;
+mod get_settings;
+mod import_chatgpt_conversations;
+mod import_cuuri_export;
+mod import_jsonl_conversations;
+mod index_folder;
+mod integrity_check;
+mod list_indexed_folders;
+mod list_mcp_servers;
+mod list_profiles;
+mod regenerate_proxy_token;
+mod remove_indexed_folder;
+mod reopen_database;
+mod restore_database;
+mod semantic_search;
 mod set_openai_api_key;
 mod stream_chatgpt_response;
+mod switch_profile;
+mod unlock_database;
+mod update_settings;
+mod watch_config_file;
+mod window_events;
 
-use establish_connection::establish_connection;
+use app_tasks::start_background_tasks;
+use approve_tool_call::approve_tool_call;
+use backup_database::backup_database;
+use cancel_chat_response::cancel_chat_response;
+use change_database_key::change_database_key;
+use create_profile::create_profile;
+use cuuri_core::config::Config;
+use cuuri_core::db_pool::DbState;
+use cuuri_core::get_config::get_config;
+use cuuri_core::init_config_file::init_config_file;
+use cuuri_core::open_chat_database::{open_chat_database, recover_interrupted_chats};
+use cuuri_core::stderr_log::{log_to_stderr, LevelFilter};
+use cuuri_core::Cuuri;
+use deny_tool_call::deny_tool_call;
+use encrypt_database::encrypt_database;
+use export_all::export_all;
+use export_session::export_session;
+use extract_document::extract_document;
 use generate_session_id::generate_session_id;
+use get_active_profile::get_active_profile;
 use get_available_models::get_available_models;
 use get_chat_history::get_chat_history;
 use get_chat_history_by_session::get_chat_history_by_session;
 use get_chatgpt_response::get_chatgpt_response;
-use get_database_path::get_database_path;
+use get_config_sources::get_config_sources;
+use get_database_encryption::get_database_encryption;
+use get_database_status::get_database_status;
 use get_default_model::get_default_model;
 use get_openai_api_key::get_openai_api_key;
+use get_proxy_status::get_proxy_status;
 use get_session_id_list::get_session_id_list;
-use init_config_file::init_config_file;
-use run_migrations::run_migrations;
+use get_settings::get_settings;
+use import_chatgpt_conversations::import_chatgpt_conversations;
+use import_cuuri_export::import_cuuri_export;
+use import_jsonl_conversations::import_jsonl_conversations;
+use index_folder::index_folder;
+use integrity_check::integrity_check;
+use list_indexed_folders::list_indexed_folders;
+use list_mcp_servers::list_mcp_servers;
+use list_profiles::list_profiles;
+use regenerate_proxy_token::regenerate_proxy_token;
+use remove_indexed_folder::remove_indexed_folder;
+use reopen_database::reopen_database;
+use restore_database::restore_database;
+use semantic_search::semantic_search;
 use set_openai_api_key::set_openai_api_key;
-use std::env;
+use std::sync::Arc;
 use stream_chatgpt_response::stream_chatgpt_response;
+use switch_profile::switch_profile;
+use unlock_database::unlock_database;
+use update_settings::update_settings;
+use watch_config_file::{restart_config_watcher, ConfigWatcher};
 
 pub fn run() {
+    log_to_stderr(LevelFilter::Info);
+
     if let Err(e) = init_config_file() {
         eprintln!("Failed to initialize config file: {}", e);
-        return;
     }
 
-    let database_path = match get_database_path() {
-        Ok(path) => path,
+    // An invalid config file should not keep the app from starting; the
+    // watcher picks up the fixed file later
+    let config = match get_config() {
+        Ok(config) => config,
         Err(e) => {
-            eprintln!("Failed to get database path: {}", e);
-            return;
+            eprintln!("Failed to load config file: {}", e);
+            let mut config = Config::default();
+            config.apply_overrides();
+            config
         }
     };
 
-    let mut connection = match establish_connection(&database_path) {
-        Ok(conn) => conn,
-        Err(e) => {
-            eprintln!("Failed to establish connection: {}", e);
-            return;
-        }
-    };
+    // A database that cannot be opened is reported in the app, which offers
+    // ways to recover, instead of keeping the app from starting
+    let db = DbState::new(open_chat_database(&config, None));
 
-    if let Err(e) = run_migrations(&mut connection) {
-        eprintln!("Failed to run migrations: {}", e);
-        return;
+    // Nothing is streaming yet, so any pending row was interrupted last time
+    if let Ok(pool) = db.pool() {
+        recover_interrupted_chats(&pool);
     }
 
     tauri::Builder::default()
+        .manage(Arc::new(Cuuri::new(config, db)))
+        .manage(ConfigWatcher::default())
+        .setup(|app| {
+            restart_config_watcher(app.handle());
+            start_background_tasks(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_chatgpt_response,
             get_chat_history,
@@ -73,6 +144,38 @@ pub fn run() {
             get_chat_history_by_session,
             get_session_id_list,
             stream_chatgpt_response,
+            get_config_sources,
+            list_profiles,
+            get_active_profile,
+            create_profile,
+            switch_profile,
+            get_settings,
+            update_settings,
+            cancel_chat_response,
+            extract_document,
+            list_mcp_servers,
+            approve_tool_call,
+            deny_tool_call,
+            index_folder,
+            list_indexed_folders,
+            remove_indexed_folder,
+            semantic_search,
+            export_session,
+            export_all,
+            import_chatgpt_conversations,
+            import_cuuri_export,
+            import_jsonl_conversations,
+            backup_database,
+            restore_database,
+            integrity_check,
+            get_database_encryption,
+            encrypt_database,
+            change_database_key,
+            unlock_database,
+            get_database_status,
+            reopen_database,
+            get_proxy_status,
+            regenerate_proxy_token,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,11 @@
+use cuuri_core::app_type::IndexedFolderInfo;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub async fn list_indexed_folders(
+    cuuri: State<'_, Arc<Cuuri>>,
+) -> Result<Vec<IndexedFolderInfo>, String> {
+    cuuri.indexed_folders().await
+}
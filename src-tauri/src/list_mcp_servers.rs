@@ -0,0 +1,9 @@
+use cuuri_core::app_type::McpServerStatus;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn list_mcp_servers(cuuri: State<'_, Arc<Cuuri>>) -> Vec<McpServerStatus> {
+    cuuri.mcp_server_statuses()
+}
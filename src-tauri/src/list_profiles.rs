@@ -0,0 +1,9 @@
+use cuuri_core::app_type::ProfileInfo;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn list_profiles(cuuri: State<'_, Arc<Cuuri>>) -> Result<Vec<ProfileInfo>, String> {
+    cuuri.list_profiles()
+}
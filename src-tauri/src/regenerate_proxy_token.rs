@@ -0,0 +1,10 @@
+use cuuri_core::app_type::ProxyStatus;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Gives the proxy server a new token, restarting it if it is running.
+#[tauri::command]
+pub async fn regenerate_proxy_token(cuuri: State<'_, Arc<Cuuri>>) -> Result<ProxyStatus, String> {
+    cuuri.regenerate_proxy_token().await
+}
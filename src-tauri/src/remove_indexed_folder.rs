@@ -0,0 +1,12 @@
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Forgets an indexed folder and its chunks. The files themselves are not touched.
+#[tauri::command]
+pub async fn remove_indexed_folder(
+    cuuri: State<'_, Arc<Cuuri>>,
+    folder_id: i32,
+) -> Result<bool, String> {
+    cuuri.remove_indexed_folder(folder_id).await
+}
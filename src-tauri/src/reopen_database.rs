@@ -0,0 +1,17 @@
+use cuuri_core::app_type::DatabaseStatus;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+/// Tries again to open a chat database that failed to open, e.g. after
+/// freeing disk space.
+#[tauri::command]
+pub async fn reopen_database(
+    app: AppHandle,
+    cuuri: State<'_, Arc<Cuuri>>,
+) -> Result<DatabaseStatus, String> {
+    if cuuri.reopen_database().await? {
+        let _ = app.emit("database-opened", ());
+    }
+    Ok(cuuri.database_status())
+}
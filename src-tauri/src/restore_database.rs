@@ -0,0 +1,18 @@
+use cuuri_core::Cuuri;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+/// Replaces the chat database with the backup at `path`. The current database
+/// is backed up first, and the path of that copy is returned so the restore
+/// can be undone.
+#[tauri::command]
+pub async fn restore_database(
+    app: AppHandle,
+    cuuri: State<'_, Arc<Cuuri>>,
+    path: String,
+) -> Result<String, String> {
+    let snapshot = cuuri.restore_database(PathBuf::from(path)).await?;
+    let _ = app.emit("database-restored", ());
+    Ok(snapshot.to_string_lossy().to_string())
+}
@@ -0,0 +1,15 @@
+use cuuri_core::app_type::SemanticSearchResult;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::State;
+
+/// Finds past questions and answers by meaning rather than exact words.
+/// Questions answered very recently may not be embedded yet.
+#[tauri::command]
+pub async fn semantic_search(
+    cuuri: State<'_, Arc<Cuuri>>,
+    query: String,
+    k: Option<usize>,
+) -> Result<Vec<SemanticSearchResult>, String> {
+    cuuri.semantic_search(query, k).await
+}
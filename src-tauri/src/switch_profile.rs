@@ -0,0 +1,26 @@
+use crate::app_tasks::{reload_config, restart_proxy_server};
+use crate::watch_config_file::restart_config_watcher;
+use cuuri_core::app_type::ProfileInfo;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+#[tauri::command]
+pub async fn switch_profile(
+    app: AppHandle,
+    cuuri: State<'_, Arc<Cuuri>>,
+    name: String,
+) -> Result<ProfileInfo, String> {
+    let profile = cuuri.switch_profile(&name).await?;
+
+    // Pick up the profile's config file and follow it for further edits
+    reload_config(&app);
+    restart_config_watcher(&app);
+
+    // Each profile has its own proxy token
+    restart_proxy_server(&app);
+
+    let _ = app.emit("profile-changed", name);
+
+    Ok(profile)
+}
@@ -0,0 +1,17 @@
+use cuuri_core::app_type::DatabaseEncryptionStatus;
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+/// Opens an encrypted chat database that has no key in the OS keyring.
+#[tauri::command]
+pub async fn unlock_database(
+    app: AppHandle,
+    cuuri: State<'_, Arc<Cuuri>>,
+    passphrase: String,
+) -> Result<DatabaseEncryptionStatus, String> {
+    if cuuri.unlock_database(passphrase).await? {
+        let _ = app.emit("database-opened", ());
+    }
+    cuuri.database_encryption()
+}
@@ -0,0 +1,19 @@
+use crate::app_tasks::{restart_mcp_servers, restart_proxy_server};
+use cuuri_core::settings::{Settings, SettingsPatch};
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+#[tauri::command]
+pub async fn update_settings(
+    app: AppHandle,
+    cuuri: State<'_, Arc<Cuuri>>,
+    patch: SettingsPatch,
+) -> Result<Settings, String> {
+    let settings = cuuri.update_settings(&patch)?;
+    let _ = app.emit("settings-changed", settings.clone());
+    // The file watcher finds the config already up to date, so it does not restart them
+    restart_mcp_servers(&app);
+    restart_proxy_server(&app);
+    Ok(settings)
+}
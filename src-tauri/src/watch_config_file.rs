@@ -0,0 +1,68 @@
+use crate::app_tasks::reload_config;
+use cuuri_core::get_config_path::get_config_path;
+use notify_debouncer_mini::notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{new_debouncer, DebounceEventResult, Debouncer};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+
+/// Keeps the config file watcher alive for as long as the app runs.
+#[derive(Default)]
+pub struct ConfigWatcher {
+    debouncer: Mutex<Option<Debouncer<RecommendedWatcher>>>,
+}
+
+impl ConfigWatcher {
+    /// Starts watching the current config file, replacing any previous watcher.
+    pub fn restart(&self, app: &AppHandle) -> Result<(), String> {
+        let debouncer = watch_config_file(app.clone())?;
+        if let Ok(mut current) = self.debouncer.lock() {
+            *current = Some(debouncer);
+        }
+        Ok(())
+    }
+}
+
+pub fn watch_config_file(app: AppHandle) -> Result<Debouncer<RecommendedWatcher>, String> {
+    let config_path = get_config_path().map_err(|e| e.to_string())?;
+
+    // Watch the directory rather than the file itself, because saving through a
+    // temporary file (as editors and update_settings do) replaces the inode
+    let watch_dir = config_path
+        .parent()
+        .ok_or("Failed to get config directory".to_string())?
+        .to_path_buf();
+    let file_name = config_path
+        .file_name()
+        .ok_or("Failed to get config file name".to_string())?
+        .to_owned();
+
+    let mut debouncer = new_debouncer(
+        Duration::from_millis(300),
+        move |result: DebounceEventResult| match result {
+            Ok(events) => {
+                if events
+                    .iter()
+                    .any(|event| event.path.file_name() == Some(file_name.as_os_str()))
+                {
+                    reload_config(&app);
+                }
+            }
+            Err(e) => eprintln!("Config file watcher error: {}", e),
+        },
+    )
+    .map_err(|e| format!("Failed to create config file watcher: {}", e))?;
+
+    debouncer
+        .watcher()
+        .watch(&watch_dir, RecursiveMode::NonRecursive)
+        .map_err(|e| format!("Failed to watch config file: {}", e))?;
+
+    Ok(debouncer)
+}
+
+pub fn restart_config_watcher(app: &AppHandle) {
+    if let Err(e) = app.state::<ConfigWatcher>().restart(app) {
+        eprintln!("{}", e);
+    }
+}
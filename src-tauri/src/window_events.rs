@@ -0,0 +1,67 @@
+use cuuri_core::chat_events::{ApprovalFuture, ChatEvents};
+use cuuri_core::document_index::RetrievedChunk;
+use cuuri_core::tool_approvals::ToolApprovalRequest;
+use cuuri_core::tool_calls::{ToolCall, ToolCallDelta, ToolCallResult};
+use cuuri_core::Cuuri;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, Manager, Window};
+
+// Forgets an approval request once nobody waits for it anymore, e.g. after
+// the answer was cancelled
+struct PendingApproval {
+    app: AppHandle,
+    request_id: String,
+}
+
+impl Drop for PendingApproval {
+    fn drop(&mut self) {
+        self.app
+            .state::<Arc<Cuuri>>()
+            .forget_tool_approval(&self.request_id);
+    }
+}
+
+/// Forwards the progress of an answer to the window that asked, as events.
+pub struct WindowEvents(pub Window);
+
+impl ChatEvents for WindowEvents {
+    fn token(&self, token: &str) {
+        let _ = self.0.emit("token", token.to_string());
+    }
+
+    fn context_sources(&self, sources: &[RetrievedChunk]) {
+        let _ = self.0.emit("context-sources", sources.to_vec());
+    }
+
+    fn tool_call_delta(&self, delta: ToolCallDelta) {
+        let _ = self.0.emit("tool-call-delta", delta);
+    }
+
+    fn tool_call(&self, call: &ToolCall) {
+        let _ = self.0.emit("tool-call", call.clone());
+    }
+
+    fn tool_result(&self, result: &ToolCallResult) {
+        let _ = self.0.emit("tool-result", result.clone());
+    }
+
+    /// Emits "tool-approval-request" and waits for `approve_tool_call` or
+    /// `deny_tool_call`.
+    fn request_approval(&self, request: ToolApprovalRequest) -> ApprovalFuture {
+        let receiver = self
+            .0
+            .state::<Arc<Cuuri>>()
+            .register_tool_approval(&request.request_id, &request.name);
+        let pending = PendingApproval {
+            app: self.0.app_handle().clone(),
+            request_id: request.request_id.clone(),
+        };
+        let _ = self.0.emit("tool-approval-request", request);
+
+        Box::pin(async move {
+            let decision = receiver.await.ok();
+            drop(pending);
+            decision
+        })
+    }
+}
@@ -0,0 +1,11 @@
+import { invoke } from "@tauri-apps/api/core";
+import { DatabaseStatus } from "./types.ts";
+
+export async function getDatabaseStatus(): Promise<DatabaseStatus | null> {
+  try {
+    return await invoke<DatabaseStatus>("get_database_status");
+  } catch (error) {
+    console.error("Failed to get database status:", error);
+    return null;
+  }
+}
@@ -23,6 +23,30 @@ export interface RawDatabaseChatEntry {
   question: UserInput;
   answer: Markdown;
   created_at: string;
+  status: "pending" | "complete" | "failed" | "cancelled";
+  model: string | null;
+  attachments: RawAttachment[];
+  tool_calls: RawToolCall[];
+}
+
+export interface RawAttachment {
+  id: number;
+  kind: string;
+  mime_type: string;
+  file_name: string | null;
+  data: string;
+  created_at: string;
+}
+
+export interface RawToolCall {
+  id: number;
+  round: number;
+  call_id: string;
+  name: string;
+  arguments: string;
+  result: string;
+  is_error: boolean;
+  created_at: string;
 }
 
 export interface ChatResponse {
@@ -44,3 +68,8 @@ export interface HtmlChatEntry {
 export interface RawChats {
   [sessionId: SessionId]: Array<RawChatEntry>;
 }
+
+export interface DatabaseStatus {
+  state: "open" | "locked" | "failed";
+  error: string | null;
+}